@@ -0,0 +1,812 @@
+// Strict encoding schema library, implementing validation and parsing of strict
+// encoded data against the schema.
+//
+// Written in 2022 by
+//     Dr. Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2022 by LNP/BP Standards Association, Switzerland.
+//
+// You should have received a copy of the MIT License along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use crate::codegen::{identifier, words, Idents, Lines, Names};
+use crate::{
+    DataSize, KeyType, PrimitiveType, StructField, StructType, TypeConstr, TypeName, TypeRef,
+    TypeSystem,
+};
+
+/// C keywords, standard library names used in the generated code and names of
+/// the generated function arguments and variables.
+const KEYWORDS: &[&str] = &[
+    "auto", "break", "case", "char", "const", "continue", "default", "do", "double", "else",
+    "enum", "extern", "float", "for", "goto", "if", "inline", "int", "long", "register",
+    "restrict", "return", "short", "signed", "sizeof", "static", "struct", "switch", "typedef",
+    "union", "unsigned", "void", "volatile", "while", "_Bool", "_Complex", "_Imaginary", "bool",
+    "true", "false", "NULL", "memset", "c", "out", "data", "len", "start", "prev", "prev_len",
+    "item", "key", "value", "tmp", "i", "j",
+];
+
+/// Runtime used by the generated decoders.
+const RUNTIME: &str = r#"#ifndef STRICT_RUNTIME_H
+#define STRICT_RUNTIME_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <string.h>
+
+#define STRICT_OK 0
+#define STRICT_ERR_TRUNCATED -1 /* unexpected end of data */
+#define STRICT_ERR_TAG -2       /* optional tag is neither 0 nor 1 */
+#define STRICT_ERR_ASCII -3     /* non-ASCII character */
+#define STRICT_ERR_UTF8 -4      /* invalid UTF-8 string */
+#define STRICT_ERR_ORDER -5     /* set items or map keys are not unique or not sorted */
+#define STRICT_ERR_TRAILING -6  /* data are not consumed */
+
+#define STRICT_TRY(expr)                  \
+    do {                                  \
+        int strict_err = (expr);          \
+        if (strict_err != STRICT_OK) {    \
+            return strict_err;            \
+        }                                 \
+    } while (0)
+
+/* Character data or encoding of a data item inside the decoded buffer */
+typedef struct {
+    const uint8_t *data;
+    size_t len;
+} strict_view_t;
+
+/* Encoded items of a list, set, map or array inside the decoded buffer */
+typedef struct {
+    uint16_t count;
+    const uint8_t *data;
+    size_t len;
+} strict_list_t;
+
+typedef struct {
+    const uint8_t *data;
+    size_t len;
+    size_t pos;
+} strict_cursor_t;
+
+static inline int strict_skip(strict_cursor_t *c, size_t size) {
+    if (c->len - c->pos < size) {
+        return STRICT_ERR_TRUNCATED;
+    }
+    c->pos += size;
+    return STRICT_OK;
+}
+
+static inline int strict_read_raw(strict_cursor_t *c, uint8_t *out, size_t size) {
+    if (c->len - c->pos < size) {
+        return STRICT_ERR_TRUNCATED;
+    }
+    memcpy(out, c->data + c->pos, size);
+    c->pos += size;
+    return STRICT_OK;
+}
+
+static inline int strict_read_le(strict_cursor_t *c, size_t size, uint64_t *out) {
+    uint64_t value = 0;
+    size_t i;
+    if (c->len - c->pos < size) {
+        return STRICT_ERR_TRUNCATED;
+    }
+    for (i = size; i > 0; i--) {
+        value = (value << 8) | c->data[c->pos + i - 1];
+    }
+    c->pos += size;
+    *out = value;
+    return STRICT_OK;
+}
+
+#define STRICT_READ_INT(name, type, utype, size)                         \
+    static inline int strict_read_##name(strict_cursor_t *c, type *out) { \
+        uint64_t value;                                                   \
+        STRICT_TRY(strict_read_le(c, size, &value));                      \
+        *out = (type)(utype)value;                                        \
+        return STRICT_OK;                                                 \
+    }
+
+STRICT_READ_INT(u8, uint8_t, uint8_t, 1)
+STRICT_READ_INT(u16, uint16_t, uint16_t, 2)
+STRICT_READ_INT(u32, uint32_t, uint32_t, 4)
+STRICT_READ_INT(u64, uint64_t, uint64_t, 8)
+STRICT_READ_INT(i8, int8_t, uint8_t, 1)
+STRICT_READ_INT(i16, int16_t, uint16_t, 2)
+STRICT_READ_INT(i32, int32_t, uint32_t, 4)
+STRICT_READ_INT(i64, int64_t, uint64_t, 8)
+
+static inline int strict_read_f32(strict_cursor_t *c, float *out) {
+    uint32_t bits;
+    STRICT_TRY(strict_read_u32(c, &bits));
+    memcpy(out, &bits, sizeof(bits));
+    return STRICT_OK;
+}
+
+static inline int strict_read_f64(strict_cursor_t *c, double *out) {
+    uint64_t bits;
+    STRICT_TRY(strict_read_u64(c, &bits));
+    memcpy(out, &bits, sizeof(bits));
+    return STRICT_OK;
+}
+
+static inline int strict_read_len(strict_cursor_t *c, uint16_t *out) {
+    return strict_read_u16(c, out);
+}
+
+static inline int strict_read_tag(strict_cursor_t *c, bool *present) {
+    uint8_t tag;
+    STRICT_TRY(strict_read_u8(c, &tag));
+    if (tag > 1) {
+        return STRICT_ERR_TAG;
+    }
+    *present = tag == 1;
+    return STRICT_OK;
+}
+
+static inline int strict_read_chars(strict_cursor_t *c, strict_view_t *out) {
+    uint16_t len;
+    STRICT_TRY(strict_read_len(c, &len));
+    out->data = c->data + c->pos;
+    out->len = len;
+    return strict_skip(c, len);
+}
+
+static inline int strict_read_ascii(strict_cursor_t *c, strict_view_t *out) {
+    size_t i;
+    STRICT_TRY(strict_read_chars(c, out));
+    for (i = 0; i < out->len; i++) {
+        if (out->data[i] >= 0x80) {
+            return STRICT_ERR_ASCII;
+        }
+    }
+    return STRICT_OK;
+}
+
+static inline int strict_read_unicode(strict_cursor_t *c, strict_view_t *out) {
+    const uint8_t *s;
+    size_t i = 0, n, k;
+    uint32_t cp;
+    STRICT_TRY(strict_read_chars(c, out));
+    s = out->data;
+    while (i < out->len) {
+        if (s[i] < 0x80) {
+            i++;
+            continue;
+        } else if ((s[i] & 0xE0) == 0xC0) {
+            n = 1;
+            cp = s[i] & 0x1F;
+        } else if ((s[i] & 0xF0) == 0xE0) {
+            n = 2;
+            cp = s[i] & 0x0F;
+        } else if ((s[i] & 0xF8) == 0xF0) {
+            n = 3;
+            cp = s[i] & 0x07;
+        } else {
+            return STRICT_ERR_UTF8;
+        }
+        if (out->len - i <= n) {
+            return STRICT_ERR_UTF8;
+        }
+        for (k = 1; k <= n; k++) {
+            if ((s[i + k] & 0xC0) != 0x80) {
+                return STRICT_ERR_UTF8;
+            }
+            cp = (cp << 6) | (s[i + k] & 0x3F);
+        }
+        if ((n == 1 && cp < 0x80) || (n == 2 && cp < 0x800) || (n == 3 && cp < 0x10000) ||
+            cp > 0x10FFFF || (cp >= 0xD800 && cp <= 0xDFFF)) {
+            return STRICT_ERR_UTF8;
+        }
+        i += n + 1;
+    }
+    return STRICT_OK;
+}
+
+/* Checks that encoding of a set item or map key follows the previous one */
+static inline int strict_check_order(const uint8_t *prev, size_t prev_len, const uint8_t *item,
+                                     size_t item_len) {
+    int cmp;
+    if (prev == NULL) {
+        return STRICT_OK;
+    }
+    cmp = memcmp(prev, item, prev_len < item_len ? prev_len : item_len);
+    if (cmp < 0 || (cmp == 0 && prev_len < item_len)) {
+        return STRICT_OK;
+    }
+    return STRICT_ERR_ORDER;
+}
+
+#endif /* STRICT_RUNTIME_H */
+"#;
+
+/// Item of a collection.
+#[derive(Copy, Clone)]
+enum Item<'ts> {
+    Prim(PrimitiveType),
+    Named(&'ts TypeName),
+    /// Item which is a collection itself.
+    Ref(&'ts TypeRef),
+}
+
+/// Representation of a structure field in C.
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum Repr {
+    /// Decoded value of primitive or structure type, or an array of them.
+    Embed,
+    /// Character data or encoding of an optional structure.
+    View,
+    /// Encoded items of a collection.
+    List,
+}
+
+impl TypeSystem {
+    /// Generates self-contained C99 header with decoders for all types of the
+    /// type system; `name` is used for the header guard.
+    ///
+    /// Fixed-size types, i.e. types without lists, sets, maps, optional fields
+    /// and character data, are represented by packed structures, and for each
+    /// of them `<Type>_SIZE` constant is defined. Variable-size types are
+    /// represented by structures in which fixed-size fields are decoded, while
+    /// collections and character data are represented by pointer-and-length
+    /// views into the decoded buffer (`strict_list_t` and `strict_view_t`).
+    /// Optional fields are preceded by `bool f<no>_present` flags; optional
+    /// structures of variable size are represented by views of their
+    /// encoding. Aliases are declared as `typedef`s of the representation of
+    /// the aliased types.
+    ///
+    /// For each type `T` header defines bounds-checked decoder
+    /// `int decode_T(const uint8_t *data, size_t len, T *out)`, returning
+    /// `STRICT_OK` or one of `STRICT_ERR_*` codes. Decoders validate all data,
+    /// including the nested items of the collections and their ordering, so
+    /// the views may be decoded further without additional checks.
+    ///
+    /// Primitive values are decoded into the host byte order; integers wider
+    /// than 64 bits and floats other than `F32`/`F64` are kept as
+    /// little-endian byte arrays.
+    ///
+    /// Characters of the type names, which are not allowed in C identifiers,
+    /// are replaced with underscores; names clashing with the keywords, the
+    /// runtime or other types get numeric suffixes.
+    pub fn to_c_header(&self, name: &str) -> String {
+        let guard =
+            format!("{}_H", name.to_uppercase().replace(|c: char| !c.is_ascii_alphanumeric(), "_"));
+        let mut names = Names::reserved(
+            words(RUNTIME).chain(KEYWORDS.iter().copied()).chain([guard.as_str()]),
+        );
+        let ids = self
+            .into_iter()
+            .map(|(name, _)| {
+                let id = names.fresh_with(&identifier(name), |id| {
+                    vec![
+                        id.to_owned(),
+                        format!("read_{}", id),
+                        format!("decode_{}", id),
+                        format!("{}_SIZE", id),
+                        format!("{}_size_check", id),
+                    ]
+                });
+                (name.clone(), id)
+            })
+            .collect();
+        let mut c = C {
+            ts: self,
+            ids,
+            code: s!(""),
+        };
+        c.code.line(0, "/* Generated from strict encoding schema; do not edit. */");
+        c.code.line(0, "");
+        c.code.line(0, format!("#ifndef {}", guard));
+        c.code.line(0, format!("#define {}", guard));
+        c.code.line(0, "");
+        c.code.push_str(RUNTIME);
+
+        let mut defined = BTreeSet::new();
+        for (name, _) in self {
+            c.define(name, &mut defined);
+        }
+
+        c.code.line(0, "");
+        for id in c.ids.values() {
+            let decl = format!("static inline int read_{0}(strict_cursor_t *c, {0} *out);", id);
+            c.code.line(0, decl);
+        }
+        for (name, ty) in self {
+            c.read(name, ty);
+        }
+
+        c.code.line(0, "");
+        c.code.line(0, format!("#endif /* {} */", guard));
+        c.code
+    }
+}
+
+/// C code generator.
+struct C<'ts> {
+    ts: &'ts TypeSystem,
+    /// C identifiers of the schema types.
+    ids: Idents,
+    code: String,
+}
+
+/// Returns C type of a primitive and array dimension for the primitives
+/// represented by byte arrays.
+fn primitive(ty: PrimitiveType) -> (&'static str, Option<u16>) {
+    match ty {
+        PrimitiveType::U8 => ("uint8_t", None),
+        PrimitiveType::U16 => ("uint16_t", None),
+        PrimitiveType::U32 => ("uint32_t", None),
+        PrimitiveType::U64 => ("uint64_t", None),
+        PrimitiveType::I8 => ("int8_t", None),
+        PrimitiveType::I16 => ("int16_t", None),
+        PrimitiveType::I32 => ("int32_t", None),
+        PrimitiveType::I64 => ("int64_t", None),
+        PrimitiveType::F32 => ("float", None),
+        PrimitiveType::F64 => ("double", None),
+        PrimitiveType::AsciiChar | PrimitiveType::UnicodeChar => ("strict_view_t", None),
+        ty => ("uint8_t", ty.fixed_size()),
+    }
+}
+
+impl<'ts> C<'ts> {
+    fn repr(&self, field: &StructField) -> Repr {
+        let required = StructField {
+            ty: field.ty.clone(),
+            optional: false,
+        };
+        if self.ts.field_size(&required).fixed().is_some() {
+            return Repr::Embed;
+        }
+        match &field.ty {
+            TypeRef::InPlace(TypeConstr::Plain(_)) => Repr::View,
+            TypeRef::NameRef(TypeConstr::Plain(_)) if field.optional => Repr::View,
+            TypeRef::NameRef(TypeConstr::Plain(_)) => Repr::Embed,
+            _ => Repr::List,
+        }
+    }
+
+    /// Defines structure for the type, defining first the structures it
+    /// embeds.
+    fn define(&mut self, name: &'ts TypeName, defined: &mut BTreeSet<&'ts TypeName>) {
+        if !defined.insert(name) {
+            return;
+        }
+        let ty = match self.ts.get(name) {
+            Some(ty) => ty,
+            None => return,
+        };
+        for field in ty {
+            if let (Repr::Embed, TypeRef::NameRef(constr)) = (self.repr(field), &field.ty) {
+                self.define(constr.type_name(), defined);
+            }
+        }
+
+        let size = self.ts.size_of(name);
+        self.code.line(0, "");
+        if let Some(aliased) = self.ts.alias(name) {
+            let field = StructField {
+                ty: aliased.clone(),
+                optional: false,
+            };
+            self.code.line(0, format!("/* {} */", aliased));
+            let decl = self.declare(&field, &self.ids[name]);
+            self.code.line(0, format!("typedef {};", decl));
+        } else {
+            self.code.line(0, format!("/* {} */", ty));
+            if size.fixed().is_some() {
+                self.code.line(0, "#pragma pack(push, 1)");
+            }
+            self.code.line(0, "typedef struct {");
+            for (no, field) in ty.into_iter().enumerate() {
+                if field.optional {
+                    self.code.line(4, format!("bool f{}_present;", no));
+                }
+                let decl = self.declare(field, &format!("f{}", no));
+                self.code.line(4, format!("{};", decl));
+            }
+            self.code.line(0, format!("}} {};", self.ids[name]));
+            if size.fixed().is_some() {
+                self.code.line(0, "#pragma pack(pop)");
+            }
+        }
+        if let DataSize::Fixed(size) = size {
+            let id = &self.ids[name];
+            self.code.line(0, format!("#define {}_SIZE {}", id, size));
+            self.code.line(
+                0,
+                format!("typedef char {0}_size_check[sizeof({0}) == {0}_SIZE ? 1 : -1];", id),
+            );
+        }
+    }
+
+    /// Declares `var` holding the data of the field.
+    fn declare(&self, field: &StructField, var: &str) -> String {
+        match (self.repr(field), &field.ty) {
+            (Repr::View, _) => format!("strict_view_t {}", var),
+            (Repr::List, _) => format!("strict_list_t {}", var),
+            (Repr::Embed, TypeRef::InPlace(constr)) => {
+                let (c_ty, dim) = primitive(*constr.inner());
+                let mut decl = format!("{} {}", c_ty, var);
+                if let TypeConstr::Array(len, _) = constr {
+                    write!(decl, "[{}]", len).ok();
+                }
+                if let Some(dim) = dim {
+                    write!(decl, "[{}]", dim).ok();
+                }
+                decl
+            }
+            (Repr::Embed, TypeRef::NameRef(constr)) => match constr {
+                TypeConstr::Array(len, name) => format!("{} {}[{}]", self.ids[name], var, len),
+                constr => format!("{} {}", self.ids[constr.type_name()], var),
+            },
+            (Repr::Embed, TypeRef::Nested(_)) => unreachable!("nested constructors are lists"),
+        }
+    }
+
+    fn read(&mut self, name: &TypeName, ty: &'ts StructType) {
+        let id = self.ids[name].clone();
+        self.code.line(0, "");
+        let head = format!("static inline int read_{0}(strict_cursor_t *c, {0} *out) {{", id);
+        self.code.line(0, head);
+        self.code.line(4, "memset(out, 0, sizeof(*out));");
+        for (no, field) in ty.into_iter().enumerate() {
+            if self.ts.is_alias(name) {
+                // the only field of the alias holds the aliased data itself
+                self.field(4, field, "(*out)");
+                continue;
+            }
+            let lv = format!("out->f{}", no);
+            let mut indent = 4;
+            if field.optional {
+                self.code.line(4, format!("STRICT_TRY(strict_read_tag(c, &{}_present));", lv));
+                self.code.line(4, format!("if ({}_present) {{", lv));
+                indent = 8;
+            }
+            self.field(indent, field, &lv);
+            if field.optional {
+                self.code.line(4, "}");
+            }
+        }
+        self.code.line(4, "return STRICT_OK;");
+        self.code.line(0, "}");
+
+        self.code.line(0, "");
+        self.code.line(
+            0,
+            format!(
+                "/* Decodes `{}` from `len` bytes of `data`, which must be consumed completely */",
+                id
+            ),
+        );
+        self.code.line(
+            0,
+            format!(
+                "static inline int decode_{0}(const uint8_t *data, size_t len, {0} *out) {{",
+                id
+            ),
+        );
+        self.code.line(4, "strict_cursor_t c = {data, len, 0};");
+        self.code.line(4, format!("STRICT_TRY(read_{}(&c, out));", id));
+        self.code.line(4, "return c.pos == len ? STRICT_OK : STRICT_ERR_TRAILING;");
+        self.code.line(0, "}");
+    }
+
+    /// Decodes data of a non-optional field into `lv`.
+    fn field(&mut self, indent: usize, field: &'ts StructField, lv: &str) {
+        match (self.repr(field), &field.ty) {
+            (Repr::Embed, TypeRef::InPlace(constr)) => {
+                self.embed(indent, constr, lv, &|ty| Item::Prim(*ty))
+            }
+            (Repr::Embed, TypeRef::NameRef(constr)) => {
+                self.embed(indent, constr, lv, &|name| Item::Named(name))
+            }
+            (Repr::View, TypeRef::InPlace(constr)) => {
+                self.store(indent, Item::Prim(*constr.inner()), lv)
+            }
+            (Repr::View, TypeRef::NameRef(constr)) => {
+                // structures are represented by views only when optional,
+                // thus we are already inside the tag check block
+                self.code.line(indent, "size_t start = c->pos;");
+                self.skip(indent, Item::Named(constr.type_name()), "tmp");
+                self.code.line(indent, format!("{}.data = c->data + start;", lv));
+                self.code.line(indent, format!("{}.len = c->pos - start;", lv));
+            }
+            (Repr::List, TypeRef::InPlace(constr)) => {
+                self.list(indent, constr, lv, &|ty| Item::Prim(*ty))
+            }
+            (Repr::List, TypeRef::NameRef(constr)) => {
+                self.list(indent, constr, lv, &|name| Item::Named(name))
+            }
+            (Repr::List, TypeRef::Nested(constr)) => {
+                self.list(indent, constr, lv, &|ty| Item::Ref(ty))
+            }
+            (_, TypeRef::Nested(_)) => unreachable!("nested constructors are lists"),
+        }
+    }
+
+    /// Decodes data item into `lv`.
+    fn store(&mut self, indent: usize, item: Item, lv: &str) {
+        let line = match item {
+            Item::Named(name) => format!("STRICT_TRY(read_{}(c, &{}));", self.ids[name], lv),
+            Item::Ref(_) => unreachable!("collections are validated with `list`"),
+            Item::Prim(ty) => match (ty, primitive(ty)) {
+                (_, (_, Some(size))) => {
+                    format!("STRICT_TRY(strict_read_raw(c, {}, {}));", lv, size)
+                }
+                (PrimitiveType::AsciiChar, _) => {
+                    format!("STRICT_TRY(strict_read_ascii(c, &{}));", lv)
+                }
+                (PrimitiveType::UnicodeChar, _) => {
+                    format!("STRICT_TRY(strict_read_unicode(c, &{}));", lv)
+                }
+                (ty, _) => {
+                    format!(
+                        "STRICT_TRY(strict_read_{}(c, &{}));",
+                        format!("{:?}", ty).to_lowercase(),
+                        lv
+                    )
+                }
+            },
+        };
+        self.code.line(indent, line);
+    }
+
+    fn embed<T>(
+        &mut self,
+        indent: usize,
+        ty: &'ts TypeConstr<T>,
+        lv: &str,
+        item: &dyn Fn(&'ts T) -> Item<'ts>,
+    ) where
+        T: Clone + Ord + Eq + std::hash::Hash + std::fmt::Debug,
+    {
+        match ty {
+            TypeConstr::Array(len, ty) if matches!(item(ty), Item::Prim(PrimitiveType::U8)) => {
+                self.code.line(indent, format!("STRICT_TRY(strict_read_raw(c, {}, {}));", lv, len))
+            }
+            TypeConstr::Array(len, ty) => {
+                self.code.line(indent, format!("for (size_t i = 0; i < {}; i++) {{", len));
+                self.store(indent + 4, item(ty), &format!("{}[i]", lv));
+                self.code.line(indent, "}");
+            }
+            ty => self.store(indent, item(ty.inner()), lv),
+        }
+    }
+
+    /// Validates and skips data item, decoding it into `var` when necessary.
+    fn skip(&mut self, indent: usize, item: Item<'ts>, var: &str) {
+        match item {
+            Item::Named(name) => {
+                let id = self.ids[name].clone();
+                self.code.line(indent, format!("{} {};", id, var));
+                self.code.line(indent, format!("STRICT_TRY(read_{}(c, &{}));", id, var));
+            }
+            Item::Prim(ty) if ty.is_char() => {
+                self.code.line(indent, format!("strict_view_t {};", var));
+                self.store(indent, item, var);
+            }
+            Item::Prim(ty) => self.code.line(
+                indent,
+                format!("STRICT_TRY(strict_skip(c, {}));", ty.fixed_size().unwrap_or_default()),
+            ),
+            // inner collections are validated in their own block, shadowing
+            // the variables of the outer ones
+            Item::Ref(ty) => {
+                self.code.line(indent, format!("strict_list_t {};", var));
+                match ty {
+                    TypeRef::InPlace(constr) => {
+                        self.list(indent, constr, var, &|ty| Item::Prim(*ty))
+                    }
+                    TypeRef::NameRef(constr) => {
+                        self.list(indent, constr, var, &|name| Item::Named(name))
+                    }
+                    TypeRef::Nested(constr) => self.list(indent, constr, var, &|ty| Item::Ref(ty)),
+                }
+            }
+        }
+    }
+
+    /// Validates and skips primitive data of a map key.
+    fn skip_key(&mut self, indent: usize, key: KeyType) {
+        match TypeConstr::from(key) {
+            TypeConstr::Plain(ty) => self.skip(indent, Item::Prim(ty), "key"),
+            TypeConstr::Array(len, ty) => {
+                self.code.line(indent, format!("for (size_t j = 0; j < {}; j++) {{", len));
+                self.skip(indent + 4, Item::Prim(ty), "key");
+                self.code.line(indent, "}");
+            }
+            TypeConstr::List(ty) => {
+                self.code.line(indent, "{");
+                self.code.line(indent + 4, "uint16_t len;");
+                self.code.line(indent + 4, "STRICT_TRY(strict_read_len(c, &len));");
+                self.code.line(indent + 4, "for (uint16_t j = 0; j < len; j++) {");
+                self.skip(indent + 8, Item::Prim(ty), "key");
+                self.code.line(indent + 4, "}");
+                self.code.line(indent, "}");
+            }
+            TypeConstr::Set(_) | TypeConstr::Map(..) => unreachable!(),
+        }
+    }
+
+    /// Validates items of a collection, storing view of their encoding into
+    /// `lv`.
+    fn list<T>(
+        &mut self,
+        indent: usize,
+        ty: &'ts TypeConstr<T>,
+        lv: &str,
+        item: &dyn Fn(&'ts T) -> Item<'ts>,
+    ) where
+        T: Clone + Ord + Eq + std::hash::Hash + std::fmt::Debug,
+    {
+        let ordered = matches!(ty, TypeConstr::Set(_) | TypeConstr::Map(..));
+        self.code.line(indent, "{");
+        let inner = indent + 4;
+        self.code.line(inner, "size_t start;");
+        if ordered {
+            self.code.line(inner, "const uint8_t *prev = NULL;");
+            self.code.line(inner, "size_t prev_len = 0;");
+        }
+        match ty {
+            TypeConstr::Array(len, _) => self.code.line(inner, format!("{}.count = {};", lv, len)),
+            _ => self.code.line(inner, format!("STRICT_TRY(strict_read_len(c, &{}.count));", lv)),
+        }
+        self.code.line(inner, "start = c->pos;");
+        self.code.line(inner, format!("for (uint16_t i = 0; i < {}.count; i++) {{", lv));
+        let body = inner + 4;
+        if ordered {
+            self.code.line(body, "size_t item = c->pos;");
+        }
+        match ty {
+            TypeConstr::Map(key, _) => self.skip_key(body, *key),
+            ty => self.skip(body, item(ty.inner()), "tmp"),
+        }
+        if ordered {
+            self.code.line(
+                body,
+                "STRICT_TRY(strict_check_order(prev, prev_len, c->data + item, c->pos - item));",
+            );
+            self.code.line(body, "prev = c->data + item;");
+            self.code.line(body, "prev_len = c->pos - item;");
+        }
+        if let TypeConstr::Map(_, ty) = ty {
+            self.skip(body, item(ty), "value");
+        }
+        self.code.line(inner, "}");
+        self.code.line(inner, format!("{}.data = c->data + start;", lv));
+        self.code.line(inner, format!("{}.len = c->pos - start;", lv));
+        self.code.line(indent, "}");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::schema::test::{golden, tn, type_system};
+    use crate::{strict_vec, type_system};
+
+    #[test]
+    fn header() { golden("types.h", &type_system().to_c_header("types")); }
+
+    #[test]
+    fn layout() {
+        let ts = type_system![
+            "Point" :: {
+                StructField::primitive(PrimitiveType::I64),
+                StructField::array(PrimitiveType::U128, 2),
+                StructField::primitive(PrimitiveType::F64),
+            },
+            "Shape" :: {
+                StructField { ty: TypeRef::NameRef(TypeConstr::Plain("Point".try_into().unwrap())), optional: true },
+                StructField { ty: TypeRef::NameRef(TypeConstr::Set("Point".try_into().unwrap())), optional: false },
+                StructField::ascii_string(),
+                StructField { ty: TypeRef::InPlace(TypeConstr::Plain(PrimitiveType::UnicodeChar)), optional: false },
+            }
+        ];
+        let h = ts.to_c_header("shapes.h");
+        assert!(h.starts_with(
+            "/* Generated from strict encoding schema; do not edit. */\n\n#ifndef SHAPES_H_H\n"
+        ));
+        assert!(h.contains(
+            "#pragma pack(push, 1)
+typedef struct {
+    int64_t f0;
+    uint8_t f1[2][16];
+    double f2;
+} Point;
+#pragma pack(pop)
+#define Point_SIZE 48
+"
+        ));
+        assert!(h.contains(
+            "typedef struct {
+    bool f0_present;
+    Point f0;
+    strict_list_t f1;
+    strict_list_t f2;
+    strict_view_t f3;
+} Shape;
+"
+        ));
+    }
+
+    #[test]
+    fn aliases() {
+        let mut ts = type_system![
+            "Input" :: {
+                StructField::with("Txid"),
+                StructField::with("Txids"),
+                StructField { ty: TypeRef::new("Name"), optional: true },
+            }
+        ];
+        ts.push_alias(tn("Txid"), TypeRef::InPlace(TypeConstr::Array(32, PrimitiveType::U8)))
+            .unwrap();
+        ts.push_alias(tn("Txids"), TypeRef::NameRef(TypeConstr::List(tn("Txid")))).unwrap();
+        ts.push_alias(tn("Name"), TypeRef::InPlace(TypeConstr::Plain(PrimitiveType::AsciiChar)))
+            .unwrap();
+        let h = ts.to_c_header("aliases");
+        assert!(h.contains(
+            "/* U8*32 */
+typedef uint8_t Txid[32];
+#define Txid_SIZE 32
+typedef char Txid_size_check[sizeof(Txid) == Txid_SIZE ? 1 : -1];
+
+/* Txid* */
+typedef strict_list_t Txids;
+
+/* Txid, Txids, Name? */
+typedef struct {
+    Txid f0;
+    Txids f1;
+    bool f2_present;
+    strict_view_t f2;
+} Input;
+
+/* AsciiChar */
+typedef strict_view_t Name;
+"
+        ));
+        assert!(h.contains(
+            "static inline int read_Txid(strict_cursor_t *c, Txid *out) {
+    memset(out, 0, sizeof(*out));
+    STRICT_TRY(strict_read_raw(c, (*out), 32));
+    return STRICT_OK;
+}
+"
+        ));
+        assert!(h.contains("    STRICT_TRY(read_Txids(c, &out->f1));\n"));
+    }
+
+    #[test]
+    fn identifiers() {
+        let ts = type_system![
+            "strict_view_t" :: { StructField::with("Tx-Out"), StructField::typed_list("tmp") },
+            "Tx-Out" :: { StructField::primitive(PrimitiveType::U8) },
+            "tmp" :: { StructField::primitive(PrimitiveType::U8) }
+        ];
+        let h = ts.to_c_header("types");
+        assert!(h.contains(
+            "typedef struct {
+    Tx_Out f0;
+    strict_list_t f1;
+} strict_view_t_2;
+"
+        ));
+        assert!(h.contains("#define Tx_Out_SIZE 1\n"));
+        assert!(h.contains("static inline int read_tmp_2(strict_cursor_t *c, tmp_2 *out);\n"));
+        assert!(h.contains(
+            "            tmp_2 tmp;
+            STRICT_TRY(read_tmp_2(c, &tmp));
+"
+        ));
+        assert!(h.contains(
+            "static inline int decode_strict_view_t_2(const uint8_t *data, size_t len, \
+             strict_view_t_2 *out) {"
+        ));
+    }
+}
@@ -0,0 +1,130 @@
+// Strict encoding schema library, implementing validation and parsing of strict
+// encoded data against the schema.
+//
+// Written in 2022 by
+//     Dr. Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2022 by LNP/BP Standards Association, Switzerland.
+//
+// You should have received a copy of the MIT License along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Helpers shared by the code generators.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use crate::TypeName;
+
+/// Identifiers of the schema types in the generated code.
+pub(crate) type Idents = BTreeMap<TypeName, String>;
+
+/// Converts type name into snake case.
+pub(crate) fn snake_case(name: &str) -> String {
+    let mut s = String::with_capacity(name.len() + 4);
+    let mut prev_lower = false;
+    for c in name.chars() {
+        if c.is_ascii_uppercase() && prev_lower {
+            s.push('_');
+        }
+        prev_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+        s.push(c.to_ascii_lowercase());
+    }
+    s
+}
+
+/// Converts type name into identifier of a C-like language, replacing
+/// characters other than ASCII letters, digits and underscores with
+/// underscores and prefixing names starting with a digit with an underscore.
+pub(crate) fn identifier(name: &str) -> String {
+    let mut s = String::with_capacity(name.len() + 1);
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        s.push('_');
+    }
+    s.extend(name.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }));
+    s
+}
+
+/// Returns all identifiers appearing in the code.
+pub(crate) fn words(code: &str) -> impl Iterator<Item = &str> {
+    code.split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .filter(|word| !word.is_empty() && !word.starts_with(|c: char| c.is_ascii_digit()))
+}
+
+/// Allocator of unique identifiers.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Names {
+    taken: BTreeSet<String>,
+}
+
+impl Names {
+    /// Constructs allocator for which the `reserved` identifiers are already
+    /// taken.
+    pub fn reserved<'a>(reserved: impl IntoIterator<Item = &'a str>) -> Self {
+        Names {
+            taken: reserved.into_iter().map(str::to_owned).collect(),
+        }
+    }
+
+    /// Returns identifier based on `base` which is not used yet, appending
+    /// numeric suffix if necessary.
+    pub fn fresh(&mut self, base: &str) -> String {
+        self.fresh_with(base, |id| vec![id.to_owned()])
+    }
+
+    /// Returns identifier based on `base` for which none of the identifiers
+    /// produced by `derive` is used yet, appending numeric suffix if
+    /// necessary; all the produced identifiers become used.
+    pub fn fresh_with(&mut self, base: &str, derive: impl Fn(&str) -> Vec<String>) -> String {
+        let mut id = base.to_owned();
+        let mut no = 1;
+        while derive(&id).iter().any(|derived| self.taken.contains(derived)) {
+            no += 1;
+            id = format!("{}_{}", base, no);
+        }
+        self.taken.extend(derive(&id));
+        id
+    }
+}
+
+/// Writer of the generated code.
+pub(crate) trait Lines {
+    /// Writes line of code with the given indentation; empty lines get no
+    /// trailing whitespace.
+    fn line(&mut self, indent: usize, line: impl AsRef<str>);
+}
+
+impl Lines for String {
+    fn line(&mut self, indent: usize, line: impl AsRef<str>) {
+        if line.as_ref().is_empty() {
+            self.push('\n');
+        } else {
+            writeln!(self, "{:indent$}{}", "", line.as_ref(), indent = indent).ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn names() {
+        assert_eq!(snake_case("OutPoint"), "out_point");
+        assert_eq!(snake_case("Txid"), "txid");
+        assert_eq!(snake_case("U8Array2"), "u8_array2");
+        assert_eq!(identifier("Tx-Out.v2"), "Tx_Out_v2");
+        assert_eq!(identifier("2Fa"), "_2Fa");
+    }
+
+    #[test]
+    fn fresh() {
+        let mut names = Names::reserved(words("class StrictReader { u8() {} }"));
+        assert_eq!(names.fresh("StrictReader"), "StrictReader_2");
+        assert_eq!(names.fresh("Txid"), "Txid");
+        assert_eq!(names.fresh("Txid"), "Txid_2");
+        let derive = |id: &str| vec![id.to_owned(), format!("read_{}", id)];
+        assert_eq!(names.fresh("read_out"), "read_out");
+        assert_eq!(names.fresh_with("out", derive), "out_2");
+    }
+}
@@ -239,6 +239,7 @@ where T: Eq + Ord + Debug + StrictEncode + StrictDecode
         Ok(len as u16)
     }
 
+    #[allow(clippy::multiple_bound_locations)]
     pub fn remove<Q: ?Sized>(&mut self, item: &Q) -> Result<bool, UndersizeError>
     where
         T: Borrow<Q> + Ord,
@@ -344,6 +345,7 @@ where
         Ok(len as u16)
     }
 
+    #[allow(clippy::multiple_bound_locations)]
     pub fn remove<Q: ?Sized>(&mut self, key: &Q) -> Result<Option<V>, UndersizeError>
     where
         K: Borrow<Q> + Ord,
@@ -0,0 +1,352 @@
+// Strict encoding schema library, implementing validation and parsing of strict
+// encoded data against the schema.
+//
+// Written in 2022 by
+//     Dr. Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2022 by LNP/BP Standards Association, Switzerland.
+//
+// You should have received a copy of the MIT License along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Conformance test vectors, allowing to check that strict encoding
+//! implementations agree on the validity and interpretation of the data.
+//!
+//! Test vectors are exported to a JSON file of the following structure:
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "schema": "<hex of strict-encoded type system>",
+//!   "types": "<type system in human-readable form>",
+//!   "vectors": [
+//!     { "name": "...", "root": "<type name>", "data": "<hex>", "valid": true,
+//!       "value": <expected value> },
+//!     { "name": "...", "root": "<type name>", "data": "<hex>", "valid": false,
+//!       "error_path": "<data path>" }
+//!   ]
+//! }
+//! ```
+//!
+//! Expected values use JSON projection of the data described in
+//! [`crate::json`] module. Error path is the path to the data item at which
+//! decoding fails.
+
+use std::io::Cursor;
+
+use amplify::hex::{FromHex, ToHex};
+#[cfg(feature = "generate")]
+use rand::rngs::StdRng;
+#[cfg(feature = "generate")]
+use rand::SeedableRng;
+use serde_json::{json, Value as Json};
+use strict_encoding::{StrictDecode, StrictEncode};
+
+use crate::{DataPath, SchemaReader, TypeName, TypeSystem, Verify};
+#[cfg(feature = "generate")]
+use crate::{GenConfig, GenError, Generator};
+
+/// Version of the test vector file format.
+pub const FORMAT_VERSION: u64 = 1;
+
+/// Expected result of processing test vector data.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum Expect {
+    /// Data are valid and decode to the value in JSON representation.
+    Valid(Json),
+
+    /// Data are invalid and decoding fails at the given path.
+    Invalid(DataPath),
+}
+
+/// Test vector: data encoding an instance of a type together with the
+/// expected result of their processing.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct TestVector {
+    pub name: String,
+    pub root: TypeName,
+    pub data: Vec<u8>,
+    pub expect: Expect,
+}
+
+/// Type system with a set of test vectors for its types.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Corpus {
+    pub ts: TypeSystem,
+    pub vectors: Vec<TestVector>,
+}
+
+/// Errors parsing test vector file.
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum CorpusError {
+    /// invalid JSON. Details: {0}
+    Json(String),
+
+    /// unsupported test vector format version {0}
+    Version(u64),
+
+    /// missing or invalid field `{0}`
+    Field(&'static str),
+
+    /// invalid type name `{0}`
+    TypeName(String),
+
+    /// invalid hex data in field `{0}`
+    Hex(&'static str),
+
+    /// invalid data path `{0}`
+    Path(String),
+
+    /// invalid strict encoding of the type system. Details: {0}
+    Schema(String),
+}
+
+/// Disagreement between the test vector and the result of its processing by
+/// one of the checks.
+#[derive(Clone, Eq, PartialEq, Debug, Display)]
+#[display("test vector `{vector}` fails {check} check: {details}")]
+pub struct Mismatch {
+    pub vector: String,
+    pub check: &'static str,
+    pub details: String,
+}
+
+impl Corpus {
+    /// Constructs empty corpus for the type system.
+    pub fn new(ts: TypeSystem) -> Corpus {
+        Corpus {
+            ts,
+            vectors: vec![],
+        }
+    }
+
+    /// Generates corpus containing `count` random valid encodings of each of
+    /// the `roots` types, followed by all invalid variants of each of them
+    /// produced by [`TypeSystem::mutate`].
+    #[cfg(feature = "generate")]
+    pub fn generate(
+        ts: TypeSystem,
+        roots: &[TypeName],
+        config: GenConfig,
+        seed: u64,
+        count: usize,
+    ) -> Result<Corpus, GenError> {
+        let mut corpus = Corpus::new(ts);
+        let mut rng = StdRng::seed_from_u64(seed);
+        for root in roots {
+            let mut generator = Generator::with(&corpus.ts, config, &mut rng);
+            let samples =
+                (0..count).map(|_| generator.generate(root)).collect::<Result<Vec<_>, _>>()?;
+            for (no, data) in samples.into_iter().enumerate() {
+                let name = format!("{} #{}", root, no);
+                let mutations =
+                    corpus.ts.mutate(root, &data).expect("generated data are always valid");
+                corpus.push(&name, root.clone(), data);
+                for mutation in mutations {
+                    corpus.push(&format!("{}: {}", name, mutation), root.clone(), mutation.data);
+                }
+            }
+        }
+        Ok(corpus)
+    }
+
+    /// Adds test vector for the data, computing the expected result with the
+    /// decoder of this crate.
+    pub fn push(&mut self, name: &str, root: TypeName, data: Vec<u8>) {
+        let expect = match self.ts.decode(&root, &data) {
+            Ok(value) => Expect::Valid(value.to_json()),
+            Err(err) => Expect::Invalid(err.path),
+        };
+        self.vectors.push(TestVector {
+            name: name.to_owned(),
+            root,
+            data,
+            expect,
+        });
+    }
+
+    /// Serializes corpus into a pretty-printed JSON.
+    pub fn to_json(&self) -> String {
+        let vectors = self
+            .vectors
+            .iter()
+            .map(|vector| {
+                let mut obj = json!({
+                    "name": vector.name,
+                    "root": vector.root.to_string(),
+                    "data": vector.data.to_hex(),
+                });
+                match &vector.expect {
+                    Expect::Valid(value) => {
+                        obj["valid"] = json!(true);
+                        obj["value"] = value.clone();
+                    }
+                    Expect::Invalid(path) => {
+                        obj["valid"] = json!(false);
+                        obj["error_path"] = json!(path.to_string());
+                    }
+                }
+                obj
+            })
+            .collect::<Vec<_>>();
+        let schema = self.ts.strict_serialize().expect("in-memory encoding");
+        let file = json!({
+            "version": FORMAT_VERSION,
+            "schema": schema.to_hex(),
+            "types": self.ts.to_string(),
+            "vectors": vectors,
+        });
+        serde_json::to_string_pretty(&file).expect("JSON serialization never fails")
+    }
+
+    /// Parses corpus from JSON.
+    pub fn from_json(s: &str) -> Result<Corpus, CorpusError> {
+        let file: Json =
+            serde_json::from_str(s).map_err(|err| CorpusError::Json(err.to_string()))?;
+        let version = file["version"].as_u64().ok_or(CorpusError::Field("version"))?;
+        if version != FORMAT_VERSION {
+            return Err(CorpusError::Version(version));
+        }
+        let schema = hex_field(&file, "schema")?;
+        let ts = TypeSystem::strict_deserialize(schema)
+            .map_err(|err| CorpusError::Schema(err.to_string()))?;
+
+        let vectors = file["vectors"].as_array().ok_or(CorpusError::Field("vectors"))?;
+        let vectors = vectors
+            .iter()
+            .map(|vector| {
+                let name = str_field(vector, "name")?;
+                let root = str_field(vector, "root")?;
+                let root = TypeName::try_from(root.to_owned())
+                    .map_err(|_| CorpusError::TypeName(root.to_owned()))?;
+                let data = hex_field(vector, "data")?;
+                let expect = match vector["valid"].as_bool() {
+                    Some(true) => Expect::Valid(vector["value"].clone()),
+                    Some(false) => {
+                        let path = str_field(vector, "error_path")?;
+                        Expect::Invalid(
+                            path.parse().map_err(|_| CorpusError::Path(path.to_owned()))?,
+                        )
+                    }
+                    None => return Err(CorpusError::Field("valid")),
+                };
+                Ok(TestVector {
+                    name: name.to_owned(),
+                    root,
+                    data,
+                    expect,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Corpus { ts, vectors })
+    }
+
+    /// Checks that [`Verify`], [`TypeSystem::decode`] and [`SchemaReader`]
+    /// agree with the test vectors, returning the list of disagreements.
+    pub fn run(&self) -> Vec<Mismatch> {
+        let mut mismatches = vec![];
+        for vector in &self.vectors {
+            let mut mismatch = |check: &'static str, details: String| {
+                mismatches.push(Mismatch {
+                    vector: vector.name.clone(),
+                    check,
+                    details,
+                })
+            };
+            let expect_valid = matches!(vector.expect, Expect::Valid(_));
+
+            let mut buf = Cursor::new(&vector.data);
+            let verified = vector.root.verify(&self.ts, &mut buf)
+                && buf.position() == vector.data.len() as u64;
+            if verified != expect_valid {
+                mismatch("verify", verdict(verified));
+            }
+
+            match (self.ts.decode(&vector.root, &vector.data), &vector.expect) {
+                (Ok(value), Expect::Valid(expected)) => {
+                    let value = value.to_json();
+                    if value != *expected {
+                        mismatch("decode", format!("value {} is decoded", value));
+                    }
+                }
+                (Err(err), Expect::Invalid(path)) if err.path != *path => {
+                    mismatch("decode", format!("error `{}` is reported", err));
+                }
+                (Err(_), Expect::Invalid(_)) => {}
+                (result, _) => mismatch("decode", verdict(result.is_ok())),
+            }
+
+            let read = SchemaReader::new(&self.ts, &vector.root, vector.data.as_slice())
+                .and_then(|mut reader| {
+                    reader.skip()?;
+                    reader.finish()
+                })
+                .map(|rest| rest.is_empty());
+            let read = matches!(read, Ok(true));
+            if read != expect_valid {
+                mismatch("reader", verdict(read));
+            }
+        }
+        mismatches
+    }
+}
+
+fn verdict(valid: bool) -> String {
+    if valid {
+        s!("invalid data are accepted")
+    } else {
+        s!("valid data are rejected")
+    }
+}
+
+fn str_field<'json>(json: &'json Json, field: &'static str) -> Result<&'json str, CorpusError> {
+    json[field].as_str().ok_or(CorpusError::Field(field))
+}
+
+fn hex_field(json: &Json, field: &'static str) -> Result<Vec<u8>, CorpusError> {
+    Vec::<u8>::from_hex(str_field(json, field)?).map_err(|_| CorpusError::Hex(field))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::schema::test::{tn, transaction, type_system};
+
+    #[test]
+    fn roundtrip() {
+        let mut corpus = Corpus::new(type_system());
+        corpus.push("transaction", tn("Transaction"), transaction());
+        corpus.push("empty", tn("Transaction"), vec![]);
+        assert_eq!(corpus.vectors[1].expect, Expect::Invalid(".0".parse().unwrap()));
+
+        let json = corpus.to_json();
+        assert!(json.contains("\"error_path\": \".0\""));
+        assert_eq!(Corpus::from_json(&json).unwrap(), corpus);
+        assert!(corpus.run().is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "generate")]
+    fn generated() {
+        let roots = [tn("Transaction"), tn("Meta")];
+        let corpus = Corpus::generate(type_system(), &roots, default!(), 1, 4).unwrap();
+        assert!(corpus.vectors.iter().any(|vector| matches!(vector.expect, Expect::Valid(_))));
+        assert!(corpus.vectors.iter().any(|vector| matches!(vector.expect, Expect::Invalid(_))));
+        assert_eq!(corpus.run(), vec![]);
+    }
+
+    #[test]
+    fn mismatches() {
+        let mut corpus = Corpus::new(type_system());
+        corpus.push("bytes", tn("Bytes"), vec![1, 0, 0xFF]);
+        corpus.vectors[0].expect = Expect::Valid(json!([["0x00"]]));
+        corpus.push("invalid", tn("Bytes"), vec![1, 0]);
+        corpus.vectors[1].expect = Expect::Invalid(DataPath::new());
+        assert_eq!(corpus.run().iter().map(Mismatch::to_string).collect::<Vec<_>>(), vec![
+            "test vector `bytes` fails decode check: value [[255]] is decoded",
+            "test vector `invalid` fails decode check: error `unexpected end of data at .0[0]` is \
+             reported"
+        ]);
+    }
+}
@@ -0,0 +1,554 @@
+// Strict encoding schema library, implementing validation and parsing of strict
+// encoded data against the schema.
+//
+// Written in 2022 by
+//     Dr. Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2022 by LNP/BP Standards Association, Switzerland.
+//
+// You should have received a copy of the MIT License along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Schema-driven state machine shared by streaming readers and writers.
+
+use std::fmt::{self, Debug, Display, Formatter};
+use std::hash::Hash;
+
+use crate::{
+    DataError, DataPath, DataStep, DecodeError, KeyType, PrimitiveType, StructField, StructType,
+    TypeConstr, TypeName, TypeRef, TypeSystem,
+};
+
+/// Shape of a data item expected by the schema at some position.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub(crate) enum Shape<'ts> {
+    Primitive(PrimitiveType),
+    Struct(&'ts TypeName),
+    Array(u16, Box<Shape<'ts>>),
+    List(Box<Shape<'ts>>),
+    Set(Box<Shape<'ts>>),
+    Map(KeyType, Box<Shape<'ts>>),
+}
+
+impl Display for Shape<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Shape::Primitive(ty) => Display::fmt(ty, f),
+            Shape::Struct(name) => Display::fmt(name, f),
+            Shape::Array(len, item) => write!(f, "{}*{}", item.grouped(), len),
+            Shape::List(item) => write!(f, "{}*", item.grouped()),
+            Shape::Set(item) => write!(f, "{{{}}}", item),
+            Shape::Map(key, item) => write!(f, "{{{}}} -> {}", key, item),
+        }
+    }
+}
+
+impl Shape<'_> {
+    fn is_collection(&self) -> bool {
+        matches!(self, Shape::Array(..) | Shape::List(_) | Shape::Set(_) | Shape::Map(..))
+    }
+
+    /// Formats the shape as an item of an array or a list, putting collections
+    /// in parentheses.
+    fn grouped(&self) -> String {
+        if self.is_collection() {
+            format!("({})", self)
+        } else {
+            self.to_string()
+        }
+    }
+}
+
+pub(crate) trait ToShape {
+    fn shape(&self) -> Shape<'_>;
+}
+
+impl ToShape for PrimitiveType {
+    fn shape(&self) -> Shape<'_> { Shape::Primitive(*self) }
+}
+
+impl ToShape for TypeName {
+    fn shape(&self) -> Shape<'_> { Shape::Struct(self) }
+}
+
+impl ToShape for KeyType {
+    fn shape(&self) -> Shape<'_> { key_shape(*self) }
+}
+
+impl ToShape for TypeRef {
+    fn shape(&self) -> Shape<'_> {
+        match self {
+            TypeRef::InPlace(ty) => ty.shape(),
+            TypeRef::NameRef(ty) => ty.shape(),
+            TypeRef::Nested(ty) => ty.shape(),
+        }
+    }
+}
+
+impl ToShape for Box<TypeRef> {
+    fn shape(&self) -> Shape<'_> { self.as_ref().shape() }
+}
+
+impl<T> ToShape for TypeConstr<T>
+where T: Clone + Ord + Eq + Hash + Debug + ToShape
+{
+    fn shape(&self) -> Shape<'_> {
+        match self {
+            TypeConstr::Plain(ty) => ty.shape(),
+            TypeConstr::Array(len, ty) => Shape::Array(*len, Box::new(ty.shape())),
+            TypeConstr::List(ty) => Shape::List(Box::new(ty.shape())),
+            TypeConstr::Set(ty) => Shape::Set(Box::new(ty.shape())),
+            TypeConstr::Map(key, ty) => Shape::Map(*key, Box::new(ty.shape())),
+        }
+    }
+}
+
+/// Errors of streaming reading or writing strict-encoded data against a
+/// schema.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum CursorError {
+    /// {op} is not allowed at {path}, where the schema expects {expected}
+    Mismatch {
+        path: DataPath,
+        op: &'static str,
+        expected: String,
+    },
+
+    /// {path} is left before all of its {total} items were processed ({done}
+    /// done)
+    Incomplete {
+        path: DataPath,
+        done: u32,
+        total: u32,
+    },
+
+    /// {error} at {path}
+    Data { path: DataPath, error: DataError },
+}
+
+impl From<DecodeError> for CursorError {
+    fn from(err: DecodeError) -> Self {
+        CursorError::Data {
+            path: err.path,
+            error: err.error,
+        }
+    }
+}
+
+/// How the cursor handles ordering of set elements and map keys.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub(crate) enum SortMode {
+    /// Elements and keys must come in the lexicographic order of their
+    /// encoding; processed bytes of elements and keys are recorded by all
+    /// enclosing sets and maps, while map values are not buffered.
+    Check,
+
+    /// Elements and entries are collected for the subsequent sorting; the
+    /// processed bytes are recorded by the innermost set or map only.
+    Collect,
+}
+
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub(crate) struct Sorting {
+    /// Encoding of the current set element or map key.
+    key: Vec<u8>,
+    /// Encoding of the current set element or map entry.
+    entry: Vec<u8>,
+    /// Encoding of the previous set element or map key.
+    last: Option<Vec<u8>>,
+    /// Collected set elements or map entries with their sort keys.
+    pub entries: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+enum Slot<'ts> {
+    /// No data item is expected until the cursor is moved.
+    Empty,
+    /// Data item of the given shape must be processed next.
+    Pending(Shape<'ts>),
+    /// Data item processing has started but not yet completed.
+    Entered,
+}
+
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+enum FrameKind<'ts> {
+    Root,
+    Struct(&'ts StructType),
+    Collection(Shape<'ts>, u16),
+}
+
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub(crate) struct Frame<'ts> {
+    kind: FrameKind<'ts>,
+    slot: Slot<'ts>,
+    /// Number of the current slot for collections; number of started fields
+    /// for structures.
+    pos: u32,
+    pub sorting: Option<Sorting>,
+}
+
+impl<'ts> Frame<'ts> {
+    fn total(&self) -> u32 {
+        match &self.kind {
+            FrameKind::Root => 1,
+            FrameKind::Struct(ty) => ty.len() as u32,
+            FrameKind::Collection(Shape::Map(..), len) => *len as u32 * 2,
+            FrameKind::Collection(_, len) => *len as u32,
+        }
+    }
+
+    fn is_complete(&self) -> bool { self.slot == Slot::Empty && self.pos == self.total() }
+
+    pub fn is_map(&self) -> bool { matches!(self.kind, FrameKind::Collection(Shape::Map(..), _)) }
+
+    /// Detects whether the current slot is a set element or a map key.
+    fn is_key_slot(&self) -> bool {
+        match &self.kind {
+            FrameKind::Collection(Shape::Set(_), _) => true,
+            FrameKind::Collection(Shape::Map(..), _) => self.pos % 2 == 0,
+            _ => false,
+        }
+    }
+
+    /// Returns shape of the collection slot and path steps leading to it.
+    fn slot_shape(&self) -> (Shape<'ts>, Vec<DataStep>) {
+        let pos = self.pos;
+        match &self.kind {
+            FrameKind::Collection(Shape::Map(key, _), _) if pos % 2 == 0 => {
+                (key_shape(*key), vec![
+                    DataStep::MapEntry((pos / 2) as u16),
+                    DataStep::MapKey(*key),
+                ])
+            }
+            FrameKind::Collection(Shape::Map(_, item), _) => ((**item).clone(), vec![]),
+            FrameKind::Collection(
+                Shape::Array(_, item) | Shape::List(item) | Shape::Set(item),
+                _,
+            ) => ((**item).clone(), vec![DataStep::ArrayIndex(pos as u16)]),
+            _ => unreachable!("slot shape request for a non-collection frame"),
+        }
+    }
+}
+
+/// Returns shape of a map key, which never references type names and thus is
+/// not bound to the lifetime of the key type.
+fn key_shape<'any>(key: KeyType) -> Shape<'any> {
+    match key {
+        KeyType::Primitive(ty) => Shape::Primitive(ty),
+        KeyType::Array(len, ty) => Shape::Array(len, Box::new(Shape::Primitive(ty))),
+        KeyType::List(ty) => Shape::List(Box::new(Shape::Primitive(ty))),
+    }
+}
+
+/// Tracks position in the data according to the schema, checking that the
+/// data items are processed in the order and of the shape expected by the
+/// schema.
+#[derive(Clone, Debug)]
+pub(crate) struct SchemaCursor<'ts> {
+    ts: &'ts TypeSystem,
+    stack: Vec<Frame<'ts>>,
+    path: DataPath,
+    mode: SortMode,
+}
+
+impl<'ts> SchemaCursor<'ts> {
+    pub fn new(
+        ts: &'ts TypeSystem,
+        root: &'ts TypeName,
+        mode: SortMode,
+    ) -> Result<Self, CursorError> {
+        let path = DataPath::new();
+        if ts.get(root).is_none() {
+            return Err(DecodeError::with(&path, DataError::UnknownType(root.clone())).into());
+        }
+        Ok(SchemaCursor {
+            ts,
+            stack: vec![Frame {
+                kind: FrameKind::Root,
+                slot: Slot::Pending(Shape::Struct(root)),
+                pos: 0,
+                sorting: None,
+            }],
+            path,
+            mode,
+        })
+    }
+
+    #[inline]
+    pub fn path(&self) -> &DataPath { &self.path }
+
+    pub fn is_finished(&self) -> bool { self.stack.len() == 1 && self.top().is_complete() }
+
+    fn top(&self) -> &Frame<'ts> { self.stack.last().expect("cursor stack is never empty") }
+
+    fn top_mut(&mut self) -> &mut Frame<'ts> {
+        self.stack.last_mut().expect("cursor stack is never empty")
+    }
+
+    /// Returns shape of the data item which must be processed next, if any.
+    pub fn pending(&self) -> Option<&Shape<'ts>> {
+        match &self.top().slot {
+            Slot::Pending(shape) => Some(shape),
+            _ => None,
+        }
+    }
+
+    pub fn mismatch(&self, op: &'static str) -> CursorError {
+        let top = self.top();
+        let expected = match (&top.kind, &top.slot) {
+            (_, Slot::Pending(shape)) => shape.to_string(),
+            (FrameKind::Root, _) => s!("end of data"),
+            (FrameKind::Struct(_), _) if !top.is_complete() => s!("next field"),
+            (FrameKind::Struct(_), _) => s!("end of structure"),
+            (FrameKind::Collection(shape, _), _) => format!("end of {}", shape),
+        };
+        CursorError::Mismatch {
+            path: self.path.clone(),
+            op,
+            expected,
+        }
+    }
+
+    pub fn data_error(&self, error: impl Into<DataError>) -> CursorError {
+        CursorError::Data {
+            path: self.path.clone(),
+            error: error.into(),
+        }
+    }
+
+    /// Starts processing of the pending data item if it matches `filter`.
+    pub fn begin(
+        &mut self,
+        op: &'static str,
+        filter: impl FnOnce(&Shape<'ts>) -> bool,
+    ) -> Result<Shape<'ts>, CursorError> {
+        match self.pending() {
+            Some(shape) if filter(shape) => {}
+            _ => return Err(self.mismatch(op)),
+        }
+        let top = self.top_mut();
+        if top.is_key_slot() {
+            let sorting = top.sorting.as_mut().expect("sets and maps have sorting data");
+            sorting.key.clear();
+            sorting.entry.clear();
+        }
+        match std::mem::replace(&mut top.slot, Slot::Entered) {
+            Slot::Pending(shape) => Ok(shape),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Enters a structure started with [`Self::begin`].
+    pub fn enter_struct(&mut self, name: &'ts TypeName) -> Result<&'ts StructType, CursorError> {
+        let ty = match self.ts.get(name) {
+            Some(ty) => ty,
+            None => return Err(self.data_error(DataError::UnknownType(name.clone()))),
+        };
+        self.stack.push(Frame {
+            kind: FrameKind::Struct(ty),
+            slot: Slot::Empty,
+            pos: 0,
+            sorting: None,
+        });
+        Ok(ty)
+    }
+
+    /// Enters a collection started with [`Self::begin`].
+    pub fn enter_collection(&mut self, shape: Shape<'ts>, len: u16) {
+        debug_assert!(shape.is_collection());
+        let sorting = match shape {
+            Shape::Set(_) | Shape::Map(..) => Some(Sorting::default()),
+            _ => None,
+        };
+        let mut frame = Frame {
+            kind: FrameKind::Collection(shape, len),
+            slot: Slot::Empty,
+            pos: 0,
+            sorting,
+        };
+        if len > 0 {
+            self.setup_slot(&mut frame);
+        }
+        self.stack.push(frame);
+    }
+
+    fn setup_slot(&mut self, frame: &mut Frame<'ts>) {
+        let (shape, steps) = frame.slot_shape();
+        frame.slot = Slot::Pending(shape);
+        steps.into_iter().for_each(|step| self.path.push(step));
+    }
+
+    /// Moves to the next field of the current structure, returning `None` if
+    /// there are no more fields.
+    pub fn next_field(
+        &mut self,
+        op: &'static str,
+    ) -> Result<Option<&'ts StructField>, CursorError> {
+        let top = self.top();
+        let ty = match (&top.kind, &top.slot) {
+            (FrameKind::Struct(ty), Slot::Empty) => *ty,
+            _ => return Err(self.mismatch(op)),
+        };
+        let pos = top.pos;
+        let field = match ty.get(pos as usize) {
+            None => return Ok(None),
+            Some(field) => field,
+        };
+        let top = self.top_mut();
+        top.pos += 1;
+        top.slot = Slot::Pending(field.ty.shape());
+        self.path.push(DataStep::StructField(pos as u16));
+        Ok(Some(field))
+    }
+
+    /// Skips pending field of the current structure, used for absent optional
+    /// fields.
+    pub fn skip_field(&mut self) {
+        let top = self.top_mut();
+        debug_assert!(matches!(top.kind, FrameKind::Struct(_)));
+        debug_assert!(matches!(top.slot, Slot::Pending(_)));
+        top.slot = Slot::Empty;
+        self.path.pop();
+    }
+
+    /// Completes processing of the current data item and moves to the next
+    /// one.
+    pub fn complete(&mut self) -> Result<(), CursorError> {
+        let mode = self.mode;
+        let path = self.path.clone();
+        let top = self.top_mut();
+        debug_assert_eq!(top.slot, Slot::Entered);
+        let is_key = top.is_key_slot();
+        let is_map = top.is_map();
+        if let Some(sorting) = &mut top.sorting {
+            match mode {
+                SortMode::Check if is_key => {
+                    let error = match &sorting.last {
+                        Some(last) if *last == sorting.key && is_map => {
+                            Some(DataError::RepeatedMapKey)
+                        }
+                        Some(last) if *last == sorting.key => Some(DataError::RepeatedSetItem),
+                        Some(last) if *last > sorting.key && is_map => {
+                            Some(DataError::UnsortedMapKeys)
+                        }
+                        Some(last) if *last > sorting.key => Some(DataError::UnsortedSetItems),
+                        _ => None,
+                    };
+                    if let Some(error) = error {
+                        return Err(CursorError::Data { path, error });
+                    }
+                    sorting.last = Some(sorting.key.clone());
+                }
+                // Map entries are collected once their values are processed
+                SortMode::Collect if !(is_key && is_map) => {
+                    let entry = (sorting.key.clone(), sorting.entry.clone());
+                    sorting.entries.push(entry);
+                }
+                _ => {}
+            }
+        }
+
+        top.slot = Slot::Empty;
+        let is_root = matches!(top.kind, FrameKind::Root);
+        let is_collection = matches!(top.kind, FrameKind::Collection(..));
+        if !is_root {
+            self.path.pop();
+        }
+        let mut top = self.stack.pop().expect("cursor stack is never empty");
+        if !matches!(top.kind, FrameKind::Struct(_)) {
+            top.pos += 1;
+        }
+        if is_collection && top.pos < top.total() {
+            self.setup_slot(&mut top);
+        }
+        self.stack.push(top);
+        Ok(())
+    }
+
+    /// Leaves completely processed structure or collection and completes
+    /// the data item in the parent frame.
+    pub fn leave(&mut self, op: &'static str) -> Result<(), CursorError> {
+        self.pop(op)?;
+        self.complete()
+    }
+
+    /// Removes completely processed structure or collection from the stack,
+    /// returning its frame. The caller must call [`Self::complete`] afterwards.
+    pub fn pop(&mut self, op: &'static str) -> Result<Frame<'ts>, CursorError> {
+        let top = self.top();
+        match top.kind {
+            FrameKind::Root => return Err(self.mismatch(op)),
+            _ if top.slot == Slot::Entered => return Err(self.mismatch(op)),
+            _ if !top.is_complete() => {
+                let mut path = self.path.clone();
+                let mut done = top.pos;
+                match (&top.kind, &top.slot) {
+                    (_, Slot::Empty) => {}
+                    (FrameKind::Struct(_), _) => {
+                        done -= 1;
+                        path.pop();
+                    }
+                    (_, _) if top.is_map() && top.is_key_slot() => {
+                        path.pop();
+                        path.pop();
+                    }
+                    _ => {
+                        path.pop();
+                    }
+                }
+                return Err(CursorError::Incomplete {
+                    path,
+                    done,
+                    total: top.total(),
+                });
+            }
+            _ => {}
+        }
+        Ok(self.stack.pop().expect("cursor stack is never empty"))
+    }
+
+    /// Returns number of fields or collection items still to be processed in
+    /// the current structure or collection.
+    pub fn remaining(&self) -> u32 {
+        let top = self.top();
+        let pending =
+            matches!(top.slot, Slot::Pending(_)) && matches!(top.kind, FrameKind::Struct(_));
+        top.total() - top.pos + pending as u32
+    }
+
+    /// Records processed bytes for the set and map sorting. Returns whether
+    /// the bytes were recorded.
+    pub fn record(&mut self, data: &[u8]) -> bool {
+        let mode = self.mode;
+        let mut recorded = false;
+        for frame in self.stack.iter_mut().rev() {
+            if frame.slot != Slot::Entered {
+                continue;
+            }
+            let is_key = frame.is_key_slot();
+            if let Some(sorting) = &mut frame.sorting {
+                if is_key {
+                    sorting.key.extend(data);
+                }
+                recorded = true;
+                // Whole entries are needed only for the subsequent sorting
+                if mode == SortMode::Collect {
+                    sorting.entry.extend(data);
+                    break;
+                }
+            }
+        }
+        recorded
+    }
+
+    /// Returns number of bytes buffered for the set and map entries.
+    #[cfg(test)]
+    pub fn buffered(&self) -> usize {
+        self.stack
+            .iter()
+            .filter_map(|frame| frame.sorting.as_ref())
+            .map(|sorting| sorting.entry.len())
+            .sum()
+    }
+}
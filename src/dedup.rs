@@ -0,0 +1,211 @@
+// Strict encoding schema library, implementing validation and parsing of strict
+// encoded data against the schema.
+//
+// Written in 2022 by
+//     Dr. Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2022 by LNP/BP Standards Association, Switzerland.
+//
+// You should have received a copy of the MIT License along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{PrimitiveType, StructType, TypeConstr, TypeName, TypeRef, TypeSystem};
+
+/// Class of a referenced type: index of the equivalence class for the
+/// defined types, or the name itself for the types absent in the type system.
+type Class = Result<usize, TypeName>;
+
+/// Structure of a field with the referenced types replaced by their classes.
+#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug)]
+enum FieldShape {
+    InPlace(TypeConstr<PrimitiveType>),
+    NameRef(TypeConstr<Class>),
+    Nested(TypeConstr<Box<FieldShape>>),
+}
+
+fn ref_shape(ty: &TypeRef, class: &dyn Fn(&TypeName) -> Class) -> FieldShape {
+    match ty {
+        TypeRef::InPlace(constr) => FieldShape::InPlace(constr.clone()),
+        TypeRef::NameRef(constr) => FieldShape::NameRef(constr.clone().map(|name| class(&name))),
+        TypeRef::Nested(constr) => {
+            FieldShape::Nested(constr.clone().map(|item| Box::new(ref_shape(&item, class))))
+        }
+    }
+}
+
+fn shape(ty: &StructType, class: impl Fn(&TypeName) -> Class) -> Vec<(bool, FieldShape)> {
+    ty.into_iter().map(|field| (field.optional, ref_shape(&field.ty, &class))).collect()
+}
+
+impl TypeSystem {
+    /// Finds groups of structurally equivalent types, i.e. types which have
+    /// the same encoding regardless of their names. Types referencing each
+    /// other (including recursive types) are compared recursively.
+    ///
+    /// Aliases are not compared, since they already name some other type.
+    ///
+    /// Returns groups containing at least two types each, ordered by their
+    /// first type name.
+    pub fn equivalent_types(&self) -> Vec<BTreeSet<TypeName>> {
+        // Refines partition of the types, starting with a single class, until
+        // types of each class have fields of the same shape referencing types
+        // of the same classes.
+        let types = self.into_iter().filter(|(name, _)| !self.is_alias(name));
+        let mut classes = types.clone().map(|(name, _)| (name, 0usize)).collect::<BTreeMap<_, _>>();
+        let mut count = 1;
+        loop {
+            let mut refined = BTreeMap::new();
+            let mut next = BTreeMap::new();
+            for (name, ty) in types.clone() {
+                let shape =
+                    shape(ty, |name| classes.get(name).copied().ok_or_else(|| name.clone()));
+                let len = refined.len();
+                let class = *refined.entry((classes[name], shape)).or_insert(len);
+                next.insert(name, class);
+            }
+            classes = next;
+            if refined.len() == count {
+                break;
+            }
+            count = refined.len();
+        }
+
+        let mut groups = BTreeMap::<usize, BTreeSet<TypeName>>::new();
+        for (name, class) in classes {
+            groups.entry(class).or_default().insert(name.clone());
+        }
+        let mut groups = groups.into_values().filter(|group| group.len() > 1).collect::<Vec<_>>();
+        groups.sort();
+        groups
+    }
+
+    /// Replaces each group of structurally equivalent types with a single
+    /// canonical type, rewriting references to the other types of the group.
+    /// The other types of the group are replaced by aliases of the canonical
+    /// type, so the resulting type system encodes the same data under the
+    /// same names.
+    ///
+    /// The canonical type is the first type of the group listed in `keep`, or
+    /// the first type of the group if none is listed. Types listed in `keep`
+    /// (like the root types) are never replaced, even when they are not
+    /// canonical.
+    ///
+    /// Returns the introduced aliases, mapping names of the replaced types to
+    /// the names of their canonical types.
+    pub fn deduplicate<'a>(
+        &mut self,
+        keep: impl IntoIterator<Item = &'a TypeName>,
+    ) -> BTreeMap<TypeName, TypeName> {
+        let keep = keep.into_iter().collect::<BTreeSet<_>>();
+        let mut canonical = BTreeMap::new();
+        for group in self.equivalent_types() {
+            let first = group
+                .iter()
+                .find(|name| keep.contains(name))
+                .unwrap_or_else(|| group.iter().next().expect("group contains at least two types"));
+            for name in &group {
+                canonical.insert(name.clone(), first.clone());
+            }
+        }
+
+        let mut deduplicated = TypeSystem::new();
+        let mut aliases = BTreeMap::new();
+        for (name, ty) in &*self {
+            match canonical.get(name) {
+                Some(first) if first != name && !keep.contains(name) => {
+                    let ty = TypeRef::NameRef(TypeConstr::Plain(first.clone()));
+                    deduplicated
+                        .push_alias(name.clone(), ty)
+                        .expect("types are taken from the type system");
+                    aliases.insert(name.clone(), first.clone());
+                    continue;
+                }
+                _ => {}
+            }
+            let ty = ty.map_refs(|name| canonical.get(name).unwrap_or(name).clone());
+            deduplicated
+                .push_decl(name.clone(), ty, self.is_alias(name))
+                .expect("types are taken from the type system");
+        }
+        *self = deduplicated;
+        aliases
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::schema::test::{tn, transaction, type_system};
+    use crate::{strict_vec, type_system, StructField, Verify};
+
+    fn duplicated() -> TypeSystem {
+        let mut ts = type_system();
+        ts.merge(&type_system![
+            "Data" :: { StructField::bytes() },
+            "Script" :: { StructField::list(PrimitiveType::U8) },
+            "Stack" :: { StructField::typed_list("Script") },
+            "TxOut" :: { StructField::primitive(PrimitiveType::U64), StructField::with("Script") },
+            "Chain" :: {
+                StructField::primitive(PrimitiveType::U8),
+                StructField::optional(TypeRef::NameRef(TypeConstr::Plain(tn("Chain")))),
+            },
+            "Link" :: {
+                StructField::primitive(PrimitiveType::U8),
+                StructField::optional(TypeRef::NameRef(TypeConstr::Plain(tn("Node")))),
+            },
+            "Node" :: {
+                StructField::primitive(PrimitiveType::U8),
+                StructField::optional(TypeRef::NameRef(TypeConstr::Plain(tn("Link")))),
+            },
+            "Pair" :: { StructField::primitive(PrimitiveType::U8), StructField::primitive(PrimitiveType::U16) }
+        ])
+        .unwrap();
+        ts
+    }
+
+    #[test]
+    fn equivalent() {
+        assert_eq!(duplicated().equivalent_types(), vec![
+            bset! { tn("Bytes"), tn("Data"), tn("Script") },
+            bset! { tn("Chain"), tn("Link"), tn("Node") },
+            bset! { tn("Output"), tn("TxOut") },
+            bset! { tn("Stack"), tn("Witness") },
+        ]);
+        assert!(type_system().equivalent_types().is_empty());
+    }
+
+    #[test]
+    fn deduplicate() {
+        let mut ts = duplicated();
+        let aliases = ts.deduplicate([&tn("Transaction"), &tn("TxOut"), &tn("Output")]);
+        assert_eq!(aliases, bmap! {
+            tn("Data") => tn("Bytes"),
+            tn("Link") => tn("Chain"),
+            tn("Node") => tn("Chain"),
+            tn("Script") => tn("Bytes"),
+            tn("Witness") => tn("Stack")
+        });
+        assert_eq!(ts.get(&tn("Transaction")).unwrap().to_string(), "U32, Input*, Output*, U32");
+        assert_eq!(ts.get(&tn("TxOut")).unwrap().to_string(), "U64, Bytes");
+        assert_eq!(ts.get(&tn("Chain")).unwrap().to_string(), "U8, Chain?");
+        assert_eq!(ts.equivalent_types(), vec![bset! { tn("Output"), tn("TxOut") }]);
+        ts.check().unwrap();
+        assert_eq!(
+            ts.decode(&tn("Transaction"), transaction()),
+            duplicated().decode(&tn("Transaction"), transaction())
+        );
+
+        let mut ts = duplicated();
+        let aliases = ts.deduplicate([&tn("Transaction")]);
+        assert_eq!(aliases[&tn("TxOut")], tn("Output"));
+        assert!(ts.equivalent_types().is_empty());
+        assert_eq!(ts.len(), duplicated().len());
+        assert!(ts.is_alias(&tn("TxOut")));
+        assert!(tn("Data").verify(&ts, &mut Cursor::new([2, 0, 0xCA, 0xFE])));
+        assert!(!tn("Data").verify(&ts, &mut Cursor::new([2, 0, 0xCA])));
+    }
+}
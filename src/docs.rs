@@ -0,0 +1,307 @@
+// Strict encoding schema library, implementing validation and parsing of strict
+// encoded data against the schema.
+//
+// Written in 2022 by
+//     Dr. Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2022 by LNP/BP Standards Association, Switzerland.
+//
+// You should have received a copy of the MIT License along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use crate::{StructField, TypeName, TypeSystem};
+
+/// Format of the generated documentation.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Display)]
+pub enum DocFormat {
+    #[display("Markdown")]
+    Markdown,
+
+    #[display("HTML")]
+    Html,
+}
+
+/// Documentation annotation of a structure field.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct FieldDoc {
+    pub name: String,
+    pub doc: String,
+}
+
+/// Documentation annotations of a type, which are not a part of the schema.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct TypeDoc {
+    /// Description of the type.
+    pub doc: String,
+
+    /// Names and descriptions of the type fields, in the order of the fields.
+    pub fields: Vec<FieldDoc>,
+}
+
+/// Parameters of the documentation generation.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct DocConfig {
+    /// Document title.
+    pub title: String,
+
+    /// Output format.
+    pub format: DocFormat,
+
+    /// Annotations for the types of the type system.
+    pub annotations: BTreeMap<TypeName, TypeDoc>,
+}
+
+impl DocConfig {
+    /// Constructs configuration without annotations.
+    pub fn new(title: impl ToString, format: DocFormat) -> Self {
+        DocConfig {
+            title: title.to_string(),
+            format,
+            annotations: empty!(),
+        }
+    }
+}
+
+/// Field table row.
+struct Row {
+    no: usize,
+    name: String,
+    ty: String,
+    link: Option<TypeName>,
+    optional: bool,
+    size: String,
+    doc: String,
+}
+
+/// Documentation section of a type.
+struct Section<'ts> {
+    name: &'ts TypeName,
+    doc: String,
+    size: String,
+    alias: bool,
+    rows: Vec<Row>,
+    uses: BTreeSet<&'ts TypeName>,
+    users: BTreeSet<&'ts TypeName>,
+}
+
+impl TypeSystem {
+    /// Generates documentation for the type system, with a section per type
+    /// containing the table of the type fields and links to the types it uses
+    /// and which use it. Sections of the aliases name the aliased type instead
+    /// of the fields table.
+    pub fn to_docs(&self, config: &DocConfig) -> String {
+        let mut sections = BTreeMap::new();
+        for (name, ty) in self {
+            let annotation = config.annotations.get(name);
+            let rows = ty
+                .into_iter()
+                .enumerate()
+                .map(|(no, field)| {
+                    let doc =
+                        annotation.and_then(|a| a.fields.get(no)).cloned().unwrap_or_default();
+                    Row {
+                        no,
+                        name: doc.name,
+                        ty: field.ty.to_string(),
+                        link: referenced(field).cloned(),
+                        optional: field.optional,
+                        size: self.field_size(field).to_string(),
+                        doc: doc.doc,
+                    }
+                })
+                .collect();
+            sections.insert(name, Section {
+                name,
+                doc: annotation.map(|a| a.doc.clone()).unwrap_or_default(),
+                size: self.size_of(name).to_string(),
+                alias: self.is_alias(name),
+                rows,
+                uses: ty.into_iter().filter_map(referenced).collect(),
+                users: empty!(),
+            });
+        }
+        for (name, ty) in self {
+            for used in ty.into_iter().filter_map(referenced) {
+                if let Some(section) = sections.get_mut(used) {
+                    section.users.insert(name);
+                }
+            }
+        }
+
+        let mut doc = String::new();
+        match config.format {
+            DocFormat::Markdown => {
+                writeln!(doc, "# {}", config.title).ok();
+                for section in sections.values() {
+                    markdown(&mut doc, section);
+                }
+            }
+            DocFormat::Html => {
+                writeln!(doc, "<!DOCTYPE html>").ok();
+                writeln!(doc, "<html>").ok();
+                writeln!(doc, "<head>").ok();
+                writeln!(doc, "<meta charset=\"utf-8\">").ok();
+                writeln!(doc, "<title>{}</title>", escape(&config.title)).ok();
+                writeln!(doc, "</head>").ok();
+                writeln!(doc, "<body>").ok();
+                writeln!(doc, "<h1>{}</h1>", escape(&config.title)).ok();
+                for section in sections.values() {
+                    html(&mut doc, section);
+                }
+                writeln!(doc, "</body>").ok();
+                writeln!(doc, "</html>").ok();
+            }
+        }
+        doc
+    }
+}
+
+fn referenced(field: &StructField) -> Option<&TypeName> { field.ty.type_name() }
+
+fn yes_no(flag: bool) -> &'static str {
+    if flag {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
+/// Anchor of the type section, matching the one generated by GitHub for the
+/// section header.
+fn anchor(name: &TypeName) -> String { name.to_lowercase() }
+
+fn markdown(doc: &mut String, section: &Section) {
+    let cell = |s: &str| s.replace('|', "\\|");
+    let link = |name: &TypeName| format!("[`{}`](#{})", name, anchor(name));
+    writeln!(doc).ok();
+    writeln!(doc, "## {}", section.name).ok();
+    writeln!(doc).ok();
+    if !section.doc.is_empty() {
+        writeln!(doc, "{}", section.doc).ok();
+        writeln!(doc).ok();
+    }
+    writeln!(doc, "Encoded size: {} bytes", section.size).ok();
+    writeln!(doc).ok();
+    let ty = |row: &Row| match &row.link {
+        Some(name) => format!("[`{}`](#{})", row.ty, anchor(name)),
+        None => format!("`{}`", row.ty),
+    };
+    if section.alias {
+        writeln!(doc, "Alias of {}", ty(&section.rows[0])).ok();
+    } else {
+        writeln!(doc, "| # | Name | Type | Optional | Size | Description |").ok();
+        writeln!(doc, "|---|------|------|----------|------|-------------|").ok();
+        for row in &section.rows {
+            writeln!(
+                doc,
+                "| {} | {} | {} | {} | {} | {} |",
+                row.no,
+                cell(&row.name),
+                ty(row),
+                yes_no(row.optional),
+                row.size,
+                cell(&row.doc)
+            )
+            .ok();
+        }
+    }
+    if !section.uses.is_empty() {
+        writeln!(doc).ok();
+        let links = section.uses.iter().map(|name| link(name)).collect::<Vec<_>>();
+        writeln!(doc, "Uses: {}", links.join(", ")).ok();
+    }
+    if !section.users.is_empty() {
+        writeln!(doc).ok();
+        let links = section.users.iter().map(|name| link(name)).collect::<Vec<_>>();
+        writeln!(doc, "Used by: {}", links.join(", ")).ok();
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn html(doc: &mut String, section: &Section) {
+    let link = |name: &TypeName| format!("<a href=\"#{0}\"><code>{0}</code></a>", escape(name));
+    writeln!(doc, "<section id=\"{}\">", escape(section.name)).ok();
+    writeln!(doc, "<h2>{}</h2>", escape(section.name)).ok();
+    if !section.doc.is_empty() {
+        writeln!(doc, "<p>{}</p>", escape(&section.doc)).ok();
+    }
+    writeln!(doc, "<p>Encoded size: {} bytes</p>", section.size).ok();
+    let ty = |row: &Row| match &row.link {
+        Some(name) => {
+            format!("<a href=\"#{}\"><code>{}</code></a>", escape(name), escape(&row.ty))
+        }
+        None => format!("<code>{}</code>", escape(&row.ty)),
+    };
+    if section.alias {
+        writeln!(doc, "<p>Alias of {}</p>", ty(&section.rows[0])).ok();
+    } else {
+        writeln!(doc, "<table>").ok();
+        writeln!(
+            doc,
+            "<thead><tr><th>#</th><th>Name</th><th>Type</th><th>Optional</th><th>Size</\
+             th><th>Description</th></tr></thead>"
+        )
+        .ok();
+        writeln!(doc, "<tbody>").ok();
+        for row in &section.rows {
+            writeln!(
+                doc,
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                row.no,
+                escape(&row.name),
+                ty(row),
+                yes_no(row.optional),
+                row.size,
+                escape(&row.doc)
+            )
+            .ok();
+        }
+        writeln!(doc, "</tbody>").ok();
+        writeln!(doc, "</table>").ok();
+    }
+    if !section.uses.is_empty() {
+        let links = section.uses.iter().map(|name| link(name)).collect::<Vec<_>>();
+        writeln!(doc, "<p>Uses: {}</p>", links.join(", ")).ok();
+    }
+    if !section.users.is_empty() {
+        let links = section.users.iter().map(|name| link(name)).collect::<Vec<_>>();
+        writeln!(doc, "<p>Used by: {}</p>", links.join(", ")).ok();
+    }
+    writeln!(doc, "</section>").ok();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::schema::test::{golden, tn, type_system};
+
+    fn config(format: DocFormat) -> DocConfig {
+        let mut config = DocConfig::new("Transactions", format);
+        config.annotations.insert(tn("OutPoint"), TypeDoc {
+            doc: s!("Reference to a transaction output."),
+            fields: vec![
+                FieldDoc {
+                    name: s!("txid"),
+                    doc: s!("Id of the transaction"),
+                },
+                FieldDoc {
+                    name: s!("vout"),
+                    doc: s!("Output number | index"),
+                },
+            ],
+        });
+        config
+    }
+
+    #[test]
+    fn markdown() { golden("docs.md", &type_system().to_docs(&config(DocFormat::Markdown))); }
+
+    #[test]
+    fn html() { golden("docs.html", &type_system().to_docs(&config(DocFormat::Html))); }
+}
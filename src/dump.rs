@@ -0,0 +1,277 @@
+// Strict encoding schema library, implementing validation and parsing of strict
+// encoded data against the schema.
+//
+// Written in 2022 by
+//     Dr. Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2022 by LNP/BP Standards Association, Switzerland.
+//
+// You should have received a copy of the MIT License along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::fmt::{self, Display, Formatter};
+use std::io::Cursor;
+
+use crate::{
+    DataPath, DataStep, KeyType, PrimitiveType, PrimitiveValue, StructField, TypeName, TypeSystem,
+    Verify, Visitor,
+};
+
+/// Number of bytes displayed in a single line of the dump.
+const BYTES_PER_LINE: usize = 16;
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+struct Line {
+    offset: u64,
+    data: Vec<u8>,
+    path: DataPath,
+    ty: String,
+    note: String,
+}
+
+/// Hex dump of strict-encoded data annotated with the schema structure.
+///
+/// Each line of the dump contains offset of the data item, its raw bytes,
+/// path to the item indented according to its depth, type of the item and its
+/// decoded value. Invalid data are dumped up to the point where verification
+/// has failed; the failure point is marked with `>>>`.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct HexDump {
+    lines: Vec<Line>,
+    failure: Option<(u64, DataPath)>,
+    rest: Vec<u8>,
+}
+
+impl HexDump {
+    /// Verifies `data` against type `root` from the type system, collecting
+    /// the annotated dump.
+    pub fn new(ts: &TypeSystem, root: &TypeName, data: &[u8]) -> HexDump {
+        let mut collector = Collector::default();
+        let mut buf = Cursor::new(data);
+        let valid = root.verify_with(ts, &mut buf, &mut DataPath::new(), &mut collector);
+        let failure = match collector.failure {
+            Some(failure) => Some(failure),
+            None if !valid => Some((0, DataPath::new())),
+            None => None,
+        };
+        let pos = match failure {
+            Some((offset, _)) => offset,
+            None => buf.position(),
+        } as usize;
+        HexDump {
+            lines: collector.lines,
+            failure,
+            rest: data[pos.min(data.len())..].to_vec(),
+        }
+    }
+
+    /// Detects whether the data were valid and completely consumed.
+    pub fn is_valid(&self) -> bool { self.failure.is_none() && self.rest.is_empty() }
+
+    /// Returns offset and path to the data item which has failed verification.
+    pub fn failure(&self) -> Option<(u64, &DataPath)> {
+        self.failure.as_ref().map(|(offset, path)| (*offset, path))
+    }
+}
+
+impl Display for HexDump {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        fn bytes(f: &mut Formatter<'_>, offset: u64, data: &[u8]) -> fmt::Result {
+            let hex = data.iter().map(|byte| format!("{:02x}", byte)).collect::<Vec<_>>();
+            write!(f, "{:08x}  {:<width$}", offset, hex.join(" "), width = BYTES_PER_LINE * 3 - 1)
+        }
+
+        for line in &self.lines {
+            let mut chunks = line.data.chunks(BYTES_PER_LINE);
+            bytes(f, line.offset, chunks.next().unwrap_or_default())?;
+            write!(
+                f,
+                "  {:indent$}{} {}",
+                "",
+                line.path,
+                line.ty,
+                indent = line.path.len() as usize * 2
+            )?;
+            if !line.note.is_empty() {
+                write!(f, " {}", line.note)?;
+            }
+            writeln!(f)?;
+            for (no, chunk) in chunks.enumerate() {
+                bytes(f, line.offset + ((no + 1) * BYTES_PER_LINE) as u64, chunk)?;
+                writeln!(f)?;
+            }
+        }
+
+        let end = self.rest.len().min(BYTES_PER_LINE);
+        match &self.failure {
+            Some((offset, path)) => {
+                bytes(f, *offset, &self.rest[..end])?;
+                writeln!(f, "  >>> invalid data at {}", path)?;
+            }
+            None if !self.rest.is_empty() => {
+                let offset = self.lines.last().map(|line| line.offset + line.data.len() as u64);
+                bytes(f, offset.unwrap_or_default(), &self.rest[..end])?;
+                writeln!(f, "  >>> {} bytes of data are not consumed", self.rest.len())?;
+            }
+            None => {}
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct Collector {
+    lines: Vec<Line>,
+    /// Fields being visited, with the depth of their paths
+    fields: Vec<(usize, StructField)>,
+    /// Offset and path of the failed data item
+    failure: Option<(u64, DataPath)>,
+}
+
+impl Collector {
+    /// Returns type of the data item at `path`: the type of the field being
+    /// visited or of the collection item, map key or map value nested into it.
+    fn ty(&self, path: &DataPath) -> String {
+        let (depth, field) = match self.fields.last() {
+            Some(field) => field,
+            None => return s!(""),
+        };
+        if path.len() as usize == *depth {
+            return if field.optional { format!("{}?", field.ty) } else { field.ty.to_string() };
+        }
+        if let Some(DataStep::MapKey(key)) = path.last() {
+            return key.to_string();
+        }
+        path.into_iter()
+            .skip(*depth)
+            .try_fold(field.ty.clone(), |ty, step| ty.item(step))
+            .map(|ty| ty.to_string())
+            .unwrap_or_default()
+    }
+
+    fn push(&mut self, offset: u64, data: impl Into<Vec<u8>>, path: &DataPath, note: String) {
+        let ty = self.ty(path);
+        self.lines.push(Line {
+            offset,
+            data: data.into(),
+            path: path.clone(),
+            ty,
+            note,
+        });
+    }
+}
+
+impl Visitor for Collector {
+    fn enter_struct(&mut self, path: &DataPath, offset: u64, name: &TypeName) {
+        self.lines.push(Line {
+            offset,
+            data: vec![],
+            path: path.clone(),
+            ty: name.to_string(),
+            note: s!(""),
+        });
+    }
+
+    fn field(&mut self, path: &DataPath, _: u64, _: u16, field: &StructField) {
+        self.leave(path, 0);
+        self.fields.push((path.len() as usize, field.clone()));
+    }
+
+    fn optional_tag(&mut self, path: &DataPath, offset: u64, tag: u8) {
+        self.push(offset, [tag], path, format!("tag = {}", tag));
+    }
+
+    fn list_len(&mut self, path: &DataPath, offset: u64, len: u16) {
+        self.push(offset, len.to_le_bytes(), path, format!("length = {}", len));
+    }
+
+    fn map_key(&mut self, _: &DataPath, _: u64, _: &KeyType, _: &[u8]) {}
+
+    fn primitive(&mut self, path: &DataPath, offset: u64, ty: PrimitiveType, data: &[u8]) {
+        let mut bytes = vec![];
+        if ty.is_char() {
+            bytes.extend((data.len() as u16).to_le_bytes());
+        }
+        bytes.extend(data);
+        let note = match PrimitiveValue::with(ty, data) {
+            Some(value) => format!("= {}", value),
+            None => s!(""),
+        };
+        self.push(offset, bytes, path, note);
+    }
+
+    fn leave(&mut self, path: &DataPath, _: u64) {
+        let depth = path.len() as usize;
+        while matches!(self.fields.last(), Some((field_depth, _)) if *field_depth >= depth) {
+            self.fields.pop();
+        }
+    }
+
+    fn fail(&mut self, path: &DataPath, offset: u64) {
+        self.failure = Some((offset, path.clone()));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::schema::test::{tn, type_system};
+    use crate::{strict_vec, type_system};
+
+    #[test]
+    fn dump_meta() {
+        let ts = type_system();
+        let data = [1, 0, 1, 0, b'x', 1, 0, 1, 0, 1, 0, b'k', 0, 0];
+        let dump = HexDump::new(&ts, &tn("Meta"), &data);
+        assert!(dump.is_valid());
+        assert_eq!(
+            dump.to_string(),
+            "\
+00000000                                                   . Meta
+00000000  01 00                                              .0 AsciiChar* length = 1
+00000002  01 00 78                                             .0[0] AsciiChar = \"x\"
+00000005  01 00                                              .1 {UnicodeChar[]} -> UnicodeString \
+             length = 1
+00000007  01 00                                                  .1{0}<UnicodeChar[]> \
+             UnicodeChar[] length = 1
+00000009  01 00 6b                                                 .1{0}<UnicodeChar[]>[0] \
+             UnicodeChar = \"k\"
+0000000c                                                       .1{0} UnicodeString
+0000000c  00 00                                                  .1{0}.0 UnicodeChar* length = 0
+"
+        );
+
+        let key = KeyType::Primitive(PrimitiveType::U8);
+        let ts = type_system!["Pairs" :: { StructField::map(key, PrimitiveType::U16) }];
+        let dump = HexDump::new(&ts, &tn("Pairs"), &[1, 0, 5, 7, 0]);
+        assert_eq!(
+            dump.to_string(),
+            "\
+00000000                                                   . Pairs
+00000000  01 00                                              .0 {U8} -> U16 length = 1
+00000002  05                                                     .0{0}<U8> U8 = 5
+00000003  07 00                                                .0{0} U16 = 7
+"
+        );
+    }
+
+    #[test]
+    fn dump_failure() {
+        let ts = type_system();
+        let data = [1, 0, 1, 0, b'x', 2, 0, 1, 0, 1, 0, b'k', 0, 0, 1, 0, 1, 0, b'a'];
+        let dump = HexDump::new(&ts, &tn("Meta"), &data);
+        assert_eq!(dump.failure().unwrap().0, 14);
+        assert_eq!(dump.failure().unwrap().1.to_string(), ".1{1}<UnicodeChar[]>");
+        assert_eq!(
+            dump.to_string().lines().last().unwrap(),
+            format!(
+                "0000000e  {:<47}  >>> invalid data at .1{{1}}<UnicodeChar[]>",
+                "01 00 01 00 61"
+            )
+        );
+
+        let dump = HexDump::new(&ts, &tn("Bytes"), &[0, 0, 0xFF]);
+        assert!(!dump.is_valid());
+        assert!(dump.to_string().ends_with(">>> 1 bytes of data are not consumed\n"));
+    }
+}
@@ -0,0 +1,381 @@
+// Strict encoding schema library, implementing validation and parsing of strict
+// encoded data against the schema.
+//
+// Written in 2022 by
+//     Dr. Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2022 by LNP/BP Standards Association, Switzerland.
+//
+// You should have received a copy of the MIT License along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::hash::Hash;
+
+use rand::Rng;
+
+use crate::{
+    KeyType, PrimitiveType, PrimitiveValue, StructType, TypeConstr, TypeName, TypeRef, TypeSystem,
+    Value,
+};
+
+/// Parameters of random data generation.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct GenConfig {
+    /// Minimal number of list, set and map elements. Sets and maps may still
+    /// contain fewer elements if some of the generated elements coincide.
+    pub min_len: u16,
+
+    /// Maximal number of list, set and map elements. The maximum is halved
+    /// with each level of structure nesting (but never goes below
+    /// `min_len`), so that recursive types produce data of a bounded size.
+    pub max_len: u16,
+
+    /// Maximal number of characters in character data. Unicode character
+    /// data are additionally limited to 65535 bytes of their UTF-8 encoding.
+    pub max_chars: u16,
+
+    /// Depth of structure nesting after which lists, sets and maps are
+    /// generated empty and optional fields are omitted, such that recursive
+    /// types produce finite data.
+    pub max_depth: u16,
+}
+
+impl Default for GenConfig {
+    fn default() -> Self {
+        GenConfig {
+            min_len: 0,
+            max_len: 8,
+            max_chars: 16,
+            max_depth: 8,
+        }
+    }
+}
+
+/// Errors of random data generation.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum GenError {
+    /// type `{0}` is not defined in the type system
+    UnknownType(TypeName),
+
+    /// type `{0}` requires infinitely-sized data, since it contains itself
+    /// without a list, set, map or optional field in between
+    InfiniteType(TypeName),
+
+    /// generated {0} data do not match the primitive type
+    InvalidPrimitive(PrimitiveType),
+}
+
+/// Generator of random data, which are valid according to the schema.
+#[derive(Debug)]
+pub struct Generator<'ts, R: Rng> {
+    ts: &'ts TypeSystem,
+    config: GenConfig,
+    rng: R,
+    depth: u16,
+}
+
+impl<'ts, R: Rng> Generator<'ts, R> {
+    /// Constructs generator with the default configuration.
+    pub fn new(ts: &'ts TypeSystem, rng: R) -> Self { Self::with(ts, GenConfig::default(), rng) }
+
+    /// Constructs generator with a custom configuration.
+    pub fn with(ts: &'ts TypeSystem, config: GenConfig, rng: R) -> Self {
+        Generator {
+            ts,
+            config,
+            rng,
+            depth: 0,
+        }
+    }
+
+    /// Generates strict encoding of a random instance of type `root`.
+    pub fn generate(&mut self, root: &TypeName) -> Result<Vec<u8>, GenError> {
+        self.generate_value(root).map(|(data, _)| data)
+    }
+
+    /// Generates strict encoding of a random instance of type `root` together
+    /// with its dynamic value, which is the same as produced by decoding the
+    /// data with [`TypeSystem::decode`].
+    pub fn generate_value(&mut self, root: &TypeName) -> Result<(Vec<u8>, Value), GenError> {
+        let mut data = vec![];
+        self.depth = 0;
+        let value = self.named(root, &mut data)?;
+        Ok((data, value))
+    }
+
+    fn is_deep(&self) -> bool { self.depth >= self.config.max_depth }
+
+    fn named(&mut self, name: &TypeName, data: &mut Vec<u8>) -> Result<Value, GenError> {
+        let ty = self.ts.get(name).ok_or_else(|| GenError::UnknownType(name.clone()))?;
+        // Once collections are collapsed, each nesting level must be a different type
+        // unless the type contains itself
+        if self.depth as usize > self.config.max_depth as usize + self.ts.len() as usize {
+            return Err(GenError::InfiniteType(name.clone()));
+        }
+        self.depth += 1;
+        let value = self.structure(ty, data);
+        self.depth -= 1;
+        value
+    }
+
+    fn structure(&mut self, ty: &StructType, data: &mut Vec<u8>) -> Result<Value, GenError> {
+        let mut fields = Vec::with_capacity(ty.len() as usize);
+        for field in ty {
+            if field.optional {
+                if self.is_deep() || self.rng.gen_bool(0.5) {
+                    data.push(0);
+                    fields.push(None);
+                    continue;
+                }
+                data.push(1);
+            }
+            fields.push(Some(self.type_ref(&field.ty, data)?));
+        }
+        Ok(Value::Struct(fields))
+    }
+
+    fn type_ref(&mut self, ty: &TypeRef, data: &mut Vec<u8>) -> Result<Value, GenError> {
+        match ty {
+            TypeRef::InPlace(ty) => self.constr(ty, data, |gen, ty, data| gen.primitive(*ty, data)),
+            TypeRef::NameRef(ty) => self.constr(ty, data, |gen, name, data| gen.named(name, data)),
+            TypeRef::Nested(ty) => {
+                self.constr(ty, data, |gen, item, data| gen.type_ref(item, data))
+            }
+        }
+    }
+
+    fn len(&mut self) -> u16 {
+        if self.is_deep() {
+            return 0;
+        }
+        let max = self.config.max_len >> self.depth.saturating_sub(1).min(15);
+        let max = max.max(self.config.min_len);
+        self.rng.gen_range(self.config.min_len..=max)
+    }
+
+    fn constr<T>(
+        &mut self,
+        ty: &TypeConstr<T>,
+        data: &mut Vec<u8>,
+        mut item: impl FnMut(&mut Self, &T, &mut Vec<u8>) -> Result<Value, GenError>,
+    ) -> Result<Value, GenError>
+    where
+        T: Clone + Ord + Eq + Hash + Debug,
+    {
+        match ty {
+            TypeConstr::Plain(ty) => item(self, ty, data),
+            TypeConstr::Array(len, ty) => {
+                let items = (0..*len).map(|_| item(self, ty, data)).collect::<Result<_, _>>()?;
+                Ok(Value::List(items))
+            }
+            TypeConstr::List(ty) => {
+                let len = self.len();
+                data.extend(len.to_le_bytes());
+                let items = (0..len).map(|_| item(self, ty, data)).collect::<Result<_, _>>()?;
+                Ok(Value::List(items))
+            }
+            TypeConstr::Set(ty) => {
+                let len = self.len();
+                let mut set = BTreeMap::new();
+                for _ in 0..len {
+                    let mut buf = vec![];
+                    let value = item(self, ty, &mut buf)?;
+                    set.insert(buf, value);
+                }
+                data.extend((set.len() as u16).to_le_bytes());
+                let items = set
+                    .into_iter()
+                    .map(|(item_data, value)| {
+                        data.extend(item_data);
+                        value
+                    })
+                    .collect();
+                Ok(Value::List(items))
+            }
+            TypeConstr::Map(key, ty) => {
+                let len = self.len();
+                let mut map = BTreeMap::new();
+                for _ in 0..len {
+                    let mut buf = vec![];
+                    let key = self.key(*key, &mut buf)?;
+                    let mut val_buf = vec![];
+                    let value = item(self, ty, &mut val_buf)?;
+                    map.insert(buf, (key, value, val_buf));
+                }
+                data.extend((map.len() as u16).to_le_bytes());
+                let entries = map
+                    .into_iter()
+                    .map(|(key_data, (key, value, val_data))| {
+                        data.extend(key_data);
+                        data.extend(val_data);
+                        (key, value)
+                    })
+                    .collect();
+                Ok(Value::Map(entries))
+            }
+        }
+    }
+
+    fn key(&mut self, key: KeyType, data: &mut Vec<u8>) -> Result<Value, GenError> {
+        let constr = TypeConstr::from(key);
+        self.constr(&constr, data, |gen, ty, data| gen.primitive(*ty, data))
+    }
+
+    fn primitive(&mut self, ty: PrimitiveType, data: &mut Vec<u8>) -> Result<Value, GenError> {
+        let bytes = match (ty, ty.fixed_size()) {
+            (_, Some(len)) => (0..len).map(|_| self.rng.gen()).collect::<Vec<u8>>(),
+            (PrimitiveType::AsciiChar, _) => {
+                let len = self.rng.gen_range(0..=self.config.max_chars);
+                (0..len).map(|_| self.rng.gen_range(0..0x80)).collect()
+            }
+            _ => {
+                let len = self.rng.gen_range(0..=self.config.max_chars);
+                let mut s = String::new();
+                for _ in 0..len {
+                    let c = self.rng.gen::<char>();
+                    if s.len() + c.len_utf8() > u16::MAX as usize {
+                        break;
+                    }
+                    s.push(c);
+                }
+                s.into_bytes()
+            }
+        };
+        let value = PrimitiveValue::with(ty, bytes).ok_or(GenError::InvalidPrimitive(ty))?;
+        if ty.is_char() {
+            data.extend((value.as_bytes().len() as u16).to_le_bytes());
+        }
+        data.extend(value.as_bytes());
+        Ok(Value::Primitive(value))
+    }
+}
+
+/// Constructs [`proptest`] strategy producing strict encodings of random
+/// instances of type `root` together with their dynamic values.
+/// Available with both `generate` and `proptest` features enabled.
+///
+/// # Panics
+///
+/// Values are generated lazily; the strategy panics if the type system can't
+/// be used to generate data of type `root`.
+#[cfg(feature = "proptest")]
+pub fn strategy(
+    ts: TypeSystem,
+    root: TypeName,
+    config: GenConfig,
+) -> impl proptest::strategy::Strategy<Value = (Vec<u8>, Value)> {
+    use proptest::prelude::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    any::<u64>().prop_map(move |seed| {
+        Generator::with(&ts, config, StdRng::seed_from_u64(seed))
+            .generate_value(&root)
+            .expect("invalid type system")
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::schema::test::{tn, type_system};
+    use crate::{strict_vec, type_system, StructField, Verify};
+
+    #[test]
+    fn generate_valid() {
+        let ts = type_system();
+        for root in ["Transaction", "Meta", "Witness"] {
+            let root = tn(root);
+            let mut gen = Generator::new(&ts, StdRng::seed_from_u64(42));
+            for _ in 0..32 {
+                let (data, value) = gen.generate_value(&root).unwrap();
+                assert!(root.verify(&ts, &mut Cursor::new(&data)));
+                assert_eq!(ts.decode(&root, &data).unwrap(), value);
+            }
+        }
+    }
+
+    #[test]
+    fn generate_recursive() {
+        let ts = type_system![
+            "Tree" :: {
+                StructField::primitive(PrimitiveType::U8),
+                StructField::typed_list("Tree"),
+                StructField { ty: TypeRef::NameRef(TypeConstr::Plain(tn("Tree"))), optional: true },
+            }
+        ];
+        let root = tn("Tree");
+        let config = GenConfig {
+            min_len: 1,
+            max_len: 2,
+            ..default!()
+        };
+        let mut gen = Generator::with(&ts, config, StdRng::seed_from_u64(1));
+        let data = gen.generate(&root).unwrap();
+        assert!(root.verify(&ts, &mut Cursor::new(&data)));
+
+        let ts = type_system![
+            "Loop" :: { StructField::with("Next") },
+            "Next" :: { StructField::with("Loop") }
+        ];
+        let mut gen = Generator::new(&ts, StdRng::seed_from_u64(1));
+        assert!(matches!(gen.generate(&tn("Loop")), Err(GenError::InfiniteType(_))));
+    }
+
+    #[test]
+    fn generate_limits() {
+        let ts = type_system![
+            "Text" :: { StructField::primitive(PrimitiveType::UnicodeChar) },
+            "Tree" :: {
+                StructField::typed_list("Tree"),
+                StructField { ty: TypeRef::NameRef(TypeConstr::Plain(tn("Tree"))), optional: true },
+            }
+        ];
+        let config = GenConfig {
+            min_len: 1,
+            max_chars: u16::MAX,
+            ..default!()
+        };
+        let mut gen = Generator::with(&ts, config, StdRng::seed_from_u64(3));
+        for _ in 0..4 {
+            let data = gen.generate(&tn("Text")).unwrap();
+            assert!(data.len() <= u16::MAX as usize + 2);
+            assert!(tn("Text").verify(&ts, &mut Cursor::new(&data)));
+        }
+
+        for seed in 0..8 {
+            let mut gen = Generator::new(&ts, StdRng::seed_from_u64(seed));
+            let data = gen.generate(&tn("Tree")).unwrap();
+            // With the halved lengths a tree has at most ~1500 nodes, three bytes each
+            assert!(data.len() < 8192);
+            assert!(tn("Tree").verify(&ts, &mut Cursor::new(&data)));
+        }
+    }
+
+    #[test]
+    fn deterministic_seed() {
+        let ts = type_system();
+        let root = tn("Transaction");
+        let first = Generator::new(&ts, StdRng::seed_from_u64(7)).generate(&root).unwrap();
+        let second = Generator::new(&ts, StdRng::seed_from_u64(7)).generate(&root).unwrap();
+        assert_eq!(first, second);
+    }
+
+    #[cfg(feature = "proptest")]
+    proptest::proptest! {
+        #[test]
+        fn strategy_valid((data, value) in strategy(type_system(), tn("Transaction"), default!())) {
+            let root = tn("Transaction");
+            proptest::prop_assert!(root.verify(&type_system(), &mut Cursor::new(&data)));
+            proptest::prop_assert_eq!(type_system().decode(&root, &data).unwrap(), value);
+        }
+    }
+}
@@ -0,0 +1,252 @@
+// Strict encoding schema library, implementing validation and parsing of strict
+// encoded data against the schema.
+//
+// Written in 2022 by
+//     Dr. Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2022 by LNP/BP Standards Association, Switzerland.
+//
+// You should have received a copy of the MIT License along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use crate::{Error, TypeConstr, TypeName, TypeRef, TypeSystem};
+
+/// Parameters of the type dependency graph export.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct DotConfig {
+    /// Root types of the schema. If empty, all types are considered reachable.
+    pub roots: Vec<TypeName>,
+
+    /// Whether to draw only types reachable from the `roots`.
+    pub only_reachable: bool,
+
+    /// Whether to color types and references forming cycles.
+    pub color_cycles: bool,
+
+    /// Whether to highlight types not reachable from the `roots`.
+    pub highlight_unreachable: bool,
+}
+
+/// Edge of the type dependency graph.
+struct Edge<'ts> {
+    from: &'ts TypeName,
+    to: &'ts TypeName,
+    label: String,
+}
+
+impl TypeSystem {
+    /// Generates Graphviz DOT description of the type dependency graph.
+    ///
+    /// Each type is represented by a node, and each structure field referencing
+    /// other type by name — by an edge labeled with the field number followed
+    /// by `?` for optional fields and by the type constructors, starting from
+    /// the outermost one (`*N` for arrays, `list`, `set` and `map{K}`).
+    pub fn to_dot(&self, config: &DotConfig) -> Result<String, Error> {
+        let reachable = if config.roots.is_empty() {
+            self.into_iter().map(|(name, _)| name.clone()).collect()
+        } else {
+            self.reachable(&config.roots)?
+        };
+        let edges = self.edges();
+        let components = if config.color_cycles { components(&edges) } else { empty!() };
+        let cyclic = |from: &TypeName, to: &TypeName| {
+            config.color_cycles && from == to
+                || matches!((components.get(from), components.get(to)), (Some(a), Some(b)) if a == b)
+        };
+        let cycles = edges
+            .iter()
+            .filter(|edge| cyclic(edge.from, edge.to))
+            .flat_map(|edge| [edge.from, edge.to])
+            .collect::<BTreeSet<_>>();
+        let shown = |name: &TypeName| !config.only_reachable || reachable.contains(name);
+
+        let mut dot = String::new();
+        writeln!(dot, "digraph types {{").ok();
+        writeln!(dot, "  node [shape=box];").ok();
+        for (name, ty) in self.into_iter().filter(|(name, _)| shown(name)) {
+            let mut attrs = vec![format!("tooltip=\"{}\"", ty)];
+            if cycles.contains(name) {
+                attrs.push(s!("color=red"));
+            }
+            if config.highlight_unreachable && !reachable.contains(name) {
+                attrs.push(s!("style=dashed"));
+                attrs.push(s!("fontcolor=gray"));
+            }
+            writeln!(dot, "  \"{}\" [{}];", name, attrs.join(", ")).ok();
+        }
+        for edge in edges.iter().filter(|edge| shown(edge.from)) {
+            let color = if cyclic(edge.from, edge.to) { ", color=red" } else { "" };
+            writeln!(
+                dot,
+                "  \"{}\" -> \"{}\" [label=\"{}\"{}];",
+                edge.from, edge.to, edge.label, color
+            )
+            .ok();
+        }
+        writeln!(dot, "}}").ok();
+        Ok(dot)
+    }
+
+    fn edges(&self) -> Vec<Edge<'_>> {
+        let mut edges = vec![];
+        for (from, ty) in self {
+            for (no, field) in ty.into_iter().enumerate() {
+                let to = match field.ty.type_name() {
+                    Some(to) => to,
+                    None => continue,
+                };
+                let optional = if field.optional { "?" } else { "" };
+                let mut label = format!("#{}{}", no, optional);
+                let mut ty = &field.ty;
+                // nested constructors are listed from the outermost one
+                loop {
+                    let (constr, item) = match ty {
+                        TypeRef::NameRef(constr) => (constr.clone().map(|_| ()), None),
+                        TypeRef::Nested(constr) => {
+                            (constr.clone().map(|_| ()), Some(constr.inner()))
+                        }
+                        TypeRef::InPlace(_) => unreachable!("type reference contains a type name"),
+                    };
+                    let _ = match constr {
+                        TypeConstr::Plain(_) => None,
+                        TypeConstr::Array(len, _) => write!(label, " *{}", len).ok(),
+                        TypeConstr::List(_) => write!(label, " list").ok(),
+                        TypeConstr::Set(_) => write!(label, " set").ok(),
+                        TypeConstr::Map(key, _) => write!(label, " map{{{}}}", key).ok(),
+                    };
+                    match item {
+                        Some(item) => ty = item,
+                        None => break,
+                    }
+                }
+                edges.push(Edge { from, to, label });
+            }
+        }
+        edges
+    }
+}
+
+/// Computes strongly connected components of the graph with Tarjan's
+/// algorithm, returning component number for each of the types.
+fn components<'ts>(edges: &[Edge<'ts>]) -> BTreeMap<&'ts TypeName, usize> {
+    struct Tarjan<'ts> {
+        adjacent: BTreeMap<&'ts TypeName, Vec<&'ts TypeName>>,
+        index: BTreeMap<&'ts TypeName, (usize, usize)>,
+        stack: Vec<&'ts TypeName>,
+        components: BTreeMap<&'ts TypeName, usize>,
+        count: usize,
+    }
+
+    impl<'ts> Tarjan<'ts> {
+        fn visit(&mut self, node: &'ts TypeName) -> usize {
+            let no = self.index.len();
+            self.index.insert(node, (no, no));
+            self.stack.push(node);
+            let mut low = no;
+            for next in self.adjacent.get(node).cloned().unwrap_or_default() {
+                match self.index.get(next) {
+                    None => low = low.min(self.visit(next)),
+                    Some((index, _)) if !self.components.contains_key(next) => {
+                        low = low.min(*index)
+                    }
+                    Some(_) => {}
+                }
+            }
+            self.index.insert(node, (no, low));
+            if low == no {
+                while let Some(member) = self.stack.pop() {
+                    self.components.insert(member, self.count);
+                    if member == node {
+                        break;
+                    }
+                }
+                self.count += 1;
+            }
+            low
+        }
+    }
+
+    let mut tarjan = Tarjan {
+        adjacent: empty!(),
+        index: empty!(),
+        stack: vec![],
+        components: empty!(),
+        count: 0,
+    };
+    for edge in edges {
+        tarjan.adjacent.entry(edge.from).or_default().push(edge.to);
+    }
+    let nodes = tarjan.adjacent.keys().copied().collect::<Vec<_>>();
+    for node in nodes {
+        if !tarjan.index.contains_key(node) {
+            tarjan.visit(node);
+        }
+    }
+    tarjan.components
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::schema::test::{tn, type_system};
+    use crate::{strict_vec, type_system, PrimitiveType, StructField};
+
+    #[test]
+    fn fixture() {
+        let dot = type_system().to_dot(&default!()).unwrap();
+        assert!(dot.starts_with("digraph types {\n  node [shape=box];\n"));
+        assert!(dot.contains("  \"Txid\" [tooltip=\"U8*32\"];\n"));
+        assert!(dot.contains("  \"Transaction\" -> \"Input\" [label=\"#1 list\"];\n"));
+        assert!(
+            dot.contains("  \"Meta\" -> \"UnicodeString\" [label=\"#1 map{UnicodeChar[]}\"];\n")
+        );
+        assert!(!dot.contains("red"));
+    }
+
+    #[test]
+    fn cycles() {
+        let ts = type_system![
+            "Tree" :: {
+                StructField::primitive(PrimitiveType::U8),
+                StructField { ty: TypeRef::NameRef(TypeConstr::Set(tn("Node"))), optional: false },
+            },
+            "Node" :: { StructField { ty: TypeRef::NameRef(TypeConstr::Plain(tn("Tree"))), optional: true } },
+            "Leaf" :: { StructField::with("Leaf2") },
+            "Leaf2" :: { StructField::primitive(PrimitiveType::U8) },
+            "Orphan" :: { StructField { ty: TypeRef::NameRef(TypeConstr::Array(2, tn("Leaf"))), optional: false } }
+        ];
+        let mut config = DotConfig {
+            roots: vec![tn("Tree")],
+            color_cycles: true,
+            highlight_unreachable: true,
+            ..default!()
+        };
+        assert_eq!(
+            ts.to_dot(&config).unwrap(),
+            "digraph types {
+  node [shape=box];
+  \"Leaf\" [tooltip=\"Leaf2\", style=dashed, fontcolor=gray];
+  \"Leaf2\" [tooltip=\"U8\", style=dashed, fontcolor=gray];
+  \"Node\" [tooltip=\"Tree?\", color=red];
+  \"Orphan\" [tooltip=\"Leaf*2\", style=dashed, fontcolor=gray];
+  \"Tree\" [tooltip=\"U8, {Node}\", color=red];
+  \"Leaf\" -> \"Leaf2\" [label=\"#0\"];
+  \"Node\" -> \"Tree\" [label=\"#0?\", color=red];
+  \"Orphan\" -> \"Leaf\" [label=\"#0 *2\"];
+  \"Tree\" -> \"Node\" [label=\"#1 set\", color=red];
+}
+"
+        );
+
+        config.only_reachable = true;
+        let dot = ts.to_dot(&config).unwrap();
+        assert!(!dot.contains("Leaf"));
+        assert!(dot.contains("\"Node\" -> \"Tree\""));
+
+        config.roots = vec![tn("Unknown")];
+        assert!(ts.to_dot(&config).is_err());
+    }
+}
@@ -0,0 +1,1052 @@
+// Strict encoding schema library, implementing validation and parsing of strict
+// encoded data against the schema.
+//
+// Written in 2022 by
+//     Dr. Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2022 by LNP/BP Standards Association, Switzerland.
+//
+// You should have received a copy of the MIT License along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! JSON projection of the strict-encoded data, export of type systems into
+//! JSON Schema describing the projection and import of type systems from JSON
+//! Schema.
+//!
+//! The data are projected to JSON in the following way:
+//! - structures are arrays of their fields, where absent optional fields are represented by `null`;
+//! - arrays, lists and sets are arrays of their items;
+//! - maps are arrays of `[key, value]` pairs, ordered by the key encoding;
+//! - integers up to 32 bits are JSON numbers, while wider integers are strings with their decimal
+//!   representation;
+//! - `F32` and `F64` floats are strings with their decimal representation, since JSON numbers can't
+//!   represent infinities and NaNs; other floats are hex strings with their big-endian
+//!   representation;
+//! - character data are strings.
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Debug, Formatter};
+use std::hash::Hash;
+
+use serde_crate::de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde_json::{json, Map, Value as Json};
+
+use crate::{
+    CollectionError, Error, KeyType, PrimitiveType, PrimitiveValue, StrictVec, StructField,
+    StructType, TypeConstr, TypeName, TypeRef, TypeSystem, Value,
+};
+
+/// URI of the JSON Schema dialect used by the exported schemata.
+pub const JSON_SCHEMA_DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// Maximal number of items in lists, sets and maps.
+const MAX_ITEMS: u16 = u16::MAX;
+
+impl PrimitiveType {
+    /// Detects whether the values of the type are projected to JSON numbers.
+    fn is_json_number(self) -> bool {
+        matches!(
+            self,
+            PrimitiveType::U8
+                | PrimitiveType::U16
+                | PrimitiveType::U32
+                | PrimitiveType::I8
+                | PrimitiveType::I16
+                | PrimitiveType::I32
+        )
+    }
+}
+
+impl PrimitiveValue {
+    /// Returns JSON projection of the value.
+    pub fn to_json(&self) -> Json {
+        if let Some(s) = self.as_str() {
+            return json!(s);
+        }
+        match self.as_int() {
+            Some((negative, abs)) if self.ty().is_json_number() => {
+                let abs = abs as i64;
+                json!(if negative { -abs } else { abs })
+            }
+            // Display shows integers wider than 128 bits in hex
+            _ => json!(self.to_decimal().unwrap_or_else(|| self.to_string())),
+        }
+    }
+}
+
+impl Value {
+    /// Returns JSON projection of the value.
+    pub fn to_json(&self) -> Json {
+        match self {
+            Value::Primitive(val) => val.to_json(),
+            Value::Struct(fields) => Json::Array(
+                fields
+                    .iter()
+                    .map(|field| field.as_ref().map(Value::to_json).unwrap_or(Json::Null))
+                    .collect(),
+            ),
+            Value::List(items) => Json::Array(items.iter().map(Value::to_json).collect()),
+            Value::Map(entries) => Json::Array(
+                entries.iter().map(|(key, val)| json!([key.to_json(), val.to_json()])).collect(),
+            ),
+        }
+    }
+}
+
+impl TypeSystem {
+    /// Constructs JSON Schema document describing the JSON projection of the
+    /// data of any of the `roots` types. The document contains definitions of
+    /// all types reachable from the roots under `$defs`. With no roots the
+    /// document has no definitions and accepts any data.
+    ///
+    /// JSON Schema can't express uniqueness of map keys for the array of
+    /// `[key, value]` pairs: `uniqueItems` rejects only fully repeated entries,
+    /// so data with the same key mapped to different values still validate
+    /// against the exported schema.
+    ///
+    /// Data of an alias are projected as a structure with a single field, so
+    /// its definition is a single-item array of the aliased type, described
+    /// as an alias of that type.
+    pub fn to_json_schema(&self, roots: &[TypeName]) -> Result<Json, Error> {
+        let mut defs = Map::new();
+        for name in self.reachable(roots)? {
+            let ty = self.get(&name).expect("reachable types are defined");
+            let mut schema = struct_schema(ty);
+            if let Some(aliased) = self.alias(&name) {
+                schema["description"] = json!(format!("Alias of {}", aliased));
+            }
+            defs.insert(name.to_string(), schema);
+        }
+        let mut schema = Map::new();
+        schema.insert(s!("$schema"), json!(JSON_SCHEMA_DIALECT));
+        match roots {
+            [] => {}
+            [root] => {
+                schema.insert(s!("$ref"), type_name_schema(root));
+            }
+            roots => {
+                let refs = roots.iter().map(|root| json!({ "$ref": type_name_schema(root) }));
+                schema.insert(s!("anyOf"), Json::Array(refs.collect()));
+            }
+        }
+        schema.insert(s!("$defs"), Json::Object(defs));
+        Ok(Json::Object(schema))
+    }
+}
+
+fn type_name_schema(name: &TypeName) -> Json { json!(format!("#/$defs/{}", escape(name))) }
+
+fn tuple_schema(items: Vec<Json>) -> Json {
+    let len = items.len();
+    json!({
+        "type": "array",
+        "prefixItems": items,
+        "items": false,
+        "minItems": len,
+        "maxItems": len,
+    })
+}
+
+fn struct_schema(ty: &StructType) -> Json {
+    let fields = ty
+        .into_iter()
+        .map(|field| {
+            let schema = type_ref_schema(&field.ty);
+            if field.optional {
+                json!({ "anyOf": [{ "type": "null" }, schema] })
+            } else {
+                schema
+            }
+        })
+        .collect();
+    tuple_schema(fields)
+}
+
+fn type_ref_schema(ty: &TypeRef) -> Json {
+    match ty {
+        TypeRef::InPlace(ty) => constr_schema(ty, |ty| primitive_schema(*ty)),
+        TypeRef::NameRef(ty) => constr_schema(ty, |name| json!({ "$ref": type_name_schema(name) })),
+        TypeRef::Nested(ty) => constr_schema(ty, |ty| type_ref_schema(ty)),
+    }
+}
+
+fn constr_schema<T>(ty: &TypeConstr<T>, item: impl Fn(&T) -> Json) -> Json
+where T: Clone + Ord + Eq + Hash + Debug {
+    match ty {
+        TypeConstr::Plain(ty) => item(ty),
+        TypeConstr::Array(len, ty) => array_schema(item(ty), *len, *len),
+        TypeConstr::List(ty) => array_schema(item(ty), 0, MAX_ITEMS),
+        TypeConstr::Set(ty) => {
+            let mut schema = array_schema(item(ty), 0, MAX_ITEMS);
+            schema["uniqueItems"] = json!(true);
+            schema
+        }
+        TypeConstr::Map(key, ty) => {
+            let entry = tuple_schema(vec![key_schema(*key), item(ty)]);
+            let mut schema = array_schema(entry, 0, MAX_ITEMS);
+            // Only excludes repeated entries; keys can't be required to be unique
+            schema["uniqueItems"] = json!(true);
+            schema
+        }
+    }
+}
+
+fn array_schema(items: Json, min: u16, max: u16) -> Json {
+    json!({
+        "type": "array",
+        "items": items,
+        "minItems": min,
+        "maxItems": max,
+    })
+}
+
+fn key_schema(key: KeyType) -> Json {
+    match key {
+        KeyType::Primitive(ty) => primitive_schema(ty),
+        KeyType::Array(len, ty) => array_schema(primitive_schema(ty), len, len),
+        KeyType::List(ty) => array_schema(primitive_schema(ty), 0, MAX_ITEMS),
+    }
+}
+
+fn primitive_schema(ty: PrimitiveType) -> Json {
+    let size = ty.fixed_size().unwrap_or_default() as u32;
+    let mut schema = match ty {
+        PrimitiveType::AsciiChar => {
+            return json!({
+                "type": "string",
+                "pattern": ASCII_PATTERN,
+                "maxLength": MAX_ITEMS,
+            })
+        }
+        PrimitiveType::UnicodeChar => return json!({ "type": "string" }),
+        ty if ty.is_json_number() && ty.is_unsigned() => json!({
+            "type": "integer",
+            "minimum": 0,
+            "maximum": (1u64 << (size * 8)) - 1,
+        }),
+        ty if ty.is_json_number() => json!({
+            "type": "integer",
+            "minimum": -(1i64 << (size * 8 - 1)),
+            "maximum": (1i64 << (size * 8 - 1)) - 1,
+        }),
+        ty if ty.is_unsigned() => json!({ "type": "string", "pattern": "^[0-9]+$" }),
+        ty if ty.is_signed() => json!({ "type": "string", "pattern": "^-?[0-9]+$" }),
+        PrimitiveType::F32 | PrimitiveType::F64 => json!({
+            "type": "string",
+            "pattern": "^(-?[0-9]+(\\.[0-9]+)?|-?inf|NaN)$",
+        }),
+        _ => json!({
+            "type": "string",
+            "pattern": format!("^0x[0-9a-f]{{{}}}$", size * 2),
+        }),
+    };
+    schema["format"] = json!(ty.to_string().to_lowercase());
+    schema
+}
+
+/// Error importing type system from JSON Schema.
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display("{error} at {pointer}")]
+pub struct ImportError {
+    /// JSON pointer to the schema which can't be imported.
+    pub pointer: String,
+    pub error: ImportIssue,
+}
+
+/// Issues with JSON Schema preventing its import.
+#[derive(Clone, Eq, PartialEq, Debug, Display)]
+#[display(doc_comments)]
+pub enum ImportIssue {
+    /// {0} has no strict encoding equivalent
+    Unsupported(&'static str),
+
+    /// schema type is not specified
+    MissingType,
+
+    /// integer without a fixed-width format or with bounds not matching any
+    /// integer type
+    IntegerWidth,
+
+    /// number without a float format
+    FloatWidth,
+
+    /// {0} values are projected to JSON strings and must be described with
+    /// `string` type
+    StringProjection(PrimitiveType),
+
+    /// {0} values are projected to JSON numbers and must be described with
+    /// `integer` type
+    NumberProjection(PrimitiveType),
+
+    /// unknown format `{0}`
+    UnknownFormat(String),
+
+    /// array size bounds {0}..={1} have no strict encoding equivalent
+    ArrayBounds(u64, u64),
+
+    /// set can't have a fixed number of items
+    FixedSizeSet,
+
+    /// map keys must be primitives or arrays of primitives
+    MapKey,
+
+    /// reference `{0}` does not point to a type definition
+    UnknownRef(String),
+
+    /// `{0}` is not a valid type name
+    TypeName(String),
+
+    /// type definition must have at least one field
+    NoFields,
+
+    /// invalid JSON: {0}
+    Json(String),
+
+    /// {0}
+    Schema(Error),
+}
+
+/// Pattern used for ASCII strings in the exported schemata.
+const ASCII_PATTERN: &str = "^[\\u0000-\\u007f]*$";
+
+/// Type of items inside type constructors.
+enum Item {
+    Primitive(PrimitiveType),
+    Name(TypeName),
+    /// Collection, which becomes a nested constructor.
+    Nested(TypeRef),
+}
+
+impl TypeSystem {
+    /// Imports type system from JSON Schema.
+    ///
+    /// Each definition from `$defs` (or `definitions`) becomes a type; the
+    /// root schema also becomes a type if it has a `title`. A definition of
+    /// an object becomes a structure with fields matching the object
+    /// properties in their order, where non-required properties are optional
+    /// fields. A definition of an array with `prefixItems` becomes a structure
+    /// with fields matching the items. Any other definition becomes a
+    /// structure with a single field.
+    ///
+    /// Field types are mapped as follows:
+    /// - `$ref` to a definition becomes a reference to the type;
+    /// - integers require either a `format` naming an integer type up to 64 bits (`u8`, `int32`,
+    ///   `int64` etc) or `minimum` and `maximum` matching the range of an integer type up to 32
+    ///   bits; 64-bit formats are accepted for integers since hand-written schemata commonly use
+    ///   them, although the projection describes such values with strings;
+    /// - strings with a `format` naming a numeric type wider than 32 bits (`i64`, `uint64`,
+    ///   `double` etc) become the type, since wider integers and floats are projected to strings;
+    ///   `integer` schemata with wider formats and `number` schemata are rejected, and so are
+    ///   strings with formats of the narrower integers, which are projected to numbers;
+    /// - strings with `ascii` format or with the pattern used by the exported schemata become ASCII
+    ///   strings; other strings become Unicode strings;
+    /// - arrays with `minItems` equal to `maxItems` become fixed-size arrays; arrays without bounds
+    ///   or with `maxItems` of 65535 become lists, or sets if `uniqueItems` is set;
+    /// - arrays of two-item `prefixItems` tuples become maps from the first tuple item to the
+    ///   second one, and objects with only `additionalProperties` become maps from Unicode strings;
+    /// - `anyOf` with a `null` type option makes the field optional.
+    ///
+    /// Items of arrays may be primitives, references to definitions or
+    /// collections themselves, which become nested constructors; inline
+    /// objects must be moved to definitions.
+    ///
+    /// The schema is taken as JSON text, since [`Json`] objects don't keep
+    /// the order of the properties.
+    pub fn from_json_schema(schema: &str) -> Result<TypeSystem, ImportError> {
+        let json_err = |err: serde_json::Error| issue("", ImportIssue::Json(err.to_string()));
+        let mut orders = BTreeMap::new();
+        let order = MemberOrder {
+            pointer: s!(""),
+            orders: &mut orders,
+        };
+        let mut deserializer = serde_json::Deserializer::from_str(schema);
+        order.deserialize(&mut deserializer).map_err(json_err)?;
+        let schema = serde_json::from_str::<Json>(schema).map_err(json_err)?;
+        let schema = &schema;
+
+        let (defs_key, defs) = match (schema.get("$defs"), schema.get("definitions")) {
+            (Some(defs), _) => ("$defs", defs),
+            (None, Some(defs)) => ("definitions", defs),
+            (None, None) => ("$defs", &Json::Null),
+        };
+        let empty = Map::new();
+        let defs = match defs {
+            Json::Null => &empty,
+            Json::Object(defs) => defs,
+            _ => {
+                return Err(issue(
+                    &format!("/{}", defs_key),
+                    ImportIssue::Unsupported("non-object definitions"),
+                ))
+            }
+        };
+
+        let importer = Importer {
+            prefix: format!("#/{}/", defs_key),
+            defs,
+            orders: &orders,
+        };
+        let mut ts = TypeSystem::new();
+        for (name, def) in defs {
+            let pointer = format!("/{}/{}", defs_key, escape(name));
+            let ty = importer.struct_type(def, &pointer)?;
+            let name = type_name(name, &pointer)?;
+            ts.push(name, ty).map_err(|err| issue(&pointer, ImportIssue::Schema(err)))?;
+        }
+        if let Some(title) = schema.get("title").and_then(Json::as_str) {
+            let ty = importer.struct_type(schema, "")?;
+            let name = type_name(title, "/title")?;
+            ts.push(name, ty).map_err(|err| issue("", ImportIssue::Schema(err)))?;
+        }
+        Ok(ts)
+    }
+}
+
+fn issue(pointer: &str, error: ImportIssue) -> ImportError {
+    ImportError {
+        pointer: if pointer.is_empty() { s!("/") } else { pointer.to_owned() },
+        error,
+    }
+}
+
+/// Records the order of the members of JSON objects under the JSON pointers of
+/// the objects.
+struct MemberOrder<'o> {
+    pointer: String,
+    orders: &'o mut BTreeMap<String, Vec<String>>,
+}
+
+impl<'o> MemberOrder<'o> {
+    fn child(&mut self, token: &str) -> MemberOrder<'_> {
+        MemberOrder {
+            pointer: format!("{}/{}", self.pointer, escape(token)),
+            orders: self.orders,
+        }
+    }
+}
+
+impl<'de, 'o> DeserializeSeed<'de> for MemberOrder<'o> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de, 'o> Visitor<'de> for MemberOrder<'o> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut Formatter) -> fmt::Result { f.write_str("JSON value") }
+
+    fn visit_bool<E: de::Error>(self, _: bool) -> Result<(), E> { Ok(()) }
+
+    fn visit_i64<E: de::Error>(self, _: i64) -> Result<(), E> { Ok(()) }
+
+    fn visit_u64<E: de::Error>(self, _: u64) -> Result<(), E> { Ok(()) }
+
+    fn visit_f64<E: de::Error>(self, _: f64) -> Result<(), E> { Ok(()) }
+
+    fn visit_str<E: de::Error>(self, _: &str) -> Result<(), E> { Ok(()) }
+
+    fn visit_unit<E: de::Error>(self) -> Result<(), E> { Ok(()) }
+
+    fn visit_seq<A: SeqAccess<'de>>(mut self, mut seq: A) -> Result<(), A::Error> {
+        let mut no = 0usize;
+        while seq.next_element_seed(self.child(&no.to_string()))?.is_some() {
+            no += 1;
+        }
+        Ok(())
+    }
+
+    fn visit_map<A: MapAccess<'de>>(mut self, mut map: A) -> Result<(), A::Error> {
+        let mut keys = Vec::<String>::new();
+        while let Some(key) = map.next_key::<String>()? {
+            map.next_value_seed(self.child(&key))?;
+            // the last of the repeated members is used, but at the first place
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+        self.orders.insert(self.pointer, keys);
+        Ok(())
+    }
+}
+
+/// Escapes JSON pointer reference token.
+fn escape(token: &str) -> String { token.replace('~', "~0").replace('/', "~1") }
+
+fn type_name(name: &str, pointer: &str) -> Result<TypeName, ImportError> {
+    TypeName::try_from(name).map_err(|_| issue(pointer, ImportIssue::TypeName(name.to_owned())))
+}
+
+fn format_type(format: &str) -> Option<PrimitiveType> {
+    Some(match format {
+        "uint8" => PrimitiveType::U8,
+        "uint16" => PrimitiveType::U16,
+        "uint32" => PrimitiveType::U32,
+        "uint64" => PrimitiveType::U64,
+        "int8" => PrimitiveType::I8,
+        "int16" => PrimitiveType::I16,
+        "int32" => PrimitiveType::I32,
+        "int64" => PrimitiveType::I64,
+        "float" => PrimitiveType::F32,
+        "double" => PrimitiveType::F64,
+        format => PrimitiveType::ALL
+            .into_iter()
+            .find(|ty| !ty.is_char() && ty.to_string().to_lowercase() == format)?,
+    })
+}
+
+/// Applies type constructor to the item type.
+fn wrap<T>(constr: &TypeConstr<()>, ty: T) -> TypeConstr<T>
+where T: Clone + Ord + Eq + Hash + Debug {
+    match constr {
+        TypeConstr::Plain(_) => TypeConstr::Plain(ty),
+        TypeConstr::Array(len, _) => TypeConstr::Array(*len, ty),
+        TypeConstr::List(_) => TypeConstr::List(ty),
+        TypeConstr::Set(_) => TypeConstr::Set(ty),
+        TypeConstr::Map(key, _) => TypeConstr::Map(*key, ty),
+    }
+}
+
+struct Importer<'json> {
+    prefix: String,
+    defs: &'json Map<String, Json>,
+    /// Order of the object members.
+    orders: &'json BTreeMap<String, Vec<String>>,
+}
+
+impl<'json> Importer<'json> {
+    fn struct_type(&self, schema: &Json, pointer: &str) -> Result<StructType, ImportError> {
+        let properties = schema.get("properties").and_then(Json::as_object);
+        let items = schema.get("prefixItems").and_then(Json::as_array);
+        let fields = match (schema.get("type").and_then(Json::as_str), properties, items) {
+            (Some("object"), Some(properties), _) => {
+                let required = schema.get("required").and_then(Json::as_array);
+                let required = required.map(Vec::as_slice).unwrap_or_default();
+                self.orders
+                    .get(&format!("{}/properties", pointer))
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|name| properties.get_key_value(name))
+                    .map(|(name, prop)| {
+                        let pointer = format!("{}/properties/{}", pointer, escape(name));
+                        let mut field = self.field(prop, &pointer)?;
+                        field.optional |= !required.contains(&json!(name));
+                        Ok(field)
+                    })
+                    .collect::<Result<Vec<_>, _>>()?
+            }
+            (Some("array"), _, Some(items)) => items
+                .iter()
+                .enumerate()
+                .map(|(no, item)| self.field(item, &format!("{}/prefixItems/{}", pointer, no)))
+                .collect::<Result<Vec<_>, _>>()?,
+            _ => vec![self.field(schema, pointer)?],
+        };
+        let fields = StrictVec::try_from(fields).map_err(|err| {
+            issue(pointer, match err {
+                CollectionError::Undersize(_) => ImportIssue::NoFields,
+                CollectionError::Oversize(err) => ImportIssue::Schema(err.into()),
+            })
+        })?;
+        Ok(StructType::from(fields))
+    }
+
+    fn field(&self, schema: &Json, pointer: &str) -> Result<StructField, ImportError> {
+        let null = json!({ "type": "null" });
+        if let Some([first, second]) =
+            schema.get("anyOf").and_then(Json::as_array).map(Vec::as_slice)
+        {
+            let (pos, ty) = match (first == &null, second == &null) {
+                (true, false) => (1, second),
+                (false, true) => (0, first),
+                _ => return Err(issue(pointer, ImportIssue::Unsupported("schema combination"))),
+            };
+            return Ok(StructField {
+                ty: self.type_ref(ty, &format!("{}/anyOf/{}", pointer, pos))?,
+                optional: true,
+            });
+        }
+        Ok(StructField {
+            ty: self.type_ref(schema, pointer)?,
+            optional: false,
+        })
+    }
+
+    fn type_ref(&self, schema: &Json, pointer: &str) -> Result<TypeRef, ImportError> {
+        if let Some(item) = self.reference(schema, pointer)? {
+            return Ok(TypeRef::NameRef(TypeConstr::Plain(item)));
+        }
+        let (constr, item) = match self.schema_type(schema, pointer)? {
+            "array" => self.array(schema, pointer)?,
+            "object" => match schema.get("additionalProperties") {
+                Some(value) if value.is_object() && schema.get("properties").is_none() => {
+                    let pointer = format!("{}/additionalProperties", pointer);
+                    let key = KeyType::Primitive(PrimitiveType::UnicodeChar);
+                    (TypeConstr::Map(key, ()), self.item(value, &pointer)?)
+                }
+                _ => {
+                    return Err(issue(
+                        pointer,
+                        ImportIssue::Unsupported("inline object (it must be moved to definitions)"),
+                    ))
+                }
+            },
+            _ => (TypeConstr::Plain(()), Item::Primitive(self.primitive(schema, pointer)?)),
+        };
+        Ok(match item {
+            Item::Primitive(ty) => TypeRef::InPlace(wrap(&constr, ty)),
+            Item::Name(name) => TypeRef::NameRef(wrap(&constr, name)),
+            Item::Nested(ty) => TypeRef::nested(wrap(&constr, ty)),
+        })
+    }
+
+    fn reference(&self, schema: &Json, pointer: &str) -> Result<Option<TypeName>, ImportError> {
+        let reference = match schema.get("$ref") {
+            None => return Ok(None),
+            Some(reference) => reference,
+        };
+        let pointer = format!("{}/$ref", pointer);
+        let unknown = || {
+            let reference = reference.as_str().map(str::to_owned);
+            issue(&pointer, ImportIssue::UnknownRef(reference.unwrap_or_else(|| s!("non-string"))))
+        };
+        let name = reference
+            .as_str()
+            .and_then(|reference| reference.strip_prefix(&self.prefix))
+            .map(|name| name.replace("~1", "/").replace("~0", "~"))
+            .filter(|name| self.defs.contains_key(name))
+            .ok_or_else(unknown)?;
+        type_name(&name, &pointer).map(Some)
+    }
+
+    fn schema_type<'s>(&self, schema: &'s Json, pointer: &str) -> Result<&'s str, ImportError> {
+        for combinator in ["anyOf", "oneOf", "allOf", "not", "if"] {
+            if schema.get(combinator).is_some() {
+                return Err(issue(pointer, ImportIssue::Unsupported("schema combination")));
+            }
+        }
+        if schema.get("enum").is_some() || schema.get("const").is_some() {
+            return Err(issue(pointer, ImportIssue::Unsupported("enumeration")));
+        }
+        match schema.get("type") {
+            None => Err(issue(pointer, ImportIssue::MissingType)),
+            Some(Json::String(ty)) => match ty.as_str() {
+                "boolean" => Err(issue(pointer, ImportIssue::Unsupported("boolean type"))),
+                "null" => Err(issue(pointer, ImportIssue::Unsupported("null type"))),
+                ty => Ok(ty),
+            },
+            Some(_) => Err(issue(pointer, ImportIssue::Unsupported("multiple types"))),
+        }
+    }
+
+    fn item(&self, schema: &Json, pointer: &str) -> Result<Item, ImportError> {
+        if let Some(name) = self.reference(schema, pointer)? {
+            return Ok(Item::Name(name));
+        }
+        match self.schema_type(schema, pointer)? {
+            "array" | "object" => self.type_ref(schema, pointer).map(Item::Nested),
+            _ => self.primitive(schema, pointer).map(Item::Primitive),
+        }
+    }
+
+    fn array(&self, schema: &Json, pointer: &str) -> Result<(TypeConstr<()>, Item), ImportError> {
+        let items = schema.get("items").filter(|items| items.is_object()).ok_or_else(|| {
+            issue(pointer, ImportIssue::Unsupported("array without items schema"))
+        })?;
+        let items_pointer = format!("{}/items", pointer);
+        let min = schema.get("minItems").and_then(Json::as_u64).unwrap_or_default();
+        let max = schema.get("maxItems").and_then(Json::as_u64);
+        let unique = schema.get("uniqueItems").and_then(Json::as_bool).unwrap_or_default();
+
+        let entry = items.get("prefixItems").and_then(Json::as_array).map(Vec::as_slice);
+        if let Some([key, value]) = entry {
+            if min != 0 || !matches!(max, None | Some(0xFFFF)) {
+                return Err(issue(pointer, ImportIssue::ArrayBounds(min, max.unwrap_or(0xFFFF))));
+            }
+            let key = self.key(key, &format!("{}/prefixItems/0", items_pointer))?;
+            let value = self.item(value, &format!("{}/prefixItems/1", items_pointer))?;
+            return Ok((TypeConstr::Map(key, ()), value));
+        }
+
+        let item = self.item(items, &items_pointer)?;
+        let constr = match (min, max) {
+            (min, Some(max)) if min == max && min <= 0xFFFF => {
+                if unique {
+                    return Err(issue(pointer, ImportIssue::FixedSizeSet));
+                }
+                TypeConstr::Array(min as u16, ())
+            }
+            (0, None | Some(0xFFFF)) if unique => TypeConstr::Set(()),
+            (0, None | Some(0xFFFF)) => TypeConstr::List(()),
+            (min, max) => {
+                return Err(issue(pointer, ImportIssue::ArrayBounds(min, max.unwrap_or(u64::MAX))))
+            }
+        };
+        Ok((constr, item))
+    }
+
+    fn key(&self, schema: &Json, pointer: &str) -> Result<KeyType, ImportError> {
+        if schema.get("type").and_then(Json::as_str) != Some("array") {
+            return match self.item(schema, pointer)? {
+                Item::Primitive(ty) => Ok(KeyType::Primitive(ty)),
+                Item::Name(_) | Item::Nested(_) => Err(issue(pointer, ImportIssue::MapKey)),
+            };
+        }
+        match self.array(schema, pointer)? {
+            (TypeConstr::Array(len, _), Item::Primitive(ty)) => Ok(KeyType::Array(len, ty)),
+            (TypeConstr::List(_), Item::Primitive(ty)) => Ok(KeyType::List(ty)),
+            _ => Err(issue(pointer, ImportIssue::MapKey)),
+        }
+    }
+
+    fn primitive(&self, schema: &Json, pointer: &str) -> Result<PrimitiveType, ImportError> {
+        let format = schema.get("format").and_then(Json::as_str);
+        let format_ty = format.map(|format| {
+            format_type(format)
+                .ok_or_else(|| issue(pointer, ImportIssue::UnknownFormat(format.to_owned())))
+        });
+        match self.schema_type(schema, pointer)? {
+            "integer" => match format_ty {
+                Some(Ok(ty)) if ty.is_json_number() => Ok(ty),
+                Some(Ok(ty @ (PrimitiveType::U64 | PrimitiveType::I64))) => Ok(ty),
+                Some(Ok(ty)) if ty.is_unsigned() || ty.is_signed() => {
+                    Err(issue(pointer, ImportIssue::StringProjection(ty)))
+                }
+                Some(Ok(_)) => Err(issue(pointer, ImportIssue::IntegerWidth)),
+                Some(Err(err)) => Err(err),
+                None => {
+                    let min = schema.get("minimum").and_then(Json::as_i64);
+                    let max = schema.get("maximum").and_then(Json::as_i64);
+                    PrimitiveType::ALL
+                        .into_iter()
+                        .filter(|ty| ty.is_json_number())
+                        .find(|ty| {
+                            let bits = ty.fixed_size().unwrap_or_default() as u32 * 8;
+                            let range = if ty.is_unsigned() {
+                                (0, (1i64 << bits) - 1)
+                            } else {
+                                (-(1i64 << (bits - 1)), (1i64 << (bits - 1)) - 1)
+                            };
+                            (min, max) == (Some(range.0), Some(range.1))
+                        })
+                        .ok_or_else(|| issue(pointer, ImportIssue::IntegerWidth))
+                }
+            },
+            "number" => match format_ty {
+                Some(Ok(ty)) if ty.is_float() => {
+                    Err(issue(pointer, ImportIssue::StringProjection(ty)))
+                }
+                Some(Err(err)) => Err(err),
+                _ => Err(issue(pointer, ImportIssue::FloatWidth)),
+            },
+            "string" => match (format, format_ty) {
+                (Some("ascii"), _) => Ok(PrimitiveType::AsciiChar),
+                (_, Some(Ok(ty))) if ty.is_json_number() => {
+                    Err(issue(pointer, ImportIssue::NumberProjection(ty)))
+                }
+                (_, Some(ty)) => ty,
+                (None, None) if schema.get("pattern") == Some(&json!(ASCII_PATTERN)) => {
+                    Ok(PrimitiveType::AsciiChar)
+                }
+                (_, None) => Ok(PrimitiveType::UnicodeChar),
+            },
+            _ => Err(issue(pointer, ImportIssue::Unsupported("type"))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::schema::test::{nested_type_system, tn, transaction, type_system};
+    use crate::{strict_vec, type_system, StructField};
+
+    #[test]
+    fn value_projection() {
+        let ts = type_system();
+        let value = ts.decode(&tn("Transaction"), transaction()).unwrap();
+        let json = value.to_json();
+        assert_eq!(json[0], json!(1));
+        assert_eq!(json[2], json!([["100000", [[81]]], ["200000", [[]]]]));
+        assert_eq!(json[1][0][2], json!([[[[255]]]]));
+
+        let value =
+            ts.decode(&tn("Meta"), [1, 0, 1, 0, b'x', 1, 0, 1, 0, 1, 0, b'k', 0, 0]).unwrap();
+        assert_eq!(value.to_json(), json!([["x"], [[["k"], [[]]]]]));
+    }
+
+    #[test]
+    fn wide_integer_projection() {
+        // 2^200 + 1 and its bitwise complement, -(2^200 + 2)
+        let mut data = [0u8; 32];
+        data[0] = 1;
+        data[25] = 1;
+        let wide = PrimitiveValue::with(PrimitiveType::U256, data).unwrap();
+        assert!(wide.as_u128().is_none());
+        let decimal = "1606938044258990275541962092341162602522202993782792835301377";
+        assert_eq!(wide.to_json(), json!(decimal));
+        let negative = PrimitiveValue::with(
+            PrimitiveType::I256,
+            data.iter().map(|byte| !byte).collect::<Vec<_>>(),
+        )
+        .unwrap();
+        assert_eq!(
+            negative.to_json(),
+            json!("-1606938044258990275541962092341162602522202993782792835301378")
+        );
+
+        // Parsing the projection back gives the encoded value
+        let mut parsed = [0u8; 32];
+        for digit in decimal.bytes() {
+            let mut carry = (digit - b'0') as u16;
+            for byte in &mut parsed {
+                let cur = *byte as u16 * 10 + carry;
+                *byte = cur as u8;
+                carry = cur >> 8;
+            }
+        }
+        assert_eq!(parsed, data);
+    }
+
+    #[test]
+    fn json_schema() {
+        let ts = type_system();
+        let schema = ts.to_json_schema(&[tn("Output"), tn("Meta")]).unwrap();
+        assert_eq!(
+            schema,
+            json!({
+                "$schema": "https://json-schema.org/draft/2020-12/schema",
+                "anyOf": [{ "$ref": "#/$defs/Output" }, { "$ref": "#/$defs/Meta" }],
+                "$defs": {
+                    "Bytes": {
+                        "type": "array",
+                        "prefixItems": [{
+                            "type": "array",
+                            "items": { "type": "integer", "minimum": 0, "maximum": 255, "format": "u8" },
+                            "minItems": 0,
+                            "maxItems": 65535,
+                        }],
+                        "items": false,
+                        "minItems": 1,
+                        "maxItems": 1,
+                    },
+                    "Meta": {
+                        "type": "array",
+                        "prefixItems": [
+                            {
+                                "type": "array",
+                                "items": {
+                                    "type": "string",
+                                    "pattern": "^[\\u0000-\\u007f]*$",
+                                    "maxLength": 65535
+                                },
+                                "minItems": 0,
+                                "maxItems": 65535,
+                            },
+                            {
+                                "type": "array",
+                                "items": {
+                                    "type": "array",
+                                    "prefixItems": [
+                                        {
+                                            "type": "array",
+                                            "items": { "type": "string" },
+                                            "minItems": 0,
+                                            "maxItems": 65535,
+                                        },
+                                        { "$ref": "#/$defs/UnicodeString" }
+                                    ],
+                                    "items": false,
+                                    "minItems": 2,
+                                    "maxItems": 2,
+                                },
+                                "minItems": 0,
+                                "maxItems": 65535,
+                                "uniqueItems": true,
+                            }
+                        ],
+                        "items": false,
+                        "minItems": 2,
+                        "maxItems": 2,
+                    },
+                    "Output": {
+                        "type": "array",
+                        "prefixItems": [
+                            { "type": "string", "pattern": "^[0-9]+$", "format": "u64" },
+                            { "$ref": "#/$defs/Bytes" }
+                        ],
+                        "items": false,
+                        "minItems": 2,
+                        "maxItems": 2,
+                    },
+                    "UnicodeString": {
+                        "type": "array",
+                        "prefixItems": [{
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "minItems": 0,
+                            "maxItems": 65535,
+                        }],
+                        "items": false,
+                        "minItems": 1,
+                        "maxItems": 1,
+                    }
+                }
+            })
+        );
+        assert_eq!(ts.to_json_schema(&[tn("Absent")]), Err(Error::UnknownType(tn("Absent"))));
+        assert_eq!(
+            ts.to_json_schema(&[]).unwrap(),
+            json!({ "$schema": JSON_SCHEMA_DIALECT, "$defs": {} })
+        );
+    }
+
+    #[test]
+    fn escaped_refs() {
+        let ts = type_system![
+            "Tx/Out" :: { StructField::with("Bytes~1") },
+            "Bytes~1" :: { StructField::bytes() }
+        ];
+        let schema = ts.to_json_schema(&[tn("Tx/Out")]).unwrap();
+        assert_eq!(schema["$ref"], json!("#/$defs/Tx~1Out"));
+        let field = &schema["$defs"]["Tx/Out"]["prefixItems"][0];
+        assert_eq!(field, &json!({ "$ref": "#/$defs/Bytes~01" }));
+        assert_eq!(TypeSystem::from_json_schema(&schema.to_string()).unwrap(), ts);
+    }
+
+    #[test]
+    fn alias_schema() {
+        let mut ts = type_system![
+            "OutPoint" :: { StructField::with("Txid"), StructField::primitive(PrimitiveType::U16) }
+        ];
+        ts.push_alias(tn("Txid"), TypeRef::InPlace(TypeConstr::Array(32, PrimitiveType::U8)))
+            .unwrap();
+        let schema = ts.to_json_schema(&[tn("OutPoint")]).unwrap();
+        assert_eq!(
+            schema["$defs"]["Txid"],
+            json!({
+                "type": "array",
+                "prefixItems": [{
+                    "type": "array",
+                    "items": { "type": "integer", "minimum": 0, "maximum": 255, "format": "u8" },
+                    "minItems": 32,
+                    "maxItems": 32,
+                }],
+                "items": false,
+                "minItems": 1,
+                "maxItems": 1,
+                "description": "Alias of U8*32",
+            })
+        );
+        assert_eq!(
+            schema["$defs"]["OutPoint"],
+            type_system().to_json_schema(&[tn("OutPoint")]).unwrap()["$defs"]["OutPoint"]
+        );
+        let value = ts.decode(&tn("Txid"), [7u8; 32]).unwrap();
+        assert_eq!(value.to_json(), json!([vec![7; 32]]));
+    }
+
+    #[test]
+    fn import_roundtrip() {
+        let ts = type_system();
+        let schema = ts.to_json_schema(&[tn("Transaction"), tn("Meta")]).unwrap();
+        assert_eq!(TypeSystem::from_json_schema(&schema.to_string()).unwrap(), ts);
+
+        let ts = nested_type_system();
+        let schema = ts.to_json_schema(&[tn("Transaction"), tn("Meta")]).unwrap();
+        assert_eq!(TypeSystem::from_json_schema(&schema.to_string()).unwrap(), ts);
+    }
+
+    #[test]
+    fn import_objects() {
+        let schema = r##"{
+            "title": "Order",
+            "type": "object",
+            "properties": {
+                "id": { "type": "string", "format": "int64" },
+                "sequence": { "type": "integer", "format": "uint64" },
+                "amount": { "type": "string", "format": "u128" },
+                "customer": { "$ref": "#/definitions/Customer" },
+                "tags": { "type": "array", "items": { "type": "string" }, "uniqueItems": true },
+                "attributes": { "type": "object", "additionalProperties": { "type": "string", "format": "double" } },
+                "note": { "type": "string", "format": "ascii" }
+            },
+            "required": ["id", "sequence", "amount", "customer", "tags", "attributes"],
+            "definitions": {
+                "Customer": {
+                    "type": "object",
+                    "properties": {
+                        "code": { "type": "integer", "minimum": 0, "maximum": 65535 },
+                        "key": { "type": "array", "items": { "type": "integer", "format": "uint8" }, "minItems": 33, "maxItems": 33 }
+                    },
+                    "required": ["code", "key"]
+                }
+            }
+        }"##;
+        let ts = TypeSystem::from_json_schema(schema).unwrap();
+        assert_eq!(
+            ts.to_string(),
+            "Customer :: U16, U8*33\nOrder :: I64, U64, U128, Customer, {UnicodeChar}, \
+             {UnicodeChar} -> F64, AsciiChar?\n"
+        );
+        assert_eq!(
+            TypeSystem::from_json_schema("{").unwrap_err().to_string(),
+            "invalid JSON: EOF while parsing an object at line 1 column 1 at /"
+        );
+    }
+
+    #[test]
+    fn import_errors() {
+        let import = |field: Json| {
+            let schema = json!({ "$defs": { "Type": { "type": "object", "properties": { "field": field } } } });
+            TypeSystem::from_json_schema(&schema.to_string()).unwrap_err().to_string()
+        };
+        assert_eq!(
+            import(json!({ "type": "boolean" })),
+            "boolean type has no strict encoding equivalent at /$defs/Type/properties/field"
+        );
+        assert_eq!(
+            import(json!({ "type": "integer" })),
+            "integer without a fixed-width format or with bounds not matching any integer type at \
+             /$defs/Type/properties/field"
+        );
+        assert_eq!(
+            import(json!({ "type": "integer", "format": "u128" })),
+            "U128 values are projected to JSON strings and must be described with `string` type at \
+             /$defs/Type/properties/field"
+        );
+        assert_eq!(
+            import(json!({ "type": "string", "format": "u8" })),
+            "U8 values are projected to JSON numbers and must be described with `integer` type at \
+             /$defs/Type/properties/field"
+        );
+        assert_eq!(
+            import(json!({ "type": "number", "format": "double" })).split(" at ").next(),
+            Some(
+                "F64 values are projected to JSON strings and must be described with `string` type"
+            )
+        );
+        assert_eq!(
+            import(json!({ "type": "array", "items": { "type": "string" }, "maxItems": 10 })),
+            "array size bounds 0..=10 have no strict encoding equivalent at \
+             /$defs/Type/properties/field"
+        );
+        assert_eq!(
+            import(json!({ "type": "array", "items": { "type": "object", "properties": {} } })),
+            "inline object (it must be moved to definitions) has no strict encoding equivalent at \
+             /$defs/Type/properties/field/items"
+        );
+        assert_eq!(
+            import(json!({ "$ref": "#/$defs/Absent" })),
+            "reference `#/$defs/Absent` does not point to a type definition at \
+             /$defs/Type/properties/field/$ref"
+        );
+        assert_eq!(
+            import(json!({ "type": "object", "properties": {} })),
+            "inline object (it must be moved to definitions) has no strict encoding equivalent at \
+             /$defs/Type/properties/field"
+        );
+    }
+}
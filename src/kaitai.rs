@@ -0,0 +1,436 @@
+// Strict encoding schema library, implementing validation and parsing of strict
+// encoded data against the schema.
+//
+// Written in 2022 by
+//     Dr. Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2022 by LNP/BP Standards Association, Switzerland.
+//
+// You should have received a copy of the MIT License along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::collections::BTreeMap;
+use std::fmt::{Debug, Write};
+use std::hash::Hash;
+
+use crate::codegen::{snake_case, Names};
+use crate::{Error, KeyType, PrimitiveType, StructType, TypeConstr, TypeName, TypeRef, TypeSystem};
+
+/// Kaitai type used for ASCII character data.
+const ASCII_TYPE: &str = "strict_ascii";
+/// Kaitai type used for Unicode character data.
+const UNICODE_TYPE: &str = "strict_unicode";
+
+/// Type of a Kaitai attribute.
+#[derive(Clone, Eq, PartialEq, Debug)]
+enum Kind {
+    Type(String),
+    Size(u16),
+    Str(&'static str),
+}
+
+/// Kaitai sequence attribute.
+#[derive(Clone, Eq, PartialEq, Debug)]
+struct Attr {
+    id: String,
+    kind: Kind,
+    repeat: Option<String>,
+    cond: Option<String>,
+    doc: Option<String>,
+}
+
+impl Attr {
+    fn with(id: impl ToString, kind: Kind) -> Attr {
+        Attr {
+            id: id.to_string(),
+            kind,
+            repeat: None,
+            cond: None,
+            doc: None,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Generator {
+    types: BTreeMap<String, Vec<Attr>>,
+    /// Kaitai identifiers of the schema types.
+    ids: BTreeMap<TypeName, String>,
+    /// Kaitai identifiers which are already used.
+    names: Names,
+    ascii: Option<String>,
+    unicode: Option<String>,
+}
+
+impl Generator {
+    fn primitive(&mut self, ty: PrimitiveType) -> Kind {
+        let size = ty.fixed_size().unwrap_or_default();
+        let prefix = match ty {
+            PrimitiveType::AsciiChar => {
+                if self.ascii.is_none() {
+                    self.ascii = Some(self.names.fresh(ASCII_TYPE));
+                }
+                return Kind::Type(self.ascii.clone().expect("just set"));
+            }
+            PrimitiveType::UnicodeChar => {
+                if self.unicode.is_none() {
+                    self.unicode = Some(self.names.fresh(UNICODE_TYPE));
+                }
+                return Kind::Type(self.unicode.clone().expect("just set"));
+            }
+            _ if size > 8 => return Kind::Size(size),
+            PrimitiveType::F16 | PrimitiveType::F16b => return Kind::Size(size),
+            ty if ty.is_unsigned() => 'u',
+            ty if ty.is_signed() => 's',
+            _ => 'f',
+        };
+        Kind::Type(format!("{}{}", prefix, size))
+    }
+
+    fn structure(&mut self, name: &str, ty: &StructType) -> Vec<Attr> {
+        let mut attrs = vec![];
+        for (no, field) in ty.into_iter().enumerate() {
+            let id = format!("f{}", no);
+            let cond = if field.optional {
+                let tag = format!("{}_tag", id);
+                attrs.push(Attr::with(&tag, Kind::Type(s!("u1"))));
+                Some(format!("{} == 1", tag))
+            } else {
+                None
+            };
+            let first = attrs.len();
+            self.type_ref(name, &id, &field.ty, &mut attrs);
+            for attr in &mut attrs[first..] {
+                attr.cond = cond.clone();
+            }
+            attrs.last_mut().expect("field attribute").doc = Some(field.to_string());
+        }
+        attrs
+    }
+
+    fn alias(&mut self, name: &str, ty: &TypeRef) -> Vec<Attr> {
+        let mut attrs = vec![];
+        self.type_ref(name, "value", ty, &mut attrs);
+        attrs.last_mut().expect("value attribute").doc = Some(ty.to_string());
+        attrs
+    }
+
+    fn type_ref(&mut self, owner: &str, id: &str, ty: &TypeRef, attrs: &mut Vec<Attr>) {
+        match ty {
+            TypeRef::InPlace(constr) => {
+                let item = |gen: &mut Self, ty: &PrimitiveType| gen.primitive(*ty);
+                self.constr(owner, id, constr, item, attrs)
+            }
+            TypeRef::NameRef(constr) => {
+                let item = |gen: &mut Self, ty: &TypeName| Kind::Type(gen.ids[ty].clone());
+                self.constr(owner, id, constr, item, attrs)
+            }
+            TypeRef::Nested(constr) => {
+                // items which are collections themselves are wrapped into a
+                // separate type, since Kaitai can't repeat repeated attributes
+                let item = |gen: &mut Self, ty: &TypeRef| {
+                    let item = gen.names.fresh(&format!("{}_{}_item", owner, id));
+                    let mut item_attrs = vec![];
+                    gen.type_ref(&item, "value", ty, &mut item_attrs);
+                    gen.types.insert(item.clone(), item_attrs);
+                    Kind::Type(item)
+                };
+                self.constr(owner, id, constr, |gen, ty| item(gen, ty), attrs)
+            }
+        }
+    }
+
+    fn constr<T>(
+        &mut self,
+        owner: &str,
+        id: &str,
+        ty: &TypeConstr<T>,
+        item: impl Fn(&mut Self, &T) -> Kind,
+        attrs: &mut Vec<Attr>,
+    ) where
+        T: Clone + Ord + Eq + Hash + Debug,
+    {
+        let len = format!("{}_len", id);
+        let (kind, repeat) = match ty {
+            TypeConstr::Plain(ty) => (item(self, ty), None),
+            TypeConstr::Array(count, ty) => (item(self, ty), Some(count.to_string())),
+            TypeConstr::List(ty) | TypeConstr::Set(ty) => {
+                attrs.push(Attr::with(&len, Kind::Type(s!("u2"))));
+                (item(self, ty), Some(len))
+            }
+            TypeConstr::Map(key, ty) => {
+                attrs.push(Attr::with(&len, Kind::Type(s!("u2"))));
+                let entry = self.names.fresh(&format!("{}_{}_entry", owner, id));
+                let mut entry_attrs = vec![];
+                self.key(*key, &mut entry_attrs);
+                let value = item(self, ty);
+                entry_attrs.push(Attr::with("value", value));
+                self.types.insert(entry.clone(), entry_attrs);
+                (Kind::Type(entry), Some(len))
+            }
+        };
+        let mut attr = Attr::with(id, kind);
+        attr.repeat = repeat;
+        attrs.push(attr);
+    }
+
+    fn key(&mut self, key: KeyType, attrs: &mut Vec<Attr>) {
+        let constr = TypeConstr::from(key);
+        self.constr("", "key", &constr, |gen, ty| gen.primitive(*ty), attrs);
+    }
+}
+
+/// Returns YAML single-quoted scalar, in which the quotes are doubled.
+fn yaml_quoted(s: &str) -> String { format!("'{}'", s.replace('\'', "''")) }
+
+fn write_seq(ksy: &mut String, indent: &str, attrs: &[Attr]) {
+    writeln!(ksy, "{}seq:", indent).ok();
+    for attr in attrs {
+        writeln!(ksy, "{}  - id: {}", indent, attr.id).ok();
+        match &attr.kind {
+            Kind::Type(ty) => writeln!(ksy, "{}    type: {}", indent, ty),
+            Kind::Size(size) => writeln!(ksy, "{}    size: {}", indent, size),
+            Kind::Str(encoding) => writeln!(
+                ksy,
+                "{0}    type: str\n{0}    size: len\n{0}    encoding: {1}",
+                indent, encoding
+            ),
+        }
+        .ok();
+        if let Some(repeat) = &attr.repeat {
+            writeln!(ksy, "{}    repeat: expr", indent).ok();
+            writeln!(ksy, "{}    repeat-expr: {}", indent, repeat).ok();
+        }
+        if let Some(cond) = &attr.cond {
+            writeln!(ksy, "{}    if: {}", indent, cond).ok();
+        }
+        if let Some(doc) = &attr.doc {
+            writeln!(ksy, "{}    doc: {}", indent, yaml_quoted(doc)).ok();
+        }
+    }
+}
+
+impl TypeSystem {
+    /// Generates Kaitai Struct description (`.ksy` file) of the data of type
+    /// `root`.
+    ///
+    /// The root type becomes the top-level Kaitai type, and all other types
+    /// reachable from it are put into `types` section under their snake-case
+    /// names. Structure fields are named `f0`, `f1` etc; optional fields are
+    /// preceded by `_tag` attributes, and lists, sets and maps by `_len`
+    /// attributes; collection items which are collections themselves are
+    /// represented by separate `_item` types. Character data are represented by `strict_ascii` and
+    /// `strict_unicode` types; primitives not supported by Kaitai are
+    /// represented by raw bytes of the primitive size. Generated types, whose
+    /// names are already used by schema types, get numeric suffixes.
+    ///
+    /// Aliases hold the data of the aliased type in a single `value`
+    /// attribute, like the generated types of the collection items.
+    pub fn to_kaitai(&self, root: &TypeName) -> Result<String, Error> {
+        let mut gen = Generator::default();
+        let reachable = self.reachable([root])?;
+        // Schema types are named first, so only the generated types get suffixes
+        for name in &reachable {
+            let id = gen.names.fresh(&snake_case(name));
+            gen.ids.insert(name.clone(), id);
+        }
+        let mut root_attrs = vec![];
+        for name in &reachable {
+            let ty = self.get(name).expect("reachable types are defined");
+            let id = gen.ids[name].clone();
+            let attrs = match self.alias(name) {
+                Some(aliased) => gen.alias(&id, aliased),
+                None => gen.structure(&id, ty),
+            };
+            if name == root {
+                root_attrs = attrs;
+            } else {
+                gen.types.insert(id, attrs);
+            }
+        }
+        let len = Attr::with("len", Kind::Type(s!("u2")));
+        if let Some(id) = gen.ascii.take() {
+            let value = Attr::with("value", Kind::Str("ASCII"));
+            gen.types.insert(id, vec![len.clone(), value]);
+        }
+        if let Some(id) = gen.unicode.take() {
+            let value = Attr::with("value", Kind::Str("UTF-8"));
+            gen.types.insert(id, vec![len, value]);
+        }
+
+        let mut ksy = String::new();
+        writeln!(ksy, "meta:").ok();
+        writeln!(ksy, "  id: {}", gen.ids[root]).ok();
+        writeln!(ksy, "  title: {}", root).ok();
+        writeln!(ksy, "  endian: le").ok();
+        write_seq(&mut ksy, "", &root_attrs);
+        if !gen.types.is_empty() {
+            writeln!(ksy, "types:").ok();
+        }
+        for (id, attrs) in &gen.types {
+            writeln!(ksy, "  {}:", id).ok();
+            write_seq(&mut ksy, "    ", attrs);
+        }
+        Ok(ksy)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::schema::test::{tn, type_system};
+    use crate::{strict_vec, type_system, StructField};
+
+    #[test]
+    fn meta() {
+        let ksy = type_system().to_kaitai(&tn("Meta")).unwrap();
+        assert_eq!(
+            ksy,
+            "meta:
+  id: meta
+  title: Meta
+  endian: le
+seq:
+  - id: f0_len
+    type: u2
+  - id: f0
+    type: strict_ascii
+    repeat: expr
+    repeat-expr: f0_len
+    doc: 'AsciiChar*'
+  - id: f1_len
+    type: u2
+  - id: f1
+    type: meta_f1_entry
+    repeat: expr
+    repeat-expr: f1_len
+    doc: '{UnicodeChar[]} -> UnicodeString'
+types:
+  meta_f1_entry:
+    seq:
+      - id: key_len
+        type: u2
+      - id: key
+        type: strict_unicode
+        repeat: expr
+        repeat-expr: key_len
+      - id: value
+        type: unicode_string
+  strict_ascii:
+    seq:
+      - id: len
+        type: u2
+      - id: value
+        type: str
+        size: len
+        encoding: ASCII
+  strict_unicode:
+    seq:
+      - id: len
+        type: u2
+      - id: value
+        type: str
+        size: len
+        encoding: UTF-8
+  unicode_string:
+    seq:
+      - id: f0_len
+        type: u2
+      - id: f0
+        type: strict_unicode
+        repeat: expr
+        repeat-expr: f0_len
+        doc: 'UnicodeChar*'
+"
+        );
+    }
+
+    #[test]
+    fn collisions() {
+        let ts = type_system![
+            "Meta" :: {
+                StructField::typed_map(KeyType::Primitive(PrimitiveType::U8), "MetaF1Entry"),
+                StructField::typed_map(KeyType::Primitive(PrimitiveType::U8), "StrictAscii"),
+            },
+            "MetaF1Entry" :: { StructField::ascii_string() },
+            "StrictAscii" :: { StructField::primitive(PrimitiveType::U8) }
+        ];
+        let ksy = ts.to_kaitai(&tn("Meta")).unwrap();
+        assert!(ksy.contains("    type: meta_f0_entry\n"));
+        assert!(ksy.contains("  meta_f1_entry_2:\n"));
+        assert!(ksy.contains("    type: meta_f1_entry_2\n"));
+        assert!(ksy.contains("        type: meta_f1_entry\n"));
+        assert!(ksy.contains("  strict_ascii:\n    seq:\n      - id: f0\n        type: u1\n"));
+        assert!(ksy.contains("  strict_ascii_2:\n    seq:\n      - id: len\n"));
+        assert!(ksy.contains("        type: strict_ascii_2\n"));
+    }
+
+    #[test]
+    fn quoted_docs() {
+        let ts = type_system![
+            "Doc" :: { StructField::typed_list("Bob's") },
+            "Bob's" :: { StructField::primitive(PrimitiveType::U8) }
+        ];
+        let ksy = ts.to_kaitai(&tn("Doc")).unwrap();
+        assert!(ksy.contains("    doc: 'Bob''s*'\n"));
+    }
+
+    #[test]
+    fn aliases() {
+        let mut ts = type_system![
+            "Input" :: { StructField::with("Txid"), StructField::with("Txids") }
+        ];
+        ts.push_alias(tn("Txid"), TypeRef::InPlace(TypeConstr::Array(32, PrimitiveType::U8)))
+            .unwrap();
+        ts.push_alias(tn("Txids"), TypeRef::NameRef(TypeConstr::List(tn("Txid")))).unwrap();
+        let ksy = ts.to_kaitai(&tn("Input")).unwrap();
+        assert!(ksy.ends_with(
+            "types:
+  txid:
+    seq:
+      - id: value
+        type: u1
+        repeat: expr
+        repeat-expr: 32
+        doc: 'U8*32'
+  txids:
+    seq:
+      - id: value_len
+        type: u2
+      - id: value
+        type: txid
+        repeat: expr
+        repeat-expr: value_len
+        doc: 'Txid*'
+"
+        ));
+        assert!(ksy.contains(
+            "  - id: f1
+    type: txids
+"
+        ));
+    }
+
+    #[test]
+    fn primitives() {
+        let ts = type_system![
+            "Prims" :: {
+                StructField::primitive(PrimitiveType::I16),
+                StructField { ty: TypeRef::InPlace(TypeConstr::Array(4, PrimitiveType::F64)), optional: true },
+                StructField::primitive(PrimitiveType::U256),
+                StructField::with("Txid"),
+            },
+            "Txid" :: { StructField { ty: TypeRef::InPlace(TypeConstr::Array(32, PrimitiveType::U8)), optional: false } }
+        ];
+        let ksy = ts.to_kaitai(&tn("Prims")).unwrap();
+        assert!(ksy.contains("  - id: f0\n    type: s2\n"));
+        assert!(ksy.contains(
+            "  - id: f1_tag\n    type: u1\n  - id: f1\n    type: f8\n    repeat: expr\n    \
+             repeat-expr: 4\n    if: f1_tag == 1\n"
+        ));
+        assert!(ksy.contains("  - id: f2\n    size: 32\n"));
+        assert!(ksy.contains("  - id: f3\n    type: txid\n"));
+        assert!(ksy.contains("  txid:\n    seq:\n      - id: f0\n        type: u1\n"));
+        assert!(ts.to_kaitai(&tn("Unknown")).is_err());
+    }
+}
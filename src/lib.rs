@@ -21,8 +21,58 @@ mod schema;
 mod path;
 mod collection;
 mod verify;
+mod value;
+mod cursor;
+mod reader;
+mod writer;
+mod dump;
+#[cfg(feature = "generate")]
+mod generate;
+mod mutate;
+mod codegen;
+mod kaitai;
+mod size;
+mod docs;
+mod typescript;
+mod python;
+mod graph;
+mod wireshark;
+mod c99;
+mod migration;
+mod merge;
+mod rename;
+mod dedup;
+#[cfg(feature = "lock")]
+mod lock;
+pub mod query;
+#[cfg(feature = "json")]
+pub mod conformance;
+#[cfg(feature = "json")]
+pub mod json;
 
 pub use collection::*;
+#[cfg(feature = "json")]
+pub use conformance::Corpus;
+pub use cursor::CursorError;
+pub use docs::{DocConfig, DocFormat, FieldDoc, TypeDoc};
+pub use dump::HexDump;
+#[cfg(all(feature = "generate", feature = "proptest"))]
+pub use generate::strategy;
+#[cfg(feature = "generate")]
+pub use generate::{GenConfig, GenError, Generator};
+pub use graph::DotConfig;
+#[cfg(feature = "lock")]
+pub use lock::{LockError, LockParseError, LockViolation, LockedType, SchemaLock, TypeHash};
+pub use merge::{MergeError, MergeStrategy};
+pub use migration::{FieldSource, Migration, MigrationError, Migrator, TypeMigration};
+pub use mutate::{Mutation, Rule};
 pub use path::*;
+pub use query::Selector;
+pub use reader::SchemaReader;
+pub use rename::TouchedField;
 pub use schema::*;
-pub use verify::Verify;
+pub use size::DataSize;
+pub use value::{DataError, Decode, DecodeError, PrimitiveValue, Value};
+pub use verify::{Verify, Visitor};
+pub use wireshark::DissectorConfig;
+pub use writer::SchemaWriter;
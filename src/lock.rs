@@ -0,0 +1,445 @@
+// Strict encoding schema library, implementing validation and parsing of strict
+// encoded data against the schema.
+//
+// Written in 2022 by
+//     Dr. Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2022 by LNP/BP Standards Association, Switzerland.
+//
+// You should have received a copy of the MIT License along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter, Write};
+use std::str::FromStr;
+
+use bitcoin_hashes::hex::FromHex;
+use bitcoin_hashes::{sha256, Hash, HashEngine};
+use strict_encoding::StrictEncode;
+
+use crate::{Error, TypeConstr, TypeName, TypeRef, TypeSystem};
+
+/// Semantic hash of a type, committing to the encoding of the type and all
+/// the types it references, but not to the type names.
+pub type TypeHash = sha256::Hash;
+
+/// Record of a locked type.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct LockedType {
+    /// Semantic hash of the type.
+    pub hash: TypeHash,
+
+    /// Definitions of the type and all types reachable from it, used to
+    /// report the changes.
+    pub definitions: BTreeMap<TypeName, String>,
+}
+
+/// Lockfile recording semantic hashes of the public types of a type system.
+///
+/// The text representation lists each locked type with its hash, followed by
+/// the indented definitions of the types reachable from it:
+///
+/// ```text
+/// Txid e3dcb5a40b07a20bcfe3d896cd6eb2473ac52cb1f1e81fb74ad1cfb706d59d53
+///     Txid :: U8*32
+/// ```
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct SchemaLock(BTreeMap<TypeName, LockedType>);
+
+impl SchemaLock {
+    /// Returns record of the locked type with the given name.
+    #[inline]
+    pub fn get(&self, name: &TypeName) -> Option<&LockedType> { self.0.get(name) }
+
+    /// Returns number of the locked types.
+    #[inline]
+    pub fn len(&self) -> usize { self.0.len() }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool { self.0.is_empty() }
+}
+
+impl<'me> IntoIterator for &'me SchemaLock {
+    type Item = (&'me TypeName, &'me LockedType);
+    type IntoIter = std::collections::btree_map::Iter<'me, TypeName, LockedType>;
+
+    fn into_iter(self) -> Self::IntoIter { self.0.iter() }
+}
+
+impl Display for SchemaLock {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (name, locked) in &self.0 {
+            writeln!(f, "{} {}", name, locked.hash)?;
+            for (name, definition) in &locked.definitions {
+                writeln!(f, "    {} :: {}", name, definition)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Errors of parsing the lockfile.
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum LockParseError {
+    /// line {0} of the lockfile is not a valid locked type record
+    InvalidRecord(usize),
+
+    /// line {0} of the lockfile is not a valid type definition
+    InvalidDefinition(usize),
+
+    /// type definition at line {0} of the lockfile doesn't follow any locked
+    /// type record
+    OrphanDefinition(usize),
+}
+
+impl FromStr for SchemaLock {
+    type Err = LockParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lock = SchemaLock::default();
+        let mut current = None;
+        for (no, line) in s.lines().enumerate().map(|(no, line)| (no + 1, line)) {
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(definition) = line.strip_prefix("    ") {
+                let locked = current
+                    .as_ref()
+                    .and_then(|name| lock.0.get_mut(name))
+                    .ok_or(LockParseError::OrphanDefinition(no))?;
+                let (name, definition) = definition
+                    .split_once(" :: ")
+                    .and_then(|(name, definition)| {
+                        Some((TypeName::try_from(name).ok()?, definition.to_owned()))
+                    })
+                    .ok_or(LockParseError::InvalidDefinition(no))?;
+                locked.definitions.insert(name, definition);
+            } else {
+                let (name, hash) = line
+                    .split_once(' ')
+                    .and_then(|(name, hash)| {
+                        Some((TypeName::try_from(name).ok()?, TypeHash::from_hex(hash).ok()?))
+                    })
+                    .ok_or(LockParseError::InvalidRecord(no))?;
+                lock.0.insert(name.clone(), LockedType {
+                    hash,
+                    definitions: empty!(),
+                });
+                current = Some(name);
+            }
+        }
+        Ok(lock)
+    }
+}
+
+/// Change of a locked type detected by [`TypeSystem::check_lock`].
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Display)]
+#[display(doc_comments)]
+pub enum LockViolation {
+    /// locked type `{0}` is not defined
+    Removed(TypeName),
+
+    /// encoding of the locked type `{name}` has changed
+    #[display("encoding of the locked type `{name}` has changed:\n{diff}")]
+    Changed { name: TypeName, diff: String },
+}
+
+/// Changes of the locked types reported by [`TypeSystem::check_lock`].
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Error)]
+pub struct LockError(pub Vec<LockViolation>);
+
+impl Display for LockError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for violation in &self.0 {
+            writeln!(f, "{}", violation)?;
+        }
+        Ok(())
+    }
+}
+
+/// Lists definitions of both versions of a type, prefixing removed ones with
+/// `-` and added ones with `+`.
+fn diff(old: &BTreeMap<TypeName, String>, new: &BTreeMap<TypeName, String>) -> String {
+    let mut names = old.keys().chain(new.keys()).collect::<Vec<_>>();
+    names.sort();
+    names.dedup();
+    let mut diff = String::new();
+    for name in names {
+        match (old.get(name), new.get(name)) {
+            (Some(old), Some(new)) if old == new => {
+                let _ = writeln!(diff, "  {} :: {}", name, old);
+            }
+            (old, new) => {
+                if let Some(old) = old {
+                    let _ = writeln!(diff, "- {} :: {}", name, old);
+                }
+                if let Some(new) = new {
+                    let _ = writeln!(diff, "+ {} :: {}", name, new);
+                }
+            }
+        }
+    }
+    diff
+}
+
+/// Computes semantic hashes, caching the ones which don't depend on the
+/// types up the stack.
+struct Hasher<'ts> {
+    ts: &'ts TypeSystem,
+    stack: Vec<&'ts TypeName>,
+    cache: BTreeMap<&'ts TypeName, TypeHash>,
+}
+
+impl<'ts> Hasher<'ts> {
+    /// Returns hash of the type together with the lowest stack depth of the
+    /// types it refers back to.
+    fn hash(&mut self, name: &'ts TypeName) -> Result<(TypeHash, usize), Error> {
+        if let Some(hash) = self.cache.get(name) {
+            return Ok((*hash, usize::MAX));
+        }
+        let ty = self.ts.get(name).ok_or_else(|| Error::UnknownType(name.clone()))?;
+        let depth = self.stack.len();
+        self.stack.push(name);
+        let mut engine = TypeHash::engine();
+        let mut lowest = usize::MAX;
+        for field in ty {
+            engine.input(&[field.optional as u8]);
+            lowest = lowest.min(self.input_ref(&mut engine, &field.ty, depth)?);
+        }
+        self.stack.pop();
+        let hash = TypeHash::from_engine(engine);
+        if lowest >= depth {
+            self.cache.insert(name, hash);
+        }
+        Ok((hash, lowest))
+    }
+
+    /// Commits to the type reference, returning the lowest stack depth of the
+    /// types it refers back to.
+    fn input_ref(
+        &mut self,
+        engine: &mut sha256::HashEngine,
+        ty: &'ts TypeRef,
+        depth: usize,
+    ) -> Result<usize, Error> {
+        match ty {
+            TypeRef::InPlace(constr) => {
+                engine.input(&[0]);
+                engine.input(&constr.strict_serialize().expect("in-memory encoding"));
+                Ok(usize::MAX)
+            }
+            TypeRef::NameRef(constr) => {
+                engine.input(&[1]);
+                input_constr(engine, constr);
+                let target = constr.type_name();
+                // recursive references are committed to by their distance up the stack
+                if let Some(pos) = self.stack.iter().position(|name| *name == target) {
+                    engine.input(&[1]);
+                    engine.input(&((depth - pos) as u16).to_le_bytes());
+                    Ok(pos)
+                } else {
+                    let (hash, depth) = self.hash(target)?;
+                    engine.input(&[0]);
+                    engine.input(&hash[..]);
+                    Ok(depth)
+                }
+            }
+            TypeRef::Nested(constr) => {
+                engine.input(&[2]);
+                input_constr(engine, constr);
+                self.input_ref(engine, constr.inner(), depth)
+            }
+        }
+    }
+}
+
+/// Commits to the constructor, leaving out the type of its items.
+fn input_constr<T>(engine: &mut sha256::HashEngine, constr: &TypeConstr<T>)
+where T: Clone + Ord + Eq + std::hash::Hash + fmt::Debug {
+    match constr {
+        TypeConstr::Plain(_) => engine.input(&[0x00]),
+        TypeConstr::Array(len, _) => {
+            engine.input(&[0x10]);
+            engine.input(&len.to_le_bytes());
+        }
+        TypeConstr::List(_) => engine.input(&[0x11]),
+        TypeConstr::Set(_) => engine.input(&[0x12]),
+        TypeConstr::Map(key, _) => {
+            engine.input(&[0x13]);
+            engine.input(&key.strict_serialize().expect("in-memory encoding"));
+        }
+    }
+}
+
+impl TypeSystem {
+    /// Computes semantic hash of the type, which changes whenever encoding of
+    /// the type or any type reachable from it changes, but not when the
+    /// types are renamed.
+    pub fn semantic_hash(&self, name: &TypeName) -> Result<TypeHash, Error> {
+        Hasher {
+            ts: self,
+            stack: empty!(),
+            cache: empty!(),
+        }
+        .hash(name)
+        .map(|(hash, _)| hash)
+    }
+
+    /// Locks the public types, recording their semantic hashes.
+    pub fn lock<'a>(
+        &self,
+        public: impl IntoIterator<Item = &'a TypeName>,
+    ) -> Result<SchemaLock, Error> {
+        let mut lock = SchemaLock::default();
+        for name in public {
+            lock.0.insert(name.clone(), LockedType {
+                hash: self.semantic_hash(name)?,
+                definitions: self.definitions(name),
+            });
+        }
+        Ok(lock)
+    }
+
+    /// Checks that the encoding of the locked types has not changed, reporting
+    /// all changed types with the diff of their definitions. Failure can be
+    /// reported from a test with
+    /// `ts.check_lock(&lock).unwrap_or_else(|err| panic!("{}", err))`.
+    pub fn check_lock(&self, lock: &SchemaLock) -> Result<(), LockError> {
+        let mut violations = vec![];
+        for (name, locked) in lock {
+            if self.get(name).is_none() {
+                violations.push(LockViolation::Removed(name.clone()));
+                continue;
+            }
+            // types referencing undefined types can't be hashed and are
+            // always reported as changed
+            if self.semantic_hash(name).ok() == Some(locked.hash) {
+                continue;
+            }
+            violations.push(LockViolation::Changed {
+                name: name.clone(),
+                diff: diff(&locked.definitions, &self.definitions(name)),
+            });
+        }
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(LockError(violations))
+        }
+    }
+
+    /// Returns definitions of the type and all defined types reachable from
+    /// it.
+    fn definitions(&self, name: &TypeName) -> BTreeMap<TypeName, String> {
+        let mut definitions = BTreeMap::new();
+        let mut queue = vec![name];
+        while let Some(name) = queue.pop() {
+            let ty = match self.get(name) {
+                Some(ty) if !definitions.contains_key(name) => ty,
+                _ => continue,
+            };
+            definitions.insert(name.clone(), ty.to_string());
+            queue.extend(ty.into_iter().filter_map(|field| field.ty.type_name()));
+        }
+        definitions
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::schema::test::{nested_type_system, tn, type_system};
+    use crate::{strict_vec, type_system, PrimitiveType, StructField};
+
+    #[test]
+    fn roundtrip() {
+        let ts = type_system();
+        let lock = ts.lock([&tn("Transaction"), &tn("Txid")]).unwrap();
+        assert_eq!(lock.len(), 2);
+        assert_eq!(lock.get(&tn("Txid")).unwrap().definitions, bmap! {
+            tn("Txid") => s!("U8*32")
+        });
+        assert_eq!(lock.to_string().parse::<SchemaLock>(), Ok(lock.clone()));
+        ts.check_lock(&lock).unwrap();
+
+        let ts = nested_type_system();
+        let lock = ts.lock([&tn("Input")]).unwrap();
+        assert_eq!(
+            lock.get(&tn("Input")).unwrap().definitions[&tn("Input")],
+            "OutPoint, Bytes, (U8*)*"
+        );
+        assert_eq!(lock.to_string().parse::<SchemaLock>(), Ok(lock.clone()));
+        ts.check_lock(&lock).unwrap();
+
+        assert_eq!("Txid 00".parse::<SchemaLock>(), Err(LockParseError::InvalidRecord(1)));
+        assert_eq!(
+            "\n    Txid :: U8*32".parse::<SchemaLock>(),
+            Err(LockParseError::OrphanDefinition(2))
+        );
+    }
+
+    #[test]
+    fn semantic() {
+        let ts = type_system();
+        let hash = ts.semantic_hash(&tn("Transaction")).unwrap();
+        let mut renamed = ts.clone();
+        renamed.rename(&tn("Bytes"), tn("Script")).unwrap();
+        assert_eq!(renamed.semantic_hash(&tn("Transaction")).unwrap(), hash);
+        assert_ne!(ts.semantic_hash(&tn("Input")).unwrap(), hash);
+
+        let chain = type_system![
+            "Chain" :: {
+                StructField::primitive(PrimitiveType::U8),
+                StructField::optional(TypeRef::NameRef(TypeConstr::Plain(tn("Chain"))))
+            },
+            "List" :: {
+                StructField::primitive(PrimitiveType::U8),
+                StructField::optional(TypeRef::NameRef(TypeConstr::Plain(tn("List"))))
+            }
+        ];
+        assert_eq!(
+            chain.semantic_hash(&tn("Chain")).unwrap(),
+            chain.semantic_hash(&tn("List")).unwrap()
+        );
+        assert_eq!(chain.semantic_hash(&tn("Block")), Err(Error::UnknownType(tn("Block"))));
+    }
+
+    #[test]
+    fn check() {
+        let lock = type_system().lock([&tn("Transaction"), &tn("Meta")]).unwrap();
+        let mut ts = type_system();
+        ts.rename(&tn("Bytes"), tn("Script")).unwrap();
+        ts.check_lock(&lock).unwrap();
+
+        let mut ts = type_system().extract([&tn("Transaction")]).unwrap().0;
+        ts.rename(&tn("Output"), tn("TxOut")).unwrap();
+        let mut changed = TypeSystem::new();
+        for (name, ty) in &ts {
+            if name == &tn("TxOut") {
+                let ty = type_system![
+                    "TxOut" :: { StructField::primitive(PrimitiveType::U32), StructField::with("Bytes") }
+                ];
+                changed.push(name.clone(), ty.get(name).unwrap().clone()).unwrap();
+            } else {
+                changed.push(name.clone(), ty.clone()).unwrap();
+            }
+        }
+        let err = changed.check_lock(&lock).unwrap_err();
+        assert_eq!(err.0[0], LockViolation::Removed(tn("Meta")));
+        assert_eq!(
+            err.0[1].to_string(),
+            "encoding of the locked type `Transaction` has changed:
+  Bytes :: U8*
+  Input :: OutPoint, Bytes, Witness
+  OutPoint :: Txid, U16
+- Output :: U64, Bytes
+- Transaction :: U32, Input*, Output*, U32
++ Transaction :: U32, Input*, TxOut*, U32
++ TxOut :: U32, Bytes
+  Txid :: U8*32
+  Witness :: Bytes*
+"
+        );
+    }
+}
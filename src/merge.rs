@@ -0,0 +1,280 @@
+// Strict encoding schema library, implementing validation and parsing of strict
+// encoded data against the schema.
+//
+// Written in 2022 by
+//     Dr. Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2022 by LNP/BP Standards Association, Switzerland.
+//
+// You should have received a copy of the MIT License along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::collections::BTreeMap;
+
+use crate::{Error, StructType, TypeName, TypeSystem};
+
+/// Strategy of resolving conflicting definitions of the same type name when
+/// merging type systems.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub enum MergeStrategy {
+    /// Conflicts are not resolved and are reported as errors.
+    Fail,
+
+    /// Conflicting types of the merged type system are renamed according to
+    /// the map; conflicts for the types absent in the map are reported as
+    /// errors.
+    Rename(BTreeMap<TypeName, TypeName>),
+
+    /// Names of the conflicting types of the merged type system are
+    /// prefixed with the given string.
+    Prefix(String),
+}
+
+impl Default for MergeStrategy {
+    fn default() -> Self { MergeStrategy::Fail }
+}
+
+/// Errors of merging type systems.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum MergeError {
+    /// type `{name}` is defined both as `{ours}` and as `{theirs}`
+    Conflict {
+        name: TypeName,
+        ours: StructType,
+        theirs: StructType,
+    },
+
+    /// type `{from}` can't be renamed into `{into}`, which is not a valid type
+    /// name
+    InvalidName { from: TypeName, into: String },
+
+    /// types `{from}` and `{other}` are both renamed into `{into}`
+    Collision {
+        from: TypeName,
+        other: TypeName,
+        into: TypeName,
+    },
+
+    /// {0}
+    #[display(inner)]
+    Schema(Error),
+}
+
+impl TypeSystem {
+    /// Adds all types of the `other` type system, failing if some type is
+    /// defined in both type systems differently.
+    pub fn merge(&mut self, other: &TypeSystem) -> Result<(), Vec<MergeError>> {
+        self.merge_with(other, &MergeStrategy::Fail).map(|_| ())
+    }
+
+    /// Adds all types of the `other` type system, resolving conflicts with the
+    /// given strategy. Types defined identically in both type systems, both as
+    /// aliases or both as structures, are merged into a single type.
+    ///
+    /// Renaming a conflicting type of `other` updates references to it from
+    /// the other types of `other`, which may produce new conflicts; these are
+    /// resolved by the same strategy. Returns map from the original names of
+    /// the renamed types of `other` to their new names. On failure, reports
+    /// all detected errors and leaves the type system unchanged.
+    pub fn merge_with(
+        &mut self,
+        other: &TypeSystem,
+        strategy: &MergeStrategy,
+    ) -> Result<BTreeMap<TypeName, TypeName>, Vec<MergeError>> {
+        let mut renames = BTreeMap::<TypeName, TypeName>::new();
+        let renamed = loop {
+            let rename = |name: &TypeName| renames.get(name).unwrap_or(name).clone();
+            let renamed = other
+                .into_iter()
+                .map(|(name, ty)| (name, rename(name), ty.map_refs(rename)))
+                .collect::<Vec<_>>();
+
+            let mut errors = vec![];
+            let mut resolved = BTreeMap::new();
+            for (from, name, theirs) in &renamed {
+                // an alias and a structure with the same single field are
+                // different types
+                let ours = match self.get(name) {
+                    Some(ours) if ours != theirs || self.is_alias(name) != other.is_alias(from) => {
+                        ours
+                    }
+                    _ => continue,
+                };
+                let conflict = || MergeError::Conflict {
+                    name: name.clone(),
+                    ours: ours.clone(),
+                    theirs: theirs.clone(),
+                };
+                match strategy {
+                    _ if renames.contains_key(*from) => errors.push(conflict()),
+                    MergeStrategy::Fail => errors.push(conflict()),
+                    MergeStrategy::Rename(map) => match map.get(*from) {
+                        Some(into) => {
+                            resolved.insert((*from).clone(), into.clone());
+                        }
+                        None => errors.push(conflict()),
+                    },
+                    MergeStrategy::Prefix(prefix) => {
+                        let into = format!("{}{}", prefix, from);
+                        match TypeName::try_from(into.clone()) {
+                            Ok(into) => {
+                                resolved.insert((*from).clone(), into);
+                            }
+                            Err(_) => errors.push(MergeError::InvalidName {
+                                from: (*from).clone(),
+                                into,
+                            }),
+                        }
+                    }
+                }
+            }
+            if !errors.is_empty() {
+                return Err(errors);
+            }
+            if resolved.is_empty() {
+                break renamed;
+            }
+            renames.extend(resolved);
+        };
+
+        let mut names = BTreeMap::new();
+        let mut errors = vec![];
+        for (from, name, _) in &renamed {
+            if let Some(other) = names.insert(name, *from) {
+                errors.push(MergeError::Collision {
+                    from: other.clone(),
+                    other: (*from).clone(),
+                    into: name.clone(),
+                });
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        let mut merged = self.clone();
+        for (from, name, ty) in renamed {
+            if merged.get(&name).is_none() {
+                merged
+                    .push_decl(name, ty, other.is_alias(from))
+                    .map_err(|err| vec![MergeError::Schema(err)])?;
+            }
+        }
+        *self = merged;
+        Ok(renames)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::schema::test::{tn, type_system};
+    use crate::{strict_vec, type_system, PrimitiveType, StructField, TypeConstr, TypeRef};
+
+    fn other() -> TypeSystem {
+        type_system![
+            "Txid" :: { StructField::array(PrimitiveType::U8, 32) },
+            "Bytes" :: { StructField::list(PrimitiveType::U16) },
+            "Output" :: { StructField::primitive(PrimitiveType::U64), StructField::with("Bytes") },
+            "Block" :: { StructField::typed_list("Output"), StructField::with("Txid") }
+        ]
+    }
+
+    #[test]
+    fn merge_identical() {
+        let mut ts = type_system();
+        ts.merge(&type_system()).unwrap();
+        assert_eq!(ts, type_system());
+
+        let mut ts = type_system();
+        let extra = type_system![
+            "Txid" :: { StructField::array(PrimitiveType::U8, 32) },
+            "Header" :: { StructField::with("Txid"), StructField::primitive(PrimitiveType::U32) }
+        ];
+        ts.merge(&extra).unwrap();
+        assert_eq!(ts.len(), type_system().len() + 1);
+        assert_eq!(ts.get(&tn("Header")), extra.get(&tn("Header")));
+    }
+
+    #[test]
+    fn conflicts() {
+        let mut ts = type_system();
+        assert_eq!(ts.merge(&other()).unwrap_err(), vec![MergeError::Conflict {
+            name: tn("Bytes"),
+            ours: type_system().get(&tn("Bytes")).unwrap().clone(),
+            theirs: other().get(&tn("Bytes")).unwrap().clone(),
+        }]);
+        assert_eq!(ts, type_system());
+
+        let strategy = MergeStrategy::Prefix(s!("TooLongPrefixForTheTypeNames"));
+        assert_eq!(
+            ts.merge_with(&other(), &strategy).unwrap_err()[0].to_string(),
+            "type `Bytes` can't be renamed into `TooLongPrefixForTheTypeNamesBytes`, which is not \
+             a valid type name"
+        );
+
+        let strategy = MergeStrategy::Rename(bmap! { tn("Bytes") => tn("Witness") });
+        assert_eq!(
+            ts.merge_with(&other(), &strategy).unwrap_err()[0].to_string(),
+            "type `Witness` is defined both as `Bytes*` and as `U16*`"
+        );
+        assert_eq!(ts, type_system());
+    }
+
+    #[test]
+    fn alias_conflicts() {
+        let mut ts = type_system();
+        let mut aliases = TypeSystem::new();
+        let txid = TypeRef::InPlace(TypeConstr::Array(32, PrimitiveType::U8));
+        aliases.push_alias(tn("Txid"), txid).unwrap();
+        assert_eq!(ts.merge(&aliases).unwrap_err(), vec![MergeError::Conflict {
+            name: tn("Txid"),
+            ours: type_system().get(&tn("Txid")).unwrap().clone(),
+            theirs: aliases.get(&tn("Txid")).unwrap().clone(),
+        }]);
+        assert_eq!(ts, type_system());
+
+        let strategy = MergeStrategy::Prefix(s!("Alias"));
+        ts.merge_with(&aliases, &strategy).unwrap();
+        assert!(ts.is_alias(&tn("AliasTxid")));
+        assert!(!ts.is_alias(&tn("Txid")));
+
+        let mut merged = aliases.clone();
+        merged.merge(&aliases).unwrap();
+        assert_eq!(merged, aliases);
+    }
+
+    #[test]
+    fn resolve() {
+        let mut ts = type_system();
+        let renames = ts.merge_with(&other(), &MergeStrategy::Prefix(s!("Ext"))).unwrap();
+        // renaming `Bytes` changes `Output`, which conflicts with the own one
+        assert_eq!(
+            renames,
+            bmap! { tn("Bytes") => tn("ExtBytes"), tn("Output") => tn("ExtOutput") }
+        );
+        assert_eq!(ts.len(), type_system().len() + 3);
+        assert_eq!(ts.get(&tn("ExtOutput")).unwrap().to_string(), "U64, ExtBytes");
+        assert_eq!(ts.get(&tn("Block")).unwrap().to_string(), "ExtOutput*, Txid");
+        ts.check().unwrap();
+
+        let mut ts = type_system();
+        let strategy = MergeStrategy::Rename(bmap! { tn("Bytes") => tn("Words") });
+        assert_eq!(ts.merge_with(&other(), &strategy).unwrap_err(), vec![MergeError::Conflict {
+            name: tn("Output"),
+            ours: type_system().get(&tn("Output")).unwrap().clone(),
+            theirs: other().get(&tn("Output")).unwrap().map_refs(|_| tn("Words")),
+        }]);
+
+        let strategy = MergeStrategy::Rename(bmap! {
+            tn("Bytes") => tn("Words"),
+            tn("Output") => tn("Block")
+        });
+        assert_eq!(ts.merge_with(&other(), &strategy).unwrap_err(), vec![MergeError::Collision {
+            from: tn("Block"),
+            other: tn("Output"),
+            into: tn("Block"),
+        }]);
+    }
+}
@@ -0,0 +1,568 @@
+// Strict encoding schema library, implementing validation and parsing of strict
+// encoded data against the schema.
+//
+// Written in 2022 by
+//     Dr. Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2022 by LNP/BP Standards Association, Switzerland.
+//
+// You should have received a copy of the MIT License along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Debug;
+use std::hash::Hash;
+
+use crate::{
+    CursorError, DecodeError, PrimitiveType, PrimitiveValue, StructField, StructType, TypeConstr,
+    TypeName, TypeRef, TypeSystem, Value,
+};
+
+/// Source of a structure field value in the migrated data.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub enum FieldSource {
+    /// Value of the field with the given number in the old structure. The
+    /// value is converted when the field was made optional, when its
+    /// primitive types were widened, or when the types it references were
+    /// migrated.
+    Field(u16),
+
+    /// Value for a field absent in the old structure; `None` stands for the
+    /// absent value of an optional field.
+    Default(Option<Value>),
+}
+
+/// Migration of a structure type.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct TypeMigration {
+    /// Name of the type in the old schema.
+    pub from: TypeName,
+
+    /// Sources for each of the fields of the type in the new schema. Fields
+    /// of the old type which are not used as a source are dropped.
+    pub fields: Vec<FieldSource>,
+}
+
+/// Description of the changes between two versions of a schema, which
+/// allows conversion of the data encoded with the old schema into the new
+/// one.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct Migration {
+    /// Root type of the data in the old schema.
+    pub old_root: TypeName,
+
+    /// Root type of the data in the new schema.
+    pub new_root: TypeName,
+
+    /// Migrations of the types, indexed by the type names in the new schema.
+    /// Types which are not listed here are taken from the old schema type
+    /// with the same name, field by field.
+    pub types: BTreeMap<TypeName, TypeMigration>,
+}
+
+impl Migration {
+    /// Constructs migration of the data between the root types, in which all
+    /// types are taken from the old schema unchanged.
+    pub fn new(old_root: TypeName, new_root: TypeName) -> Self {
+        Migration {
+            old_root,
+            new_root,
+            types: empty!(),
+        }
+    }
+
+    fn type_migration(&self, name: &TypeName, new: &StructType) -> TypeMigration {
+        self.types.get(name).cloned().unwrap_or_else(|| TypeMigration {
+            from: name.clone(),
+            fields: (0..new.len()).map(FieldSource::Field).collect(),
+        })
+    }
+}
+
+/// Errors of schema migration.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum MigrationError {
+    /// type `{0}` is not defined in the old schema
+    UnknownOldType(TypeName),
+
+    /// type `{0}` is not defined in the new schema
+    UnknownNewType(TypeName),
+
+    /// type `{ty}` is migrated from `{from}`, but is used in place of `{found}`
+    SourceMismatch {
+        ty: TypeName,
+        from: TypeName,
+        found: TypeName,
+    },
+
+    /// migration of type `{ty}` lists {listed} fields, while the type has {len}
+    /// fields
+    FieldCount {
+        ty: TypeName,
+        listed: usize,
+        len: u16,
+    },
+
+    /// field {field} of type `{ty}` is taken from field {from_field} absent in
+    /// the old type `{from}`
+    NoSourceField {
+        ty: TypeName,
+        field: u16,
+        from: TypeName,
+        from_field: u16,
+    },
+
+    /// field {field} of type `{ty}` can't be converted from `{from}` into
+    /// `{into}`
+    Incompatible {
+        ty: TypeName,
+        field: u16,
+        from: StructField,
+        into: StructField,
+    },
+
+    /// required field {field} of type `{ty}` has no default value
+    NoDefault { ty: TypeName, field: u16 },
+
+    /// old data are invalid: {0}
+    Decode(DecodeError),
+
+    /// migrated data can't be encoded with the new schema: {0}
+    Encode(CursorError),
+
+    /// migrated data fail verification against the new schema: {0}
+    Verify(DecodeError),
+}
+
+/// Engine converting data encoded with an old schema into a new one
+/// according to a [`Migration`].
+#[derive(Debug)]
+pub struct Migrator<'a> {
+    old: &'a TypeSystem,
+    new: &'a TypeSystem,
+    migration: &'a Migration,
+}
+
+impl<'a> Migrator<'a> {
+    /// Constructs migration engine, checking that the migration is complete
+    /// and all fields of the types reachable from the new root can be
+    /// converted.
+    pub fn new(
+        old: &'a TypeSystem,
+        new: &'a TypeSystem,
+        migration: &'a Migration,
+    ) -> Result<Self, MigrationError> {
+        let migrator = Migrator {
+            old,
+            new,
+            migration,
+        };
+        let mut checked = BTreeSet::new();
+        migrator.check(&migration.new_root, &migration.old_root, &mut checked)?;
+        Ok(migrator)
+    }
+
+    /// Converts data encoded as an instance of the old root type into the
+    /// encoding of the new root type, verifying the result against the new
+    /// schema.
+    pub fn migrate(&self, data: impl AsRef<[u8]>) -> Result<Vec<u8>, MigrationError> {
+        let value =
+            self.old.decode(&self.migration.old_root, data).map_err(MigrationError::Decode)?;
+        let value = self.migrate_value(value)?;
+        let data =
+            self.new.encode(&self.migration.new_root, &value).map_err(MigrationError::Encode)?;
+        self.new.decode(&self.migration.new_root, &data).map_err(MigrationError::Verify)?;
+        Ok(data)
+    }
+
+    /// Converts dynamic value of the old root type into the value of the new
+    /// root type.
+    pub fn migrate_value(&self, value: Value) -> Result<Value, MigrationError> {
+        self.structure(&self.migration.new_root, value)
+    }
+
+    fn get_types(
+        &self,
+        name: &TypeName,
+    ) -> Result<(&'a StructType, TypeMigration, &'a StructType), MigrationError> {
+        let new = self.new.get(name).ok_or_else(|| MigrationError::UnknownNewType(name.clone()))?;
+        let migration = self.migration.type_migration(name, new);
+        let old = self
+            .old
+            .get(&migration.from)
+            .ok_or_else(|| MigrationError::UnknownOldType(migration.from.clone()))?;
+        Ok((new, migration, old))
+    }
+
+    fn check(
+        &self,
+        name: &TypeName,
+        found: &TypeName,
+        checked: &mut BTreeSet<(TypeName, TypeName)>,
+    ) -> Result<(), MigrationError> {
+        if !checked.insert((name.clone(), found.clone())) {
+            return Ok(());
+        }
+        let (new, migration, old) = self.get_types(name)?;
+        if &migration.from != found {
+            return Err(MigrationError::SourceMismatch {
+                ty: name.clone(),
+                from: migration.from,
+                found: found.clone(),
+            });
+        }
+        if migration.fields.len() != new.len() as usize {
+            return Err(MigrationError::FieldCount {
+                ty: name.clone(),
+                listed: migration.fields.len(),
+                len: new.len(),
+            });
+        }
+        for (no, (source, into)) in migration.fields.iter().zip(new).enumerate() {
+            let from_field = match source {
+                FieldSource::Default(None) if !into.optional => {
+                    return Err(MigrationError::NoDefault {
+                        ty: name.clone(),
+                        field: no as u16,
+                    })
+                }
+                FieldSource::Default(_) => continue,
+                FieldSource::Field(from_field) => *from_field,
+            };
+            let from =
+                old.get(from_field as usize).ok_or_else(|| MigrationError::NoSourceField {
+                    ty: name.clone(),
+                    field: no as u16,
+                    from: migration.from.clone(),
+                    from_field,
+                })?;
+            let incompatible = || MigrationError::Incompatible {
+                ty: name.clone(),
+                field: no as u16,
+                from: from.clone(),
+                into: into.clone(),
+            };
+            if from.optional && !into.optional {
+                return Err(incompatible());
+            }
+            if !self.check_ref(&from.ty, &into.ty, checked)? {
+                return Err(incompatible());
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks whether values of type `from` can be migrated into type `into`.
+    fn check_ref(
+        &self,
+        from: &TypeRef,
+        into: &TypeRef,
+        checked: &mut BTreeSet<(TypeName, TypeName)>,
+    ) -> Result<bool, MigrationError> {
+        Ok(match (from, into) {
+            (TypeRef::InPlace(from), TypeRef::InPlace(into)) => compatible(from, into, &widens),
+            (TypeRef::NameRef(from), TypeRef::NameRef(into))
+                if compatible(from, into, &|_, _| true) =>
+            {
+                self.check(into.type_name(), from.type_name(), checked)?;
+                true
+            }
+            (TypeRef::Nested(from), TypeRef::Nested(into))
+                if compatible(from, into, &|_, _| true) =>
+            {
+                self.check_ref(from.inner(), into.inner(), checked)?
+            }
+            _ => false,
+        })
+    }
+
+    fn structure(&self, name: &TypeName, value: Value) -> Result<Value, MigrationError> {
+        let (new, migration, old) = self.get_types(name)?;
+        let mut fields = match value {
+            Value::Struct(fields) => fields,
+            value => return Ok(value),
+        };
+        let mut migrated = Vec::with_capacity(new.len() as usize);
+        for (source, into) in migration.fields.iter().zip(new) {
+            let (value, from) = match source {
+                FieldSource::Default(value) => {
+                    migrated.push(value.clone());
+                    continue;
+                }
+                FieldSource::Field(no) => {
+                    match (fields.get_mut(*no as usize), old.get(*no as usize)) {
+                        (Some(value), Some(from)) => (value.take(), from),
+                        _ => {
+                            migrated.push(None);
+                            continue;
+                        }
+                    }
+                }
+            };
+            let value = match value {
+                None => None,
+                Some(value) => Some(self.type_ref(value, &from.ty, &into.ty)?),
+            };
+            migrated.push(value);
+        }
+        Ok(Value::Struct(migrated))
+    }
+
+    fn type_ref(
+        &self,
+        value: Value,
+        from: &TypeRef,
+        into: &TypeRef,
+    ) -> Result<Value, MigrationError> {
+        match (from, into) {
+            (TypeRef::InPlace(from), TypeRef::InPlace(into)) => {
+                constr(value, from, into, &|value, _, into| Ok(widen(value, *into)))
+            }
+            (TypeRef::NameRef(from), TypeRef::NameRef(into)) => {
+                constr(value, from, into, &|value, _, into| self.structure(into, value))
+            }
+            (TypeRef::Nested(from), TypeRef::Nested(into)) => {
+                constr(value, from, into, &|value, from, into| self.type_ref(value, from, into))
+            }
+            _ => Ok(value),
+        }
+    }
+}
+
+/// Checks whether the primitive values of type `from` can be converted into
+/// type `into` without loss.
+fn widens(from: &PrimitiveType, into: &PrimitiveType) -> bool {
+    let (from, into) = (*from, *into);
+    let wider = match (from.fixed_size(), into.fixed_size()) {
+        (Some(from), Some(into)) => from < into,
+        _ => false,
+    };
+    from == into
+        || (from.is_unsigned() && (into.is_unsigned() || into.is_signed()) && wider)
+        || (from.is_signed() && into.is_signed() && wider)
+        || (from == PrimitiveType::F32 && into == PrimitiveType::F64)
+        || (from == PrimitiveType::AsciiChar && into == PrimitiveType::UnicodeChar)
+}
+
+fn widen(value: Value, into: PrimitiveType) -> Value {
+    let value = match value {
+        Value::Primitive(value) if value.ty() != into => value,
+        value => return value,
+    };
+    let from = value.ty();
+    let data = if from == PrimitiveType::F32 {
+        let mut data = [0u8; 4];
+        data.copy_from_slice(value.as_bytes());
+        (f32::from_le_bytes(data) as f64).to_le_bytes().to_vec()
+    } else if from.is_char() {
+        value.as_bytes().to_vec()
+    } else {
+        let negative =
+            from.is_signed() && value.as_bytes().last().copied().unwrap_or_default() >= 0x80;
+        let mut data = value.as_bytes().to_vec();
+        data.resize(
+            into.fixed_size().unwrap_or_default() as usize,
+            if negative { 0xFF } else { 0x00 },
+        );
+        data
+    };
+    PrimitiveValue::with(into, data).map(Value::Primitive).expect("widened value")
+}
+
+fn compatible<T>(
+    from: &TypeConstr<T>,
+    into: &TypeConstr<T>,
+    item: &dyn Fn(&T, &T) -> bool,
+) -> bool
+where
+    T: Clone + Ord + Eq + Hash + Debug,
+{
+    match (from, into) {
+        (TypeConstr::Plain(from), TypeConstr::Plain(into))
+        | (TypeConstr::List(from), TypeConstr::List(into))
+        | (TypeConstr::Set(from), TypeConstr::Set(into)) => item(from, into),
+        (TypeConstr::Array(len1, from), TypeConstr::Array(len2, into)) => {
+            len1 == len2 && item(from, into)
+        }
+        (TypeConstr::Map(key1, from), TypeConstr::Map(key2, into)) => {
+            compatible(&TypeConstr::from(*key1), &TypeConstr::from(*key2), &widens)
+                && item(from, into)
+        }
+        _ => false,
+    }
+}
+
+fn constr<T>(
+    value: Value,
+    from: &TypeConstr<T>,
+    into: &TypeConstr<T>,
+    item: &dyn Fn(Value, &T, &T) -> Result<Value, MigrationError>,
+) -> Result<Value, MigrationError>
+where
+    T: Clone + Ord + Eq + Hash + Debug,
+{
+    Ok(match (value, from, into) {
+        (value, TypeConstr::Plain(from), TypeConstr::Plain(into)) => item(value, from, into)?,
+        (Value::List(items), from, into) => Value::List(
+            items
+                .into_iter()
+                .map(|value| item(value, from.inner(), into.inner()))
+                .collect::<Result<_, _>>()?,
+        ),
+        (Value::Map(entries), TypeConstr::Map(key1, from), TypeConstr::Map(key2, into)) => {
+            let (key1, key2) = (TypeConstr::from(*key1), TypeConstr::from(*key2));
+            let key = |value, _: &PrimitiveType, into: &PrimitiveType| Ok(widen(value, *into));
+            Value::Map(
+                entries
+                    .into_iter()
+                    .map(|(k, v)| Ok((constr(k, &key1, &key2, &key)?, item(v, from, into)?)))
+                    .collect::<Result<_, MigrationError>>()?,
+            )
+        }
+        (value, ..) => value,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::schema::test::tn;
+    use crate::{strict_vec, type_system};
+
+    fn old() -> TypeSystem {
+        type_system![
+            "Record" :: {
+                StructField::primitive(PrimitiveType::U32),
+                StructField::ascii_string(),
+                StructField::primitive(PrimitiveType::U16),
+                StructField::with("Point"),
+                StructField::map(PrimitiveType::I8, PrimitiveType::F32),
+            },
+            "Point" :: {
+                StructField::primitive(PrimitiveType::I16),
+                StructField::primitive(PrimitiveType::I16),
+            }
+        ]
+    }
+
+    fn new() -> TypeSystem {
+        type_system![
+            "Entry" :: {
+                StructField::ascii_string(),
+                StructField::primitive(PrimitiveType::U64),
+                StructField::optional(TypeRef::NameRef(TypeConstr::Plain(tn("Point")))),
+                StructField::map(PrimitiveType::I64, PrimitiveType::F64),
+                StructField::primitive(PrimitiveType::U8),
+                StructField::optional(TypeRef::InPlace(TypeConstr::Plain(PrimitiveType::UnicodeChar))),
+            },
+            "Point" :: {
+                StructField::primitive(PrimitiveType::I16),
+                StructField::primitive(PrimitiveType::I16),
+            }
+        ]
+    }
+
+    fn migration() -> Migration {
+        let mut migration = Migration::new(tn("Record"), tn("Entry"));
+        migration.types.insert(tn("Entry"), TypeMigration {
+            from: tn("Record"),
+            fields: vec![
+                FieldSource::Field(1),
+                FieldSource::Field(0),
+                FieldSource::Field(3),
+                FieldSource::Field(4),
+                FieldSource::Default(Some(PrimitiveValue::u8(1).into())),
+                FieldSource::Default(None),
+            ],
+        });
+        migration
+    }
+
+    #[test]
+    fn migrate() {
+        let (old, new, migration) = (old(), new(), migration());
+        let migrator = Migrator::new(&old, &new, &migration).unwrap();
+        let data = [
+            0x2A, 0, 0, 0, // id
+            1, 0, 2, 0, b'h', b'i', // name
+            7, 0, // dropped flags
+            0xFF, 0xFF, 2, 0, // point
+            2, 0, 0x01, 0, 0, 0x80, 0x3F, 0xFF, 0, 0, 0, 0xC0, // map
+        ];
+        let migrated = migrator.migrate(data).unwrap();
+        assert_eq!(
+            new.decode(&tn("Entry"), &migrated).unwrap().to_string(),
+            "([\"hi\"], 42, (-1, 2), {1: 1, -1: -2}, 1, ~)"
+        );
+        assert_eq!(
+            migrator.migrate(&data[..12]).unwrap_err().to_string(),
+            "old data are invalid: unexpected end of data at .3.0"
+        );
+
+        let mut migration = self::migration();
+        migration.types.get_mut(&tn("Entry")).unwrap().fields[4] =
+            FieldSource::Default(Some(PrimitiveValue::u16(1).into()));
+        assert_eq!(
+            Migrator::new(&old, &new, &migration).unwrap().migrate(data).unwrap_err().to_string(),
+            "migrated data can't be encoded with the new schema: write_primitive is not allowed \
+             at .4, where the schema expects U8"
+        );
+    }
+
+    #[test]
+    fn invalid_migrations() {
+        let (old, new) = (old(), new());
+        let check = |migration: &Migration| Migrator::new(&old, &new, migration).unwrap_err();
+
+        assert_eq!(
+            check(&Migration::new(tn("Record"), tn("Entry"))),
+            MigrationError::UnknownOldType(tn("Entry"))
+        );
+
+        let mut migration = migration();
+        migration.types.get_mut(&tn("Entry")).unwrap().fields.pop();
+        assert_eq!(
+            check(&migration).to_string(),
+            "migration of type `Entry` lists 5 fields, while the type has 6 fields"
+        );
+
+        let mut migration = self::migration();
+        migration.types.get_mut(&tn("Entry")).unwrap().fields[4] = FieldSource::Default(None);
+        assert_eq!(check(&migration), MigrationError::NoDefault {
+            ty: tn("Entry"),
+            field: 4
+        });
+
+        let mut migration = self::migration();
+        migration.types.get_mut(&tn("Entry")).unwrap().fields[1] = FieldSource::Field(3);
+        assert_eq!(
+            check(&migration).to_string(),
+            "field 1 of type `Entry` can't be converted from `Point` into `U64`"
+        );
+
+        let mut migration = self::migration();
+        migration.types.get_mut(&tn("Entry")).unwrap().fields[0] = FieldSource::Field(0);
+        assert_eq!(
+            check(&migration).to_string(),
+            "field 0 of type `Entry` can't be converted from `U32` into `AsciiChar*`"
+        );
+
+        let mut migration = self::migration();
+        migration.types.get_mut(&tn("Entry")).unwrap().fields[1] = FieldSource::Field(5);
+        assert_eq!(
+            check(&migration).to_string(),
+            "field 1 of type `Entry` is taken from field 5 absent in the old type `Record`"
+        );
+
+        let mut migration = self::migration();
+        migration.types.insert(tn("Point"), TypeMigration {
+            from: tn("Record"),
+            fields: vec![FieldSource::Field(0), FieldSource::Field(0)],
+        });
+        assert_eq!(
+            check(&migration).to_string(),
+            "type `Point` is migrated from `Record`, but is used in place of `Point`"
+        );
+    }
+}
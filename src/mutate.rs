@@ -0,0 +1,318 @@
+// Strict encoding schema library, implementing validation and parsing of strict
+// encoded data against the schema.
+//
+// Written in 2022 by
+//     Dr. Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2022 by LNP/BP Standards Association, Switzerland.
+//
+// You should have received a copy of the MIT License along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::io::Cursor;
+use std::ops::Range;
+
+use crate::{
+    DataPath, DataStep, DecodeError, PrimitiveType, StructField, TypeConstr, TypeName, TypeRef,
+    TypeSystem, Verify, Visitor,
+};
+
+/// Encoding rule broken by a [`Mutation`].
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display)]
+#[display(doc_comments)]
+pub enum Rule {
+    /// length prefix matches the number of items
+    LengthPrefix,
+
+    /// optional tag is either 0 or 1
+    OptionalTag,
+
+    /// set items are unique and sorted in lexicographic order
+    SetOrder,
+
+    /// map keys are unique
+    UniqueMapKeys,
+
+    /// data contain all fields of the structure
+    Truncation,
+
+    /// ASCII character data contain only ASCII characters
+    Ascii,
+
+    /// Unicode character data are a valid UTF-8 string
+    Utf8,
+}
+
+/// Invalid variant of a valid strict encoding.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Display)]
+#[display("mutation at {path} breaks the rule that {rule}")]
+pub struct Mutation {
+    /// Encoding rule which is broken by the mutation.
+    pub rule: Rule,
+    /// Path to the data item targeted by the mutation.
+    pub path: DataPath,
+    /// Mutated data.
+    pub data: Vec<u8>,
+}
+
+impl TypeSystem {
+    /// Produces near-miss invalid variants of valid strict-encoded `data`
+    /// representing type `root`.
+    ///
+    /// Only variants which are rejected by [`TypeSystem::decode`] are
+    /// returned: some mutations (for instance decreasing length prefix of a
+    /// list) may result in a different valid encoding.
+    pub fn mutate(&self, root: &TypeName, data: &[u8]) -> Result<Vec<Mutation>, DecodeError> {
+        self.decode(root, data)?;
+
+        let mut spans = Spans::default();
+        let valid =
+            root.verify_with(self, &mut Cursor::new(data), &mut DataPath::new(), &mut spans);
+        debug_assert!(valid, "decoded data fail verification");
+
+        let mut mutations = vec![];
+        let mut push = |rule: Rule, path: &DataPath, variant: Vec<u8>| {
+            mutations.push(Mutation {
+                rule,
+                path: path.clone(),
+                data: variant,
+            })
+        };
+        let patch = |offset: u64, bytes: &[u8]| {
+            let mut variant = data.to_vec();
+            let offset = offset as usize;
+            variant[offset..offset + bytes.len()].copy_from_slice(bytes);
+            variant
+        };
+
+        for (path, offset, len) in &spans.lens {
+            if *len < u16::MAX {
+                push(Rule::LengthPrefix, path, patch(*offset, &(len + 1).to_le_bytes()));
+            }
+            if *len > 0 {
+                push(Rule::LengthPrefix, path, patch(*offset, &(len - 1).to_le_bytes()));
+            }
+        }
+
+        for (path, offset) in &spans.tags {
+            push(Rule::OptionalTag, path, patch(*offset, &[2]));
+        }
+
+        for (path, offset, len) in &spans.lens {
+            let (offset, len) = (*offset as usize, *len);
+            let ty = match spans.type_ref(path) {
+                Some(ty) => ty,
+                None => continue,
+            };
+            match ty {
+                TypeRef::InPlace(TypeConstr::Set(_))
+                | TypeRef::NameRef(TypeConstr::Set(_))
+                | TypeRef::Nested(TypeConstr::Set(_))
+                    if len >= 2 =>
+                {
+                    let first = spans.range(&path.with(DataStep::ArrayIndex(0)));
+                    let second = spans.range(&path.with(DataStep::ArrayIndex(1)));
+                    let mut variant = data[..first.start].to_vec();
+                    variant.extend(&data[second.clone()]);
+                    variant.extend(&data[first]);
+                    variant.extend(&data[second.end..]);
+                    push(Rule::SetOrder, &path.with(DataStep::ArrayIndex(1)), variant);
+                }
+                TypeRef::InPlace(TypeConstr::Map(key, _))
+                | TypeRef::NameRef(TypeConstr::Map(key, _))
+                | TypeRef::Nested(TypeConstr::Map(key, _))
+                    if (1..u16::MAX).contains(&len) =>
+                {
+                    let entry = spans.range(&path.with(DataStep::MapEntry(0)));
+                    let mut variant = data[..entry.end].to_vec();
+                    variant.extend(&data[entry.clone()]);
+                    variant.extend(&data[entry.end..]);
+                    variant[offset..offset + 2].copy_from_slice(&(len + 1).to_le_bytes());
+                    let path = path.with(DataStep::MapEntry(1)).with(DataStep::MapKey(key));
+                    push(Rule::UniqueMapKeys, &path, variant);
+                }
+                _ => {}
+            }
+        }
+
+        let mut last = 0;
+        for (path, offset, _) in &spans.fields {
+            if *offset > last && (*offset as usize) < data.len() {
+                push(Rule::Truncation, path, data[..*offset as usize].to_vec());
+                last = *offset;
+            }
+        }
+
+        for (path, offset, ty, len) in &spans.chars {
+            if *len == 0 {
+                continue;
+            }
+            match ty {
+                PrimitiveType::AsciiChar => push(Rule::Ascii, path, patch(offset + 2, &[0x80])),
+                PrimitiveType::UnicodeChar => push(Rule::Utf8, path, patch(offset + 2, &[0xFF])),
+                _ => unreachable!(),
+            }
+        }
+
+        mutations.retain(|mutation| self.decode(root, &mutation.data).is_err());
+        Ok(mutations)
+    }
+}
+
+/// Collects positions of the data items during verification.
+#[derive(Default)]
+struct Spans {
+    /// Byte ranges of all data items carrying data
+    items: Vec<(DataPath, Range<usize>)>,
+    fields: Vec<(DataPath, u64, TypeRef)>,
+    tags: Vec<(DataPath, u64)>,
+    lens: Vec<(DataPath, u64, u16)>,
+    chars: Vec<(DataPath, u64, PrimitiveType, usize)>,
+}
+
+impl Spans {
+    /// Returns byte range of the data item at `path`, including all nested
+    /// items.
+    fn range(&self, path: &DataPath) -> Range<usize> {
+        let mut items = self.items.iter().filter(|(p, _)| p.starts_with(path));
+        let first = items.next().expect("data item without data").1.clone();
+        items.fold(first, |range, (_, r)| range.start.min(r.start)..range.end.max(r.end))
+    }
+
+    /// Returns type of the data item at `path`, which is either a field or an
+    /// item of the collections nested into a field.
+    fn type_ref(&self, path: &DataPath) -> Option<TypeRef> {
+        let (field, _, ty) = self
+            .fields
+            .iter()
+            .filter(|(field, ..)| path.starts_with(field))
+            .max_by_key(|(field, ..)| field.len())?;
+        path.into_iter().skip(field.len() as usize).try_fold(ty.clone(), |ty, step| ty.item(step))
+    }
+
+    fn push(&mut self, path: &DataPath, offset: u64, len: usize) {
+        let offset = offset as usize;
+        self.items.push((path.clone(), offset..offset + len));
+    }
+}
+
+impl Visitor for Spans {
+    fn field(&mut self, path: &DataPath, offset: u64, _: u16, field: &StructField) {
+        self.fields.push((path.clone(), offset, field.ty.clone()));
+    }
+
+    fn optional_tag(&mut self, path: &DataPath, offset: u64, _: u8) {
+        self.push(path, offset, 1);
+        self.tags.push((path.clone(), offset));
+    }
+
+    fn list_len(&mut self, path: &DataPath, offset: u64, len: u16) {
+        self.push(path, offset, 2);
+        self.lens.push((path.clone(), offset, len));
+    }
+
+    fn primitive(&mut self, path: &DataPath, offset: u64, ty: PrimitiveType, data: &[u8]) {
+        if ty.is_char() {
+            self.push(path, offset, data.len() + 2);
+            self.chars.push((path.clone(), offset, ty, data.len()));
+        } else {
+            self.push(path, offset, data.len());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::schema::test::{tn, transaction, type_system};
+    use crate::{strict_vec, type_system, DataError, KeyType};
+
+    #[test]
+    fn mutate_transaction() {
+        let ts = type_system();
+        let root = tn("Transaction");
+        let mutations = ts.mutate(&root, &transaction()).unwrap();
+        let paths = |rule: Rule| {
+            mutations
+                .iter()
+                .filter(|m| m.rule == rule)
+                .map(|m| m.path.to_string())
+                .collect::<Vec<_>>()
+        };
+        // Both increasing and decreasing number of inputs breaks the encoding
+        assert_eq!(paths(Rule::LengthPrefix)[..2], [".1", ".1"]);
+        assert_eq!(paths(Rule::Truncation).len(), 16);
+        assert!(paths(Rule::Truncation).contains(&s!(".1[0].2.0[0].0")));
+        for mutation in mutations {
+            assert!(ts.decode(&root, &mutation.data).is_err());
+        }
+    }
+
+    #[test]
+    fn mutate_collections() {
+        let ts = type_system![
+            "Doc" :: {
+                StructField { ty: TypeRef::ascii_string(), optional: true },
+                StructField { ty: TypeRef::InPlace(TypeConstr::Set(PrimitiveType::U8)), optional: false },
+                StructField::typed_map(KeyType::Primitive(PrimitiveType::U8), "Text"),
+            },
+            "Text" :: { StructField::unicode_string() }
+        ];
+        let root = tn("Doc");
+        let data = [1, 1, 0, 1, 0, b'a', 2, 0, 1, 2, 1, 0, 5, 1, 0, 1, 0, b'b'];
+        let mutations = ts.mutate(&root, &data).unwrap();
+
+        let find = |rule: Rule| mutations.iter().find(|m| m.rule == rule).unwrap();
+        assert_eq!(find(Rule::OptionalTag).path.to_string(), ".0");
+        assert_eq!(
+            ts.decode(&root, &find(Rule::OptionalTag).data).unwrap_err().error,
+            DataError::OptionalTag(2)
+        );
+
+        let set_order = find(Rule::SetOrder);
+        assert_eq!(set_order.path.to_string(), ".1[1]");
+        assert_eq!(set_order.data, [1, 1, 0, 1, 0, b'a', 2, 0, 2, 1, 1, 0, 5, 1, 0, 1, 0, b'b']);
+
+        let map_keys = find(Rule::UniqueMapKeys);
+        assert_eq!(map_keys.path.to_string(), ".2{1}<U8>");
+        assert_eq!(
+            map_keys.to_string(),
+            "mutation at .2{1}<U8> breaks the rule that map keys are unique"
+        );
+        assert_eq!(ts.decode(&root, &map_keys.data).unwrap_err().error, DataError::RepeatedMapKey);
+
+        assert_eq!(find(Rule::Ascii).path.to_string(), ".0[0]");
+        assert_eq!(
+            ts.decode(&root, &find(Rule::Ascii).data).unwrap_err().error,
+            DataError::NonAscii(0x80)
+        );
+        assert_eq!(find(Rule::Utf8).path.to_string(), ".2{0}.0[0]");
+
+        let truncations = mutations.iter().filter(|m| m.rule == Rule::Truncation).count();
+        assert_eq!(truncations, 3);
+    }
+
+    #[test]
+    fn mutate_nested() {
+        let sets = TypeRef::nested(TypeConstr::List(TypeRef::InPlace(TypeConstr::Set(
+            PrimitiveType::U8,
+        ))));
+        let mut ts = type_system![
+            "Doc" :: { StructField { ty: sets, optional: false }, StructField::typed_list("Keys") }
+        ];
+        let keys = TypeConstr::Map(KeyType::Primitive(PrimitiveType::U8), PrimitiveType::U8);
+        ts.push_alias(tn("Keys"), TypeRef::InPlace(keys)).unwrap();
+        let root = tn("Doc");
+        let data = [1, 0, 2, 0, 1, 2, 1, 0, 1, 0, 5, 7];
+        let mutations = ts.mutate(&root, &data).unwrap();
+
+        let find = |rule: Rule| mutations.iter().find(|m| m.rule == rule).unwrap();
+        let set_order = find(Rule::SetOrder);
+        assert_eq!(set_order.path.to_string(), ".0[0][1]");
+        assert_eq!(set_order.data, [1, 0, 2, 0, 2, 1, 1, 0, 1, 0, 5, 7]);
+
+        let map_keys = find(Rule::UniqueMapKeys);
+        assert_eq!(map_keys.path.to_string(), ".1[0].0{1}<U8>");
+        assert_eq!(map_keys.data, [1, 0, 2, 0, 1, 2, 1, 0, 2, 0, 5, 7, 5, 7]);
+    }
+}
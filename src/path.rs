@@ -9,16 +9,145 @@
 // You should have received a copy of the MIT License along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
 use crate::{KeyType, StrictVec};
 
-#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug)]
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display)]
 #[derive(StrictEncode, StrictDecode)]
 pub enum DataStep {
+    #[display(".{0}")]
     StructField(u16),
+
+    #[display("[{0}]")]
     ArrayIndex(u16),
+
+    /// Key of the map entry; always follows [`DataStep::MapEntry`] step.
+    #[display("<{0}>")]
     MapKey(KeyType),
+
+    /// Value of the map entry with the given position in the key order.
+    #[display("{{{0}}}")]
+    MapEntry(u16),
 }
 
-#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug)]
+#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Default)]
 #[derive(StrictEncode, StrictDecode)]
 pub struct DataPath(StrictVec<DataStep, 0>);
+
+impl Display for DataPath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.is_root() {
+            return f.write_str(".");
+        }
+        for step in &self.0 {
+            Display::fmt(step, f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Error parsing data path from its string representation.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Display, Error)]
+#[display("invalid data path step at position {0}")]
+pub struct PathParseError(pub usize);
+
+impl FromStr for DataPath {
+    type Err = PathParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut path = DataPath::new();
+        if s == "." {
+            return Ok(path);
+        }
+        let mut rest = s;
+        while !rest.is_empty() {
+            let pos = s.len() - rest.len();
+            let err = PathParseError(pos);
+            let (close, make): (char, fn(&str) -> Option<DataStep>) = match rest.as_bytes()[0] {
+                b'.' => ('.', |s| s.parse().ok().map(DataStep::StructField)),
+                b'[' => (']', |s| s.parse().ok().map(DataStep::ArrayIndex)),
+                b'{' => ('}', |s| s.parse().ok().map(DataStep::MapEntry)),
+                b'<' => ('>', |s| s.parse().ok().map(DataStep::MapKey)),
+                _ => return Err(err),
+            };
+            let (step, tail) = if close == '.' {
+                let end =
+                    rest[1..].find(['.', '[', '{', '<']).map(|end| end + 1).unwrap_or(rest.len());
+                (&rest[1..end], &rest[end..])
+            } else {
+                let end = rest.find(close).ok_or(err)?;
+                (&rest[1..end], &rest[end + 1..])
+            };
+            path.push(make(step).ok_or(err)?);
+            rest = tail;
+        }
+        Ok(path)
+    }
+}
+
+impl<'me> IntoIterator for &'me DataPath {
+    type Item = &'me DataStep;
+    type IntoIter = std::slice::Iter<'me, DataStep>;
+
+    fn into_iter(self) -> Self::IntoIter { self.0.iter() }
+}
+
+#[allow(clippy::len_without_is_empty)]
+impl DataPath {
+    pub fn new() -> Self { default!() }
+
+    #[inline]
+    pub fn is_root(&self) -> bool { self.0.is_empty() }
+
+    #[inline]
+    pub fn len(&self) -> u16 { self.0.len() }
+
+    #[inline]
+    pub fn last(&self) -> Option<&DataStep> { self.0.last() }
+
+    pub fn push(&mut self, step: DataStep) {
+        self.0.push(step).expect("data nesting exceeds 2^16 levels");
+    }
+
+    pub fn pop(&mut self) -> Option<DataStep> {
+        match self.0.len() {
+            0 => None,
+            len => self.0.remove(len - 1).ok(),
+        }
+    }
+
+    /// Returns a copy of the path extended with an additional step.
+    pub fn with(&self, step: DataStep) -> DataPath {
+        let mut path = self.clone();
+        path.push(step);
+        path
+    }
+
+    /// Checks whether the path starts with all steps of the `prefix` path.
+    pub fn starts_with(&self, prefix: &DataPath) -> bool { self.0.starts_with(&prefix.0) }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::PrimitiveType;
+
+    #[test]
+    fn path_from_str() {
+        for path in [".", ".0", ".1[2]{3}<U8[32]>[4]", ".0{1}<UnicodeChar[]>[0].2{0}<I64>"] {
+            assert_eq!(DataPath::from_str(path).unwrap().to_string(), path);
+        }
+        assert_eq!(
+            DataPath::from_str(".2{0}<U16>").unwrap(),
+            DataPath::new()
+                .with(DataStep::StructField(2))
+                .with(DataStep::MapEntry(0))
+                .with(DataStep::MapKey(KeyType::Primitive(PrimitiveType::U16)))
+        );
+        assert_eq!(DataPath::from_str(".1[x]"), Err(PathParseError(2)));
+        assert_eq!(DataPath::from_str("[1"), Err(PathParseError(0)));
+        assert_eq!(DataPath::from_str(""), Ok(DataPath::new()));
+    }
+}
@@ -0,0 +1,693 @@
+// Strict encoding schema library, implementing validation and parsing of strict
+// encoded data against the schema.
+//
+// Written in 2022 by
+//     Dr. Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2022 by LNP/BP Standards Association, Switzerland.
+//
+// You should have received a copy of the MIT License along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::collections::BTreeSet;
+use std::fmt::{Debug, Write};
+use std::hash::Hash;
+
+use crate::codegen::{identifier, snake_case, words, Idents, Names};
+use crate::{PrimitiveType, StructType, TypeConstr, TypeName, TypeRef, TypeSystem};
+
+/// Python keywords, builtins used in the generated code and names of the
+/// generated function arguments and variables.
+const KEYWORDS: &[&str] = &[
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class",
+    "continue", "def", "del", "elif", "else", "except", "finally", "for", "from", "global", "if",
+    "import", "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try",
+    "while", "with", "yield", "bytes", "float", "int", "str", "cls", "data", "r", "reader",
+    "self", "value", "w", "writer",
+];
+
+/// Preamble of the generated module: imports, type variables and the stream
+/// classes used by the generated `read` and `write` methods.
+const RUNTIME: &str = r#"from __future__ import annotations
+
+import struct
+from dataclasses import dataclass
+from typing import Callable, List, Optional, Tuple, TypeVar
+
+T = TypeVar("T")
+K = TypeVar("K")
+V = TypeVar("V")
+
+
+class DecodeError(ValueError):
+    pass
+
+
+class EncodeError(ValueError):
+    pass
+
+
+class StrictReader:
+    def __init__(self, data: bytes) -> None:
+        self.data = bytes(data)
+        self.offset = 0
+
+    def _take(self, size: int) -> bytes:
+        if self.offset + size > len(self.data):
+            raise DecodeError(f"unexpected end of data at offset {self.offset}")
+        chunk = self.data[self.offset:self.offset + size]
+        self.offset += size
+        return chunk
+
+    def uint(self, size: int) -> int:
+        return int.from_bytes(self._take(size), "little")
+
+    def sint(self, size: int) -> int:
+        return int.from_bytes(self._take(size), "little", signed=True)
+
+    def f32(self) -> float:
+        return struct.unpack("<f", self._take(4))[0]
+
+    def f64(self) -> float:
+        return struct.unpack("<d", self._take(8))[0]
+
+    def raw(self, size: int) -> bytes:
+        return self._take(size)
+
+    def bytes(self) -> bytes:
+        return self._take(self.uint(2))
+
+    def ascii(self) -> str:
+        data = self.bytes()
+        for byte in data:
+            if byte >= 0x80:
+                raise DecodeError(f"non-ASCII character {byte:#04x}")
+        return data.decode("ascii")
+
+    def unicode(self) -> str:
+        try:
+            return self.bytes().decode("utf-8")
+        except UnicodeDecodeError:
+            raise DecodeError("invalid UTF-8 string") from None
+
+    def optional(self, item: Callable[[], T]) -> Optional[T]:
+        tag = self.uint(1)
+        if tag > 1:
+            raise DecodeError(f"invalid optional tag {tag}")
+        return item() if tag == 1 else None
+
+    def array(self, size: int, item: Callable[[], T]) -> List[T]:
+        return [item() for _ in range(size)]
+
+    def list(self, item: Callable[[], T]) -> List[T]:
+        return self.array(self.uint(2), item)
+
+    def _encoded(self, item: Callable[[], T]) -> Tuple[T, bytes]:
+        start = self.offset
+        value = item()
+        return value, self.data[start:self.offset]
+
+    def set(self, item: Callable[[], T]) -> List[T]:
+        items = []
+        last = None
+        for _ in range(self.uint(2)):
+            value, data = self._encoded(item)
+            if last is not None and last >= data:
+                raise DecodeError("repeated set item" if last == data else "set items are not sorted")
+            last = data
+            items.append(value)
+        return items
+
+    def map(self, key: Callable[[], K], value: Callable[[], V]) -> List[Tuple[K, V]]:
+        entries = []
+        last = None
+        for _ in range(self.uint(2)):
+            k, data = self._encoded(key)
+            if last is not None and last >= data:
+                raise DecodeError("repeated map key" if last == data else "map keys are not sorted")
+            last = data
+            entries.append((k, value()))
+        return entries
+
+    def finish(self) -> None:
+        if self.offset < len(self.data):
+            raise DecodeError(f"{len(self.data) - self.offset} bytes of data are not consumed")
+
+
+class StrictWriter:
+    def __init__(self) -> None:
+        self.data = bytearray()
+
+    def uint(self, value: int, size: int) -> None:
+        if not 0 <= value < 1 << (8 * size):
+            raise EncodeError(f"value {value} does not fit into {size} bytes")
+        self.data += value.to_bytes(size, "little")
+
+    def sint(self, value: int, size: int) -> None:
+        half = 1 << (8 * size - 1)
+        if not -half <= value < half:
+            raise EncodeError(f"value {value} does not fit into {size} bytes")
+        self.data += value.to_bytes(size, "little", signed=True)
+
+    def f32(self, value: float) -> None:
+        self.data += struct.pack("<f", value)
+
+    def f64(self, value: float) -> None:
+        self.data += struct.pack("<d", value)
+
+    def raw(self, value: bytes, size: int) -> None:
+        if len(value) != size:
+            raise EncodeError(f"expected {size} bytes, got {len(value)}")
+        self.data += value
+
+    def bytes(self, value: bytes) -> None:
+        self._len(len(value))
+        self.data += value
+
+    def ascii(self, value: str) -> None:
+        if not value.isascii():
+            raise EncodeError(f"string {value!r} contains non-ASCII characters")
+        self.bytes(value.encode("ascii"))
+
+    def unicode(self, value: str) -> None:
+        self.bytes(value.encode("utf-8"))
+
+    def optional(self, value: Optional[T], item: Callable[[T], None]) -> None:
+        if value is None:
+            self.uint(0, 1)
+        else:
+            self.uint(1, 1)
+            item(value)
+
+    def array(self, items: List[T], size: int, item: Callable[[T], None]) -> None:
+        if len(items) != size:
+            raise EncodeError(f"expected {size} items, got {len(items)}")
+        for value in items:
+            item(value)
+
+    def list(self, items: List[T], item: Callable[[T], None]) -> None:
+        self._len(len(items))
+        for value in items:
+            item(value)
+
+    def set(self, items: List[T], item: Callable[[T], None]) -> None:
+        encoded = sorted(self._capture(lambda: item(value)) for value in items)
+        if any(a == b for a, b in zip(encoded, encoded[1:])):
+            raise EncodeError("repeated set item")
+        self._len(len(encoded))
+        for data in encoded:
+            self.data += data
+
+    def map(
+        self,
+        entries: List[Tuple[K, V]],
+        key: Callable[[K], None],
+        value: Callable[[V], None],
+    ) -> None:
+        encoded = sorted(
+            (self._capture(lambda: key(k)), self._capture(lambda: value(v))) for k, v in entries
+        )
+        if any(a[0] == b[0] for a, b in zip(encoded, encoded[1:])):
+            raise EncodeError("repeated map key")
+        self._len(len(encoded))
+        for k, v in encoded:
+            self.data += k + v
+
+    def finish(self) -> bytes:
+        return bytes(self.data)
+
+    def _len(self, size: int) -> None:
+        if size > 0xFFFF:
+            raise EncodeError(f"{size} items exceed 16-bit length limit")
+        self.uint(size, 2)
+
+    def _capture(self, write: Callable[[], None]) -> bytes:
+        data = self.data
+        self.data = bytearray()
+        write()
+        captured = bytes(self.data)
+        self.data = data
+        return captured
+"#;
+
+impl TypeSystem {
+    /// Generates Python module with a dataclass for each type of the type
+    /// system, together with methods decoding and encoding the data.
+    ///
+    /// Structure fields are named `f0`, `f1` etc; optional fields are
+    /// `Optional`. Integers of all sizes are represented by `int` and checked
+    /// against the primitive range on encoding; `F32`/`F64` floats are `float`,
+    /// other floats are their raw `bytes`. Lists and arrays of `U8` are `bytes`,
+    /// characters are `str`. Sets are lists and maps are lists of key-value
+    /// tuples; the encoders sort them by the encoding of the items (keys), and
+    /// the decoders reject data in which set items or map keys are not unique
+    /// or not sorted.
+    ///
+    /// Each class has `read(reader)`/`write(writer)` methods operating on
+    /// `StrictReader` and `StrictWriter`, and `decode(data)`/`encode()` methods
+    /// operating on `bytes`.
+    ///
+    /// Aliases are declared as Python type aliases of the aliased types after
+    /// all the classes, each with `read_alias(reader)`/`write_alias(writer,
+    /// value)` and `decode_alias(data)`/`encode_alias(value)` functions, named
+    /// after the alias in snake case.
+    ///
+    /// Characters of the type names, which are not allowed in Python
+    /// identifiers, are replaced with underscores; names clashing with the
+    /// keywords, the runtime or other types get numeric suffixes.
+    pub fn to_python(&self) -> String {
+        let mut names = Names::reserved(words(RUNTIME).chain(KEYWORDS.iter().copied()));
+        let mut ids = Ids::default();
+        for (name, _) in self {
+            ids.classes.insert(name.clone(), names.fresh(&identifier(name)));
+        }
+        for (name, _) in self {
+            if self.is_alias(name) {
+                let snake = names.fresh_with(&snake_case(&ids.classes[name]), |snake| {
+                    ["read", "write", "decode", "encode"]
+                        .iter()
+                        .map(|prefix| format!("{}_{}", prefix, snake))
+                        .collect()
+                });
+                ids.functions.insert(name.clone(), snake);
+            }
+        }
+
+        let mut py = String::new();
+        writeln!(py, "# Generated from strict encoding schema; do not edit.").ok();
+        writeln!(py).ok();
+        py.push_str(RUNTIME);
+        for (name, ty) in self {
+            if !self.is_alias(name) {
+                structure(&mut py, &ids, name, ty);
+            }
+        }
+        // aliases are evaluated on import, so the aliased aliases go first
+        let mut declared = BTreeSet::new();
+        for (name, _) in self {
+            let mut chain = vec![];
+            let mut next = Some(name);
+            while let Some(name) = next
+                .filter(|name| self.is_alias(name) && !declared.contains(*name))
+                .filter(|name| !chain.contains(name))
+            {
+                chain.push(name);
+                next = self.alias(name).and_then(TypeRef::type_name);
+            }
+            for name in chain.into_iter().rev() {
+                alias(&mut py, &ids, name, self.alias(name).expect("alias is checked"));
+                declared.insert(name);
+            }
+        }
+        py
+    }
+}
+
+/// Python identifiers of the schema types.
+#[derive(Default)]
+struct Ids {
+    /// Names of the classes and type aliases.
+    classes: Idents,
+    /// Snake case names used by the functions of the type aliases.
+    functions: Idents,
+}
+
+fn alias(py: &mut String, ids: &Ids, name: &TypeName, ty: &TypeRef) {
+    let snake = &ids.functions[name];
+    let name = &ids.classes[name];
+    writeln!(py).ok();
+    writeln!(py).ok();
+    writeln!(py, "# {}", ty).ok();
+    writeln!(py, "{} = {}", name, type_ref(ids, ty)).ok();
+
+    writeln!(py).ok();
+    writeln!(py).ok();
+    writeln!(py, "def read_{}(r: StrictReader) -> {}:", snake, name).ok();
+    writeln!(py, "    return {}", read_ref(ids, ty)).ok();
+
+    writeln!(py).ok();
+    writeln!(py).ok();
+    writeln!(py, "def write_{}(w: StrictWriter, value: {}) -> None:", snake, name).ok();
+    writeln!(py, "    {}", write_ref(ids, ty, "value", 0)).ok();
+
+    writeln!(py).ok();
+    writeln!(py).ok();
+    writeln!(py, "def decode_{}(data: bytes) -> {}:", snake, name).ok();
+    writeln!(py, "    reader = StrictReader(data)").ok();
+    writeln!(py, "    value = read_{}(reader)", snake).ok();
+    writeln!(py, "    reader.finish()").ok();
+    writeln!(py, "    return value").ok();
+
+    writeln!(py).ok();
+    writeln!(py).ok();
+    writeln!(py, "def encode_{}(value: {}) -> bytes:", snake, name).ok();
+    writeln!(py, "    writer = StrictWriter()").ok();
+    writeln!(py, "    write_{}(writer, value)", snake).ok();
+    writeln!(py, "    return writer.finish()").ok();
+}
+
+fn structure(py: &mut String, ids: &Ids, name: &TypeName, ty: &StructType) {
+    let name = &ids.classes[name];
+    writeln!(py).ok();
+    writeln!(py).ok();
+    writeln!(py, "@dataclass").ok();
+    writeln!(py, "class {}:", name).ok();
+    writeln!(py, "    \"\"\"{}\"\"\"", ty).ok();
+    writeln!(py).ok();
+    for (no, field) in ty.into_iter().enumerate() {
+        let mut field_ty = type_ref(ids, &field.ty);
+        if field.optional {
+            field_ty = format!("Optional[{}]", field_ty);
+        }
+        writeln!(py, "    f{}: {}", no, field_ty).ok();
+    }
+
+    writeln!(py).ok();
+    writeln!(py, "    @classmethod").ok();
+    writeln!(py, "    def read(cls, r: StrictReader) -> {}:", name).ok();
+    writeln!(py, "        return cls(").ok();
+    for field in ty {
+        let mut expr = read_ref(ids, &field.ty);
+        if field.optional {
+            expr = format!("r.optional(lambda: {})", expr);
+        }
+        writeln!(py, "            {},", expr).ok();
+    }
+    writeln!(py, "        )").ok();
+
+    writeln!(py).ok();
+    writeln!(py, "    def write(self, w: StrictWriter) -> None:").ok();
+    for (no, field) in ty.into_iter().enumerate() {
+        let value = format!("self.f{}", no);
+        let stmt = if field.optional {
+            format!("w.optional({}, lambda v0: {})", value, write_ref(ids, &field.ty, "v0", 1))
+        } else {
+            write_ref(ids, &field.ty, &value, 0)
+        };
+        writeln!(py, "        {}", stmt).ok();
+    }
+
+    writeln!(py).ok();
+    writeln!(py, "    @classmethod").ok();
+    writeln!(py, "    def decode(cls, data: bytes) -> {}:", name).ok();
+    writeln!(py, "        reader = StrictReader(data)").ok();
+    writeln!(py, "        value = cls.read(reader)").ok();
+    writeln!(py, "        reader.finish()").ok();
+    writeln!(py, "        return value").ok();
+
+    writeln!(py).ok();
+    writeln!(py, "    def encode(self) -> bytes:").ok();
+    writeln!(py, "        writer = StrictWriter()").ok();
+    writeln!(py, "        self.write(writer)").ok();
+    writeln!(py, "        return writer.finish()").ok();
+}
+
+fn primitive(ty: PrimitiveType) -> String {
+    match ty {
+        PrimitiveType::AsciiChar | PrimitiveType::UnicodeChar => "str",
+        PrimitiveType::F32 | PrimitiveType::F64 => "float",
+        ty if ty.is_float() => "bytes",
+        _ => "int",
+    }
+    .to_owned()
+}
+
+/// Returns Python type annotation for the primitive data.
+fn prim_constr(ty: &TypeConstr<PrimitiveType>) -> String {
+    match ty {
+        TypeConstr::Array(_, PrimitiveType::U8) | TypeConstr::List(PrimitiveType::U8) => {
+            s!("bytes")
+        }
+        ty => constr(ty, &|ty| primitive(*ty)),
+    }
+}
+
+/// Returns Python type annotation for the data.
+fn constr<T>(ty: &TypeConstr<T>, item: &dyn Fn(&T) -> String) -> String
+where T: Clone + Ord + Eq + Hash + Debug {
+    match ty {
+        TypeConstr::Plain(ty) => item(ty),
+        TypeConstr::Array(_, ty) | TypeConstr::List(ty) | TypeConstr::Set(ty) => {
+            format!("List[{}]", item(ty))
+        }
+        TypeConstr::Map(key, ty) => {
+            format!("List[Tuple[{}, {}]]", prim_constr(&TypeConstr::from(*key)), item(ty))
+        }
+    }
+}
+
+fn type_ref(ids: &Ids, ty: &TypeRef) -> String {
+    match ty {
+        TypeRef::InPlace(ty) => prim_constr(ty),
+        TypeRef::NameRef(ty) => constr(ty, &|name| ids.classes[name].clone()),
+        TypeRef::Nested(ty) => constr(ty, &|item| type_ref(ids, item)),
+    }
+}
+
+fn read_primitive(ty: PrimitiveType) -> String {
+    match (ty, ty.fixed_size()) {
+        (PrimitiveType::AsciiChar, _) => s!("r.ascii()"),
+        (PrimitiveType::UnicodeChar, _) => s!("r.unicode()"),
+        (PrimitiveType::F32, _) => s!("r.f32()"),
+        (PrimitiveType::F64, _) => s!("r.f64()"),
+        (ty, Some(size)) if ty.is_float() => format!("r.raw({})", size),
+        (ty, Some(size)) if ty.is_unsigned() => format!("r.uint({})", size),
+        (_, size) => format!("r.sint({})", size.unwrap_or_default()),
+    }
+}
+
+fn read_prim_constr(ty: &TypeConstr<PrimitiveType>) -> String {
+    match ty {
+        TypeConstr::Array(len, PrimitiveType::U8) => format!("r.raw({})", len),
+        TypeConstr::List(PrimitiveType::U8) => s!("r.bytes()"),
+        ty => read_constr(ty, &|ty| read_primitive(*ty)),
+    }
+}
+
+fn read_constr<T>(ty: &TypeConstr<T>, item: &dyn Fn(&T) -> String) -> String
+where T: Clone + Ord + Eq + Hash + Debug {
+    match ty {
+        TypeConstr::Plain(ty) => item(ty),
+        TypeConstr::Array(len, ty) => format!("r.array({}, lambda: {})", len, item(ty)),
+        TypeConstr::List(ty) => format!("r.list(lambda: {})", item(ty)),
+        TypeConstr::Set(ty) => format!("r.set(lambda: {})", item(ty)),
+        TypeConstr::Map(key, ty) => {
+            let key = read_prim_constr(&TypeConstr::from(*key));
+            format!("r.map(lambda: {}, lambda: {})", key, item(ty))
+        }
+    }
+}
+
+fn read_ref(ids: &Ids, ty: &TypeRef) -> String {
+    match ty {
+        TypeRef::InPlace(ty) => read_prim_constr(ty),
+        TypeRef::NameRef(ty) => read_constr(ty, &|name| match ids.functions.get(name) {
+            Some(snake) => format!("read_{}(r)", snake),
+            None => format!("{}.read(r)", ids.classes[name]),
+        }),
+        TypeRef::Nested(ty) => read_constr(ty, &|item| read_ref(ids, item)),
+    }
+}
+
+fn write_primitive(ty: PrimitiveType, value: &str) -> String {
+    match (ty, ty.fixed_size()) {
+        (PrimitiveType::AsciiChar, _) => format!("w.ascii({})", value),
+        (PrimitiveType::UnicodeChar, _) => format!("w.unicode({})", value),
+        (PrimitiveType::F32, _) => format!("w.f32({})", value),
+        (PrimitiveType::F64, _) => format!("w.f64({})", value),
+        (ty, Some(size)) if ty.is_float() => format!("w.raw({}, {})", value, size),
+        (ty, Some(size)) if ty.is_unsigned() => format!("w.uint({}, {})", value, size),
+        (_, size) => format!("w.sint({}, {})", value, size.unwrap_or_default()),
+    }
+}
+
+fn write_prim_constr(ty: &TypeConstr<PrimitiveType>, value: &str, depth: usize) -> String {
+    match ty {
+        TypeConstr::Array(len, PrimitiveType::U8) => format!("w.raw({}, {})", value, len),
+        TypeConstr::List(PrimitiveType::U8) => format!("w.bytes({})", value),
+        ty => write_constr(ty, value, depth, &|ty, value| write_primitive(*ty, value)),
+    }
+}
+
+/// Returns Python statement writing `value`; `depth` is used to name the
+/// arguments of the nested lambdas.
+fn write_constr<T>(
+    ty: &TypeConstr<T>,
+    value: &str,
+    depth: usize,
+    item: &dyn Fn(&T, &str) -> String,
+) -> String
+where
+    T: Clone + Ord + Eq + Hash + Debug,
+{
+    let var = format!("v{}", depth);
+    match ty {
+        TypeConstr::Plain(ty) => item(ty, value),
+        TypeConstr::Array(len, ty) => {
+            format!("w.array({}, {}, lambda {}: {})", value, len, var, item(ty, &var))
+        }
+        TypeConstr::List(ty) => format!("w.list({}, lambda {}: {})", value, var, item(ty, &var)),
+        TypeConstr::Set(ty) => format!("w.set({}, lambda {}: {})", value, var, item(ty, &var)),
+        TypeConstr::Map(key, ty) => {
+            let key_var = format!("k{}", depth);
+            let key = write_prim_constr(&TypeConstr::from(*key), &key_var, depth + 1);
+            format!(
+                "w.map({}, lambda {}: {}, lambda {}: {})",
+                value,
+                key_var,
+                key,
+                var,
+                item(ty, &var)
+            )
+        }
+    }
+}
+
+fn write_ref(ids: &Ids, ty: &TypeRef, value: &str, depth: usize) -> String {
+    match ty {
+        TypeRef::InPlace(ty) => write_prim_constr(ty, value, depth),
+        TypeRef::NameRef(ty) => write_constr(ty, value, depth, &|name, value| {
+            match ids.functions.get(name) {
+                Some(snake) => format!("write_{}(w, {})", snake, value),
+                None => format!("{}.write(w)", value),
+            }
+        }),
+        TypeRef::Nested(ty) => {
+            write_constr(ty, value, depth, &|item, value| write_ref(ids, item, value, depth + 1))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::schema::test::{golden, tn, type_system};
+    use crate::{strict_vec, type_system, KeyType, StructField};
+
+    #[test]
+    fn python() { golden("types.py", &type_system().to_python()); }
+
+    #[test]
+    fn primitives() {
+        let ts = type_system![
+            "Prims" :: {
+                StructField::primitive(PrimitiveType::I64),
+                StructField { ty: TypeRef::InPlace(TypeConstr::Set(PrimitiveType::U128)), optional: true },
+                StructField::primitive(PrimitiveType::F16),
+                StructField::map(KeyType::Array(4, PrimitiveType::U8), PrimitiveType::F32),
+                StructField::array(PrimitiveType::U16, 2),
+            }
+        ];
+        let py = ts.to_python();
+        assert!(py.contains(
+            "    f0: int
+    f1: Optional[List[int]]
+    f2: bytes
+    f3: List[Tuple[bytes, float]]
+    f4: List[int]
+"
+        ));
+        assert!(py.contains(
+            "            r.sint(8),
+            r.optional(lambda: r.set(lambda: r.uint(16))),
+            r.raw(2),
+            r.map(lambda: r.raw(4), lambda: r.f32()),
+            r.array(2, lambda: r.uint(2)),
+"
+        ));
+        assert!(py.contains(
+            "        w.sint(self.f0, 8)
+        w.optional(self.f1, lambda v0: w.set(v0, lambda v1: w.uint(v1, 16)))
+        w.raw(self.f2, 2)
+        w.map(self.f3, lambda k0: w.raw(k0, 4), lambda v0: w.f32(v0))
+        w.array(self.f4, 2, lambda v0: w.uint(v0, 2))
+"
+        ));
+    }
+
+    #[test]
+    fn aliases() {
+        let mut ts = type_system![
+            "Input" :: { StructField::with("Txids"), StructField::primitive(PrimitiveType::U16) }
+        ];
+        ts.push_alias(tn("Txids"), TypeRef::NameRef(TypeConstr::List(tn("Txid")))).unwrap();
+        ts.push_alias(tn("Txid"), TypeRef::InPlace(TypeConstr::Array(32, PrimitiveType::U8)))
+            .unwrap();
+        let py = ts.to_python();
+        assert!(py.contains(
+            "            read_txids(r),
+            r.uint(2),
+        )
+
+    def write(self, w: StrictWriter) -> None:
+        write_txids(w, self.f0)
+"
+        ));
+        assert!(py.contains(
+            "# U8*32
+Txid = bytes
+
+
+def read_txid(r: StrictReader) -> Txid:
+    return r.raw(32)
+
+
+def write_txid(w: StrictWriter, value: Txid) -> None:
+    w.raw(value, 32)
+
+
+def decode_txid(data: bytes) -> Txid:
+    reader = StrictReader(data)
+    value = read_txid(reader)
+    reader.finish()
+    return value
+
+
+def encode_txid(value: Txid) -> bytes:
+    writer = StrictWriter()
+    write_txid(writer, value)
+    return writer.finish()
+
+
+# Txid*
+Txids = List[Txid]
+
+
+def read_txids(r: StrictReader) -> Txids:
+    return r.list(lambda: read_txid(r))
+
+
+def write_txids(w: StrictWriter, value: Txids) -> None:
+    w.list(value, lambda v0: write_txid(w, v0))
+"
+        ));
+        assert!(!py.contains("class Txid"));
+    }
+
+    #[test]
+    fn identifiers() {
+        let mut ts = type_system![
+            "List" :: { StructField::with("T"), StructField::with("for") },
+            "for" :: { StructField::primitive(PrimitiveType::U8) }
+        ];
+        ts.push_alias(tn("T"), TypeRef::InPlace(TypeConstr::Plain(PrimitiveType::U8))).unwrap();
+        let py = ts.to_python();
+        assert!(py.contains(
+            "class List_2:
+    \"\"\"T, for\"\"\"
+
+    f0: T_2
+    f1: for_2
+
+    @classmethod
+    def read(cls, r: StrictReader) -> List_2:
+        return cls(
+            read_t_2(r),
+            for_2.read(r),
+        )
+"
+        ));
+        assert!(py.contains("T_2 = int\n"));
+        assert!(py.contains("def encode_t_2(value: T_2) -> bytes:"));
+    }
+}
@@ -0,0 +1,601 @@
+// Strict encoding schema library, implementing validation and parsing of strict
+// encoded data against the schema.
+//
+// Written in 2022 by
+//     Dr. Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2022 by LNP/BP Standards Association, Switzerland.
+//
+// You should have received a copy of the MIT License along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Selector language for querying strict-encoded data.
+//!
+//! Selector is a sequence of steps, each of which maps a set of matched values
+//! into a new set:
+//! - `.N` selects structure field number `N`, `.*` selects all present fields;
+//! - `[N]` selects element number `N` of an array, list or set, `[*]` selects all elements;
+//! - `{N}` selects value of the map entry number `N` (in the key order), `{*}` selects values of
+//!   all entries and `{<op> <literal>}` selects values of the entries which key is or contains a
+//!   primitive value matching the predicate;
+//! - `..` selects the value itself and all values nested in it at any depth;
+//! - `?(<selector> <op> <literal>)` keeps only values which have a nested primitive value reachable
+//!   via the relative selector and matching the predicate.
+//!
+//! Predicate operators are `=`, `<`, `>` and `^=` (string prefix); literals are
+//! decimal integer and float numbers and double-quoted strings. For instance,
+//! `.2[*]?(.0 > 100000)` selects all transaction outputs with more than 100000
+//! sats and `..?(^= "lnp")` selects all character values starting with `lnp`.
+
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fmt::{self, Debug, Display, Formatter, Write};
+use std::str::FromStr;
+
+use crate::{DataPath, DataStep, DecodeError, PrimitiveValue, TypeName, TypeSystem, Value};
+
+/// Comparison operator used in selector predicates.
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display)]
+pub enum CmpOp {
+    #[display("=")]
+    Eq,
+
+    #[display("<")]
+    Lt,
+
+    #[display(">")]
+    Gt,
+
+    /// String prefix match
+    #[display("^=")]
+    Prefix,
+}
+
+/// Literal value used in selector predicates.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Literal {
+    /// Integer number, represented by its sign (`true` for negative numbers)
+    /// and absolute value.
+    Int(bool, u128),
+
+    Float(f64),
+
+    Str(String),
+}
+
+impl Display for Literal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Literal::Int(negative, abs) => {
+                if *negative {
+                    f.write_str("-")?;
+                }
+                Display::fmt(abs, f)
+            }
+            Literal::Float(val) => Debug::fmt(val, f),
+            Literal::Str(s) => {
+                f.write_char('"')?;
+                for c in s.chars() {
+                    match c {
+                        '"' => f.write_str("\\\"")?,
+                        '\\' => f.write_str("\\\\")?,
+                        '\n' => f.write_str("\\n")?,
+                        '\t' => f.write_str("\\t")?,
+                        c => f.write_char(c)?,
+                    }
+                }
+                f.write_char('"')
+            }
+        }
+    }
+}
+
+/// Predicate over primitive values.
+#[derive(Clone, PartialEq, Debug, Display)]
+#[display("{op} {literal}")]
+pub struct Cmp {
+    pub op: CmpOp,
+    pub literal: Literal,
+}
+
+impl Cmp {
+    /// Checks whether primitive value matches the predicate. Values which are
+    /// not comparable with the literal never match.
+    pub fn matches(&self, val: &PrimitiveValue) -> bool {
+        let ordering = match (&self.literal, self.op) {
+            (Literal::Str(s), CmpOp::Prefix) => {
+                return val.as_str().map(|v| v.starts_with(s.as_str())).unwrap_or_default()
+            }
+            (_, CmpOp::Prefix) => return false,
+            (Literal::Str(s), _) => val.as_str().map(|v| v.cmp(s)),
+            (Literal::Int(negative, abs), _) if val.ty().is_float() => {
+                let lit = *abs as f64;
+                let lit = if *negative { -lit } else { lit };
+                val.as_f64().and_then(|v| v.partial_cmp(&lit))
+            }
+            (Literal::Int(negative, abs), _) => cmp_int(val, *negative, *abs),
+            (Literal::Float(lit), _) => {
+                int_to_f64(val).or_else(|| val.as_f64()).and_then(|v| v.partial_cmp(lit))
+            }
+        };
+        ordering
+            == Some(match self.op {
+                CmpOp::Eq => Ordering::Equal,
+                CmpOp::Lt => Ordering::Less,
+                CmpOp::Gt => Ordering::Greater,
+                CmpOp::Prefix => unreachable!(),
+            })
+    }
+}
+
+fn cmp_int(val: &PrimitiveValue, negative: bool, abs: u128) -> Option<Ordering> {
+    if !val.ty().is_signed() && !val.ty().is_unsigned() {
+        return None;
+    }
+    let (val_negative, val_abs) = match val.as_int() {
+        Some(int) => int,
+        // Value is wider than any literal
+        None if val.is_negative() => return Some(Ordering::Less),
+        None => return Some(Ordering::Greater),
+    };
+    Some(match (val_negative, negative) {
+        (false, false) => val_abs.cmp(&abs),
+        (true, true) => abs.cmp(&val_abs),
+        // Negative zero literal is equal to zero value
+        _ if val_abs == 0 && abs == 0 => Ordering::Equal,
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+    })
+}
+
+fn int_to_f64(val: &PrimitiveValue) -> Option<f64> {
+    let (negative, abs) = val.as_int()?;
+    let abs = abs as f64;
+    Some(if negative { -abs } else { abs })
+}
+
+/// Relative selector with a predicate, used for filtering values.
+#[derive(Clone, PartialEq, Debug, Display)]
+#[display("?({path} {cmp})")]
+pub struct Filter {
+    pub path: Selector,
+    pub cmp: Cmp,
+}
+
+/// Single step of a [`Selector`].
+#[derive(Clone, PartialEq, Debug)]
+pub enum Step {
+    /// Structure field with a given number or all present fields.
+    Field(Option<u16>),
+
+    /// Array, list or set element with a given index or all elements.
+    Element(Option<u16>),
+
+    /// Value of a map entry with a given position or values of all entries.
+    Entry(Option<u16>),
+
+    /// Values of map entries which keys are or contain a primitive value
+    /// matching the predicate.
+    EntryKey(Cmp),
+
+    /// Recursive descent.
+    Descend,
+
+    /// Filter keeping only values matching the predicate.
+    Filter(Filter),
+}
+
+impl Display for Step {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let fmt_index = |index: &Option<u16>| match index {
+            None => s!("*"),
+            Some(index) => index.to_string(),
+        };
+        match self {
+            Step::Field(index) => write!(f, ".{}", fmt_index(index)),
+            Step::Element(index) => write!(f, "[{}]", fmt_index(index)),
+            Step::Entry(index) => write!(f, "{{{}}}", fmt_index(index)),
+            Step::EntryKey(cmp) => write!(f, "{{{}}}", cmp),
+            Step::Descend => f.write_str(".."),
+            Step::Filter(filter) => Display::fmt(filter, f),
+        }
+    }
+}
+
+/// Query over strict-encoded data; see [module-level documentation](self) for
+/// the syntax.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Selector(Vec<Step>);
+
+impl Display for Selector {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            return f.write_str(".");
+        }
+        for step in &self.0 {
+            Display::fmt(step, f)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<Vec<Step>> for Selector {
+    fn from(steps: Vec<Step>) -> Self { Selector(steps) }
+}
+
+impl<'me> IntoIterator for &'me Selector {
+    type Item = &'me Step;
+    type IntoIter = std::slice::Iter<'me, Step>;
+
+    fn into_iter(self) -> Self::IntoIter { self.0.iter() }
+}
+
+impl Selector {
+    /// Selects all values nested in `value` matching the selector, returning
+    /// them together with their paths relative to `value`.
+    pub fn select<'val>(&self, value: &'val Value) -> Vec<(DataPath, &'val Value)> {
+        let mut matches = vec![(DataPath::new(), value)];
+        for step in &self.0 {
+            let mut next = vec![];
+            let mut seen = HashSet::new();
+            for (path, val) in matches {
+                match step {
+                    Step::Descend => descend(path, val, &mut |path, val| {
+                        if seen.insert(path.clone()) {
+                            next.push((path, val));
+                        }
+                    }),
+                    Step::Filter(filter) => {
+                        let found = filter
+                            .path
+                            .select(val)
+                            .into_iter()
+                            .any(|(_, nested)| contains_match(nested, &filter.cmp, false));
+                        if found {
+                            next.push((path, val));
+                        }
+                    }
+                    step => children(val, &mut |child_path, child| {
+                        let selected = match (step, child_path) {
+                            (Step::Field(index), DataStep::StructField(no))
+                            | (Step::Element(index), DataStep::ArrayIndex(no))
+                            | (Step::Entry(index), DataStep::MapEntry(no)) => {
+                                index.map(|index| index == no).unwrap_or(true)
+                            }
+                            (Step::EntryKey(cmp), DataStep::MapEntry(no)) => match val {
+                                Value::Map(entries) => {
+                                    contains_match(&entries[no as usize].0, cmp, true)
+                                }
+                                _ => false,
+                            },
+                            _ => false,
+                        };
+                        if selected {
+                            next.push((path.with(child_path), child));
+                        }
+                    }),
+                }
+            }
+            matches = next;
+        }
+        matches
+    }
+
+    /// Parses data encoded as an instance of type `root` and selects all values
+    /// matching the selector, returning them together with their paths.
+    pub fn query(
+        &self,
+        ts: &TypeSystem,
+        root: &TypeName,
+        data: impl AsRef<[u8]>,
+    ) -> Result<Vec<(DataPath, Value)>, DecodeError> {
+        let value = ts.decode(root, data)?;
+        Ok(self.select(&value).into_iter().map(|(path, val)| (path, val.clone())).collect())
+    }
+}
+
+/// Checks whether the value is a primitive matching the predicate or, if
+/// `nested` is set, contains such primitive at any depth.
+fn contains_match(val: &Value, cmp: &Cmp, nested: bool) -> bool {
+    let mut found = false;
+    let mut check = |_, val: &Value| {
+        found |= matches!(val, Value::Primitive(prim) if cmp.matches(prim));
+    };
+    if nested {
+        descend(DataPath::new(), val, &mut check);
+    } else {
+        check(DataPath::new(), val);
+    }
+    found
+}
+
+/// Calls `f` for each direct child of the value (map keys excluded).
+fn children<'val>(val: &'val Value, f: &mut impl FnMut(DataStep, &'val Value)) {
+    match val {
+        Value::Primitive(_) => {}
+        Value::Struct(fields) => {
+            for (no, field) in fields.iter().enumerate() {
+                if let Some(field) = field {
+                    f(DataStep::StructField(no as u16), field);
+                }
+            }
+        }
+        Value::List(items) => {
+            for (no, item) in items.iter().enumerate() {
+                f(DataStep::ArrayIndex(no as u16), item);
+            }
+        }
+        Value::Map(entries) => {
+            for (no, (_, val)) in entries.iter().enumerate() {
+                f(DataStep::MapEntry(no as u16), val);
+            }
+        }
+    }
+}
+
+/// Calls `f` for the value itself and all its descendants in pre-order.
+fn descend<'val>(path: DataPath, val: &'val Value, f: &mut impl FnMut(DataPath, &'val Value)) {
+    f(path.clone(), val);
+    children(val, &mut |step, child| descend(path.with(step), child, f));
+}
+
+/// Errors parsing selector string.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum SelectorError {
+    /// unexpected character '{1}' at position {0} of the selector
+    UnexpectedChar(usize, char),
+
+    /// selector ends unexpectedly
+    UnexpectedEnd,
+
+    /// invalid number literal at position {0} of the selector
+    InvalidNumber(usize),
+}
+
+impl FromStr for Selector {
+    type Err = SelectorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            chars: s.chars().collect(),
+            pos: 0,
+        };
+        let selector = parser.selector()?;
+        parser.skip_ws();
+        match parser.peek() {
+            None => Ok(selector),
+            Some(c) => Err(SelectorError::UnexpectedChar(parser.pos, c)),
+        }
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> { self.chars.get(self.pos).copied() }
+
+    fn next(&mut self) -> Result<char, SelectorError> {
+        let c = self.peek().ok_or(SelectorError::UnexpectedEnd)?;
+        self.pos += 1;
+        Ok(c)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), SelectorError> {
+        match self.next()? {
+            c if c == expected => Ok(()),
+            c => Err(SelectorError::UnexpectedChar(self.pos - 1, c)),
+        }
+    }
+
+    fn skip_ws(&mut self) {
+        while matches!(self.peek(), Some(c) if c.is_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn selector(&mut self) -> Result<Selector, SelectorError> {
+        let mut steps = vec![];
+        self.skip_ws();
+        loop {
+            match self.peek() {
+                Some('.') => {
+                    self.pos += 1;
+                    if self.peek() == Some('.') {
+                        self.pos += 1;
+                        steps.push(Step::Descend);
+                    }
+                    match self.peek() {
+                        Some(c) if c == '*' || c.is_ascii_digit() => {
+                            steps.push(Step::Field(self.index()?))
+                        }
+                        // Identity selector or the end of recursive descent
+                        _ => {}
+                    }
+                }
+                Some('[') => {
+                    self.pos += 1;
+                    steps.push(Step::Element(self.index()?));
+                    self.expect(']')?;
+                }
+                Some('{') => {
+                    self.pos += 1;
+                    self.skip_ws();
+                    match self.peek() {
+                        Some(c) if c == '*' || c.is_ascii_digit() => {
+                            steps.push(Step::Entry(self.index()?))
+                        }
+                        _ => steps.push(Step::EntryKey(self.cmp()?)),
+                    }
+                    self.skip_ws();
+                    self.expect('}')?;
+                }
+                Some('?') => {
+                    self.pos += 1;
+                    self.expect('(')?;
+                    let path = self.selector()?;
+                    let cmp = self.cmp()?;
+                    self.skip_ws();
+                    self.expect(')')?;
+                    steps.push(Step::Filter(Filter { path, cmp }));
+                }
+                _ => return Ok(Selector(steps)),
+            }
+        }
+    }
+
+    fn index(&mut self) -> Result<Option<u16>, SelectorError> {
+        if self.peek() == Some('*') {
+            self.pos += 1;
+            return Ok(None);
+        }
+        let start = self.pos;
+        while matches!(self.peek(), Some(c) if c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        let s = self.chars[start..self.pos].iter().collect::<String>();
+        s.parse().map(Some).map_err(|_| SelectorError::InvalidNumber(start))
+    }
+
+    fn cmp(&mut self) -> Result<Cmp, SelectorError> {
+        self.skip_ws();
+        let op = match self.next()? {
+            '=' => CmpOp::Eq,
+            '<' => CmpOp::Lt,
+            '>' => CmpOp::Gt,
+            '^' => {
+                self.expect('=')?;
+                CmpOp::Prefix
+            }
+            c => return Err(SelectorError::UnexpectedChar(self.pos - 1, c)),
+        };
+        self.skip_ws();
+        let literal = self.literal()?;
+        Ok(Cmp { op, literal })
+    }
+
+    fn literal(&mut self) -> Result<Literal, SelectorError> {
+        let start = self.pos;
+        match self.next()? {
+            '"' => {
+                let mut s = String::new();
+                loop {
+                    match self.next()? {
+                        '"' => return Ok(Literal::Str(s)),
+                        '\\' => match self.next()? {
+                            'n' => s.push('\n'),
+                            't' => s.push('\t'),
+                            c @ ('"' | '\\') => s.push(c),
+                            c => return Err(SelectorError::UnexpectedChar(self.pos - 1, c)),
+                        },
+                        c => s.push(c),
+                    }
+                }
+            }
+            c if c == '-' || c.is_ascii_digit() => {
+                while let Some(c) = self.peek() {
+                    match c {
+                        c if c.is_ascii_digit() || c == '.' => self.pos += 1,
+                        'e' | 'E' => {
+                            self.pos += 1;
+                            // exponent may be signed
+                            if matches!(self.peek(), Some('-' | '+')) {
+                                self.pos += 1;
+                            }
+                        }
+                        _ => break,
+                    }
+                }
+                let s = self.chars[start..self.pos].iter().collect::<String>();
+                let err = SelectorError::InvalidNumber(start);
+                if s.contains(['.', 'e', 'E']) {
+                    return s.parse().map(Literal::Float).map_err(|_| err);
+                }
+                let (negative, abs) = match s.strip_prefix('-') {
+                    Some(abs) => (true, abs),
+                    None => (false, s.as_str()),
+                };
+                abs.parse().map(|abs| Literal::Int(negative, abs)).map_err(|_| err)
+            }
+            c => Err(SelectorError::UnexpectedChar(start, c)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::schema::test::{tn, transaction, type_system};
+
+    fn query(selector: &str) -> Vec<String> {
+        let selector = Selector::from_str(selector).unwrap();
+        assert_eq!(Selector::from_str(&selector.to_string()).unwrap(), selector);
+        selector
+            .query(&type_system(), &tn("Transaction"), transaction())
+            .unwrap()
+            .into_iter()
+            .map(|(path, val)| format!("{} = {}", path, val))
+            .collect()
+    }
+
+    #[test]
+    fn wildcards() {
+        assert_eq!(query(".3"), vec![".3 = 0"]);
+        assert_eq!(query(".2[*].0"), vec![".2[0].0 = 100000", ".2[1].0 = 200000"]);
+        assert_eq!(query(".1[1].*.1"), vec![".1[1].0.1 = 0"]);
+        assert_eq!(query(".1[0].2.0[*].0[*]"), vec![".1[0].2.0[0].0[0] = 255"]);
+    }
+
+    #[test]
+    fn descent() {
+        assert_eq!(query("..?(= 255)"), vec![".1[0].2.0[0].0[0] = 255"]);
+        assert_eq!(query("..?(> 150000)"), vec![".2[1].0 = 200000"]);
+        assert_eq!(query(".2[*]?(.0 > 100000)"), vec![".2[1] = (200000, ([]))"]);
+        assert_eq!(query("..[*]?(.0 < 150000.5).0"), vec![".2[0].0 = 100000"]);
+        assert_eq!(query("..?(= -1)").len(), 0);
+    }
+
+    #[test]
+    fn map_entries() {
+        let ts = type_system();
+        let data = [
+            1, 0, 1, 0, b'a', 2, 0, 1, 0, 2, 0, b'a', b'b', 1, 0, 1, 0, b'x', 1, 0, 2, 0, b'c',
+            b'd', 0, 0,
+        ];
+        let run = |selector: &str| {
+            Selector::from_str(selector)
+                .unwrap()
+                .query(&ts, &tn("Meta"), data)
+                .unwrap()
+                .into_iter()
+                .map(|(path, val)| format!("{} = {}", path, val))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(run(".1{*}"), vec![r#".1{0} = (["x"])"#, ".1{1} = ([])"]);
+        assert_eq!(run(".1{1}"), vec![".1{1} = ([])"]);
+        assert_eq!(run(r#".1{= "cd"}"#), vec![".1{1} = ([])"]);
+        assert_eq!(run("..?(^= \"a\")"), vec![r#".0[0] = "a""#]);
+        assert!(run(".*[*]?(> \"a\")").is_empty());
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(Selector::from_str(".0[").unwrap_err(), SelectorError::InvalidNumber(3));
+        assert_eq!(Selector::from_str(".0?(= 1").unwrap_err(), SelectorError::UnexpectedEnd);
+        assert_eq!(Selector::from_str(".0 x").unwrap_err(), SelectorError::UnexpectedChar(3, 'x'));
+        assert_eq!(Selector::from_str("").unwrap().to_string(), ".");
+    }
+
+    #[test]
+    fn parse_exponent() {
+        assert_eq!(
+            Selector::from_str("..?(> 1e-5)").unwrap(),
+            Selector::from_str("..?(> 0.00001)").unwrap()
+        );
+        assert_eq!(query("..?(> 1.5E+5)"), vec![".2[1].0 = 200000"]);
+        assert_eq!(Selector::from_str("..?(> 1e-)").unwrap_err(), SelectorError::InvalidNumber(6));
+    }
+}
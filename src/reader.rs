@@ -0,0 +1,344 @@
+// Strict encoding schema library, implementing validation and parsing of strict
+// encoded data against the schema.
+//
+// Written in 2022 by
+//     Dr. Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2022 by LNP/BP Standards Association, Switzerland.
+//
+// You should have received a copy of the MIT License along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::io::Read;
+
+use crate::cursor::{SchemaCursor, Shape, SortMode};
+use crate::value::check_chars;
+use crate::{
+    CursorError, DataError, DataPath, PrimitiveType, PrimitiveValue, StructType, TypeName,
+    TypeSystem,
+};
+
+/// Pull-style reader of strict-encoded data, which allows to read data items
+/// one by one while ensuring that they are read in the order and with the
+/// types defined by the schema.
+///
+/// The reader does not keep parsed data in memory; the only data it buffers
+/// are encodings of the current set element or map key, required to check the
+/// lexicographic order of set elements and map keys.
+#[derive(Debug)]
+pub struct SchemaReader<'ts, R: Read> {
+    cursor: SchemaCursor<'ts>,
+    reader: R,
+    pos: u64,
+}
+
+macro_rules! read_num {
+    ($name:ident, $ty:ty, $prim:ident) => {
+        pub fn $name(&mut self) -> Result<$ty, CursorError> {
+            let data = self.read_typed(PrimitiveType::$prim, stringify!($name))?;
+            let mut buf = [0u8; std::mem::size_of::<$ty>()];
+            buf.copy_from_slice(&data);
+            Ok(<$ty>::from_le_bytes(buf))
+        }
+    };
+}
+
+impl<'ts, R: Read> SchemaReader<'ts, R> {
+    /// Constructs reader for the data encoding an instance of type `root`.
+    pub fn new(ts: &'ts TypeSystem, root: &'ts TypeName, reader: R) -> Result<Self, CursorError> {
+        Ok(SchemaReader {
+            cursor: SchemaCursor::new(ts, root, SortMode::Check)?,
+            reader,
+            pos: 0,
+        })
+    }
+
+    /// Returns path to the current data item.
+    #[inline]
+    pub fn path(&self) -> &DataPath { self.cursor.path() }
+
+    /// Returns number of bytes read so far.
+    #[inline]
+    pub fn position(&self) -> u64 { self.pos }
+
+    /// Detects whether the root data item was read completely.
+    #[inline]
+    pub fn is_finished(&self) -> bool { self.cursor.is_finished() }
+
+    /// Returns the underlying reader if the root data item was read
+    /// completely.
+    pub fn finish(self) -> Result<R, CursorError> {
+        if !self.is_finished() {
+            return Err(self.cursor.mismatch("finish"));
+        }
+        Ok(self.reader)
+    }
+
+    fn read_exact(&mut self, len: usize) -> Result<Vec<u8>, CursorError> {
+        let mut data = vec![0u8; len];
+        self.reader.read_exact(&mut data).map_err(|err| self.cursor.data_error(err))?;
+        self.cursor.record(&data);
+        self.pos += len as u64;
+        Ok(data)
+    }
+
+    fn read_len(&mut self) -> Result<u16, CursorError> {
+        let data = self.read_exact(2)?;
+        Ok(u16::from_le_bytes([data[0], data[1]]))
+    }
+
+    /// Enters the structure which is the current data item.
+    pub fn enter_struct(&mut self) -> Result<&'ts StructType, CursorError> {
+        match self.cursor.begin("enter_struct", |shape| matches!(shape, Shape::Struct(_)))? {
+            Shape::Struct(name) => self.cursor.enter_struct(name),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Moves to the next field of the current structure. Returns `None` if
+    /// all fields were read, or whether the field value is present otherwise.
+    /// Field values of non-optional fields are always present.
+    pub fn next_field(&mut self) -> Result<Option<bool>, CursorError> {
+        let field = match self.cursor.next_field("next_field")? {
+            None => return Ok(None),
+            Some(field) => field,
+        };
+        if !field.optional {
+            return Ok(Some(true));
+        }
+        match self.read_exact(1)?[0] {
+            0 => {
+                self.cursor.skip_field();
+                Ok(Some(false))
+            }
+            1 => Ok(Some(true)),
+            tag => Err(self.cursor.data_error(DataError::OptionalTag(tag))),
+        }
+    }
+
+    /// Enters the array, list or set which is the current data item, returning
+    /// number of its elements.
+    pub fn enter_list(&mut self) -> Result<u16, CursorError> {
+        let shape = self.cursor.begin("enter_list", |shape| {
+            matches!(shape, Shape::Array(..) | Shape::List(_) | Shape::Set(_))
+        })?;
+        let len = match shape {
+            Shape::Array(len, _) => len,
+            _ => self.read_len()?,
+        };
+        self.cursor.enter_collection(shape, len);
+        Ok(len)
+    }
+
+    /// Enters the map which is the current data item, returning number of its
+    /// entries. Each entry is read as a key data item followed by a value
+    /// data item.
+    pub fn enter_map(&mut self) -> Result<u16, CursorError> {
+        let shape = self.cursor.begin("enter_map", |shape| matches!(shape, Shape::Map(..)))?;
+        let len = self.read_len()?;
+        self.cursor.enter_collection(shape, len);
+        Ok(len)
+    }
+
+    /// Leaves the current structure or collection; all of its fields or
+    /// elements must be read or skipped before.
+    pub fn leave(&mut self) -> Result<(), CursorError> { self.cursor.leave("leave").map(|_| ()) }
+
+    /// Reads the current data item, which must be of a primitive type.
+    pub fn read_primitive(&mut self) -> Result<PrimitiveValue, CursorError> {
+        let ty = match self.cursor.pending() {
+            Some(Shape::Primitive(ty)) => *ty,
+            _ => return Err(self.cursor.mismatch("read_primitive")),
+        };
+        let data = self.read_typed(ty, "read_primitive")?;
+        Ok(PrimitiveValue::with(ty, data).expect("data length matches primitive type"))
+    }
+
+    fn read_typed(&mut self, ty: PrimitiveType, op: &'static str) -> Result<Vec<u8>, CursorError> {
+        self.cursor.begin(op, |shape| *shape == Shape::Primitive(ty))?;
+        let len = match ty.fixed_size() {
+            Some(len) => len,
+            None => self.read_len()?,
+        };
+        let data = self.read_exact(len as usize)?;
+        check_chars(ty, &data).map_err(|err| self.cursor.data_error(err))?;
+        self.cursor.complete()?;
+        Ok(data)
+    }
+
+    read_num!(read_u8, u8, U8);
+    read_num!(read_u16, u16, U16);
+    read_num!(read_u32, u32, U32);
+    read_num!(read_u64, u64, U64);
+    read_num!(read_u128, u128, U128);
+    read_num!(read_i8, i8, I8);
+    read_num!(read_i16, i16, I16);
+    read_num!(read_i32, i32, I32);
+    read_num!(read_i64, i64, I64);
+    read_num!(read_i128, i128, I128);
+    read_num!(read_f32, f32, F32);
+    read_num!(read_f64, f64, F64);
+
+    /// Reads ASCII character data, checking that they contain only ASCII
+    /// characters.
+    pub fn read_ascii(&mut self) -> Result<String, CursorError> {
+        let data = self.read_typed(PrimitiveType::AsciiChar, "read_ascii")?;
+        Ok(String::from_utf8(data).expect("ASCII string is a valid UTF-8"))
+    }
+
+    /// Reads Unicode character data, checking that they are valid UTF-8.
+    pub fn read_unicode(&mut self) -> Result<String, CursorError> {
+        let data = self.read_typed(PrimitiveType::UnicodeChar, "read_unicode")?;
+        Ok(String::from_utf8(data).expect("data are checked to be a valid UTF-8"))
+    }
+
+    /// Skips the current data item, including all data nested in it. The
+    /// skipped data are still checked against the schema.
+    pub fn skip(&mut self) -> Result<(), CursorError> {
+        match self.cursor.pending() {
+            None => Err(self.cursor.mismatch("skip")),
+            Some(Shape::Primitive(_)) => self.read_primitive().map(|_| ()),
+            Some(Shape::Struct(_)) => {
+                self.enter_struct()?;
+                while let Some(present) = self.next_field()? {
+                    if present {
+                        self.skip()?;
+                    }
+                }
+                self.leave()
+            }
+            Some(Shape::Map(..)) => {
+                let len = self.enter_map()?;
+                for _ in 0..len as u32 * 2 {
+                    self.skip()?;
+                }
+                self.leave()
+            }
+            Some(_) => {
+                let len = self.enter_list()?;
+                for _ in 0..len {
+                    self.skip()?;
+                }
+                self.leave()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::schema::test::{tn, transaction, type_system};
+
+    #[test]
+    fn read_transaction() {
+        let ts = type_system();
+        let root = tn("Transaction");
+        let data = transaction();
+        let mut reader = SchemaReader::new(&ts, &root, data.as_slice()).unwrap();
+
+        reader.enter_struct().unwrap();
+        assert_eq!(reader.next_field().unwrap(), Some(true));
+        assert_eq!(reader.read_u32().unwrap(), 1);
+        assert_eq!(reader.next_field().unwrap(), Some(true));
+        assert_eq!(reader.enter_list().unwrap(), 2);
+        reader.skip().unwrap();
+        assert_eq!(reader.path().to_string(), ".1[1]");
+        reader.skip().unwrap();
+        reader.leave().unwrap();
+
+        reader.next_field().unwrap();
+        assert_eq!(reader.enter_list().unwrap(), 2);
+        reader.enter_struct().unwrap();
+        reader.next_field().unwrap();
+        assert_eq!(reader.read_u64().unwrap(), 100_000);
+        reader.next_field().unwrap();
+        reader.enter_struct().unwrap();
+        reader.next_field().unwrap();
+        assert_eq!(reader.enter_list().unwrap(), 1);
+        assert_eq!(reader.path().to_string(), ".2[0].1.0[0]");
+        assert_eq!(reader.read_u8().unwrap(), 0x51);
+        reader.leave().unwrap();
+        assert_eq!(reader.next_field().unwrap(), None);
+        reader.leave().unwrap();
+        assert_eq!(reader.next_field().unwrap(), None);
+        reader.leave().unwrap();
+        reader.skip().unwrap();
+        reader.leave().unwrap();
+
+        reader.next_field().unwrap();
+        assert_eq!(reader.read_u32().unwrap(), 0);
+        assert_eq!(reader.next_field().unwrap(), None);
+        reader.leave().unwrap();
+        assert_eq!(reader.position(), data.len() as u64);
+        reader.finish().unwrap();
+    }
+
+    #[test]
+    fn schema_violations() {
+        let ts = type_system();
+        let root = tn("Transaction");
+        let data = transaction();
+        let mut reader = SchemaReader::new(&ts, &root, data.as_slice()).unwrap();
+
+        assert_eq!(
+            reader.read_u32().unwrap_err().to_string(),
+            "read_u32 is not allowed at ., where the schema expects Transaction"
+        );
+        reader.enter_struct().unwrap();
+        reader.next_field().unwrap();
+        assert_eq!(
+            reader.read_u16().unwrap_err().to_string(),
+            "read_u16 is not allowed at .0, where the schema expects U32"
+        );
+        reader.skip().unwrap();
+        reader.next_field().unwrap();
+        reader.enter_list().unwrap();
+        reader.skip().unwrap();
+        assert_eq!(
+            reader.leave().unwrap_err().to_string(),
+            ".1 is left before all of its 2 items were processed (1 done)"
+        );
+    }
+
+    #[test]
+    fn unsorted_map() {
+        let ts = type_system();
+        let root = tn("Meta");
+        let data = [0, 0, 2, 0, 1, 0, 1, 0, b'k', 0, 0, 1, 0, 1, 0, b'a', 0, 0];
+        let mut reader = SchemaReader::new(&ts, &root, &data[..]).unwrap();
+        reader.enter_struct().unwrap();
+        reader.next_field().unwrap();
+        reader.skip().unwrap();
+        reader.next_field().unwrap();
+        assert_eq!(reader.enter_map().unwrap(), 2);
+        reader.skip().unwrap();
+        reader.skip().unwrap();
+        assert_eq!(
+            reader.skip().unwrap_err().to_string(),
+            "map keys are not sorted in lexicographic order at .1{1}<UnicodeChar[]>"
+        );
+    }
+
+    #[test]
+    fn map_entries_not_buffered() {
+        let ts = type_system();
+        let root = tn("Meta");
+        let mut data = vec![0, 0, 1, 0, 1, 0, 1, 0, b'k', 0xE8, 0x03];
+        for _ in 0..1000 {
+            data.extend([1, 0, b'v']);
+        }
+        let mut reader = SchemaReader::new(&ts, &root, &data[..]).unwrap();
+        reader.enter_struct().unwrap();
+        reader.next_field().unwrap();
+        reader.skip().unwrap();
+        reader.next_field().unwrap();
+        assert_eq!(reader.enter_map().unwrap(), 1);
+        reader.skip().unwrap();
+        reader.skip().unwrap();
+        assert_eq!(reader.cursor.buffered(), 0);
+        reader.leave().unwrap();
+        reader.leave().unwrap();
+        reader.finish().unwrap();
+    }
+}
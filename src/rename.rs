@@ -0,0 +1,137 @@
+// Strict encoding schema library, implementing validation and parsing of strict
+// encoded data against the schema.
+//
+// Written in 2022 by
+//     Dr. Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2022 by LNP/BP Standards Association, Switzerland.
+//
+// You should have received a copy of the MIT License along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{Error, TypeName, TypeSystem};
+
+/// Structure field which type reference was rewritten by renaming.
+#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display)]
+#[display("{ty}.{field}: {from} -> {into}")]
+pub struct TouchedField {
+    /// Name of the structure containing the field, after the renaming.
+    pub ty: TypeName,
+
+    /// Number of the field in the structure.
+    pub field: u16,
+
+    /// Referenced type name before the renaming.
+    pub from: TypeName,
+
+    /// Referenced type name after the renaming.
+    pub into: TypeName,
+}
+
+impl TypeSystem {
+    /// Renames type `old` into `new`, rewriting all references to it. Fails if
+    /// `old` is not defined or if `new` is already used by some other type.
+    ///
+    /// Returns list of the fields which type references were rewritten.
+    pub fn rename(&mut self, old: &TypeName, new: TypeName) -> Result<Vec<TouchedField>, Error> {
+        self.rename_all(&bmap! { old.clone() => new })
+    }
+
+    /// Renames types according to the map from the old names to the new ones,
+    /// rewriting all references to them. All types are renamed
+    /// simultaneously, thus types may exchange their names.
+    ///
+    /// Fails, leaving the type system unchanged, if some of the renamed types
+    /// is not defined, or if some new name is used by a type which is not
+    /// renamed or is given to more than one type.
+    ///
+    /// Returns list of the fields which type references were rewritten.
+    pub fn rename_all(
+        &mut self,
+        renames: &BTreeMap<TypeName, TypeName>,
+    ) -> Result<Vec<TouchedField>, Error> {
+        let mut names = BTreeSet::new();
+        for (old, new) in renames {
+            if self.get(old).is_none() {
+                return Err(Error::UnknownType(old.clone()));
+            }
+            if !names.insert(new) || (self.get(new).is_some() && !renames.contains_key(new)) {
+                return Err(Error::DuplicatedType(new.clone()));
+            }
+        }
+
+        let rename = |name: &TypeName| renames.get(name).unwrap_or(name).clone();
+        let mut renamed = TypeSystem::new();
+        let mut touched = vec![];
+        for (name, ty) in &*self {
+            for (no, field) in ty.into_iter().enumerate() {
+                let from = match field.ty.type_name() {
+                    Some(from) if renames.contains_key(from) => from,
+                    _ => continue,
+                };
+                touched.push(TouchedField {
+                    ty: rename(name),
+                    field: no as u16,
+                    from: from.clone(),
+                    into: rename(from),
+                });
+            }
+            renamed.push_decl(rename(name), ty.map_refs(rename), self.is_alias(name))?;
+        }
+        touched.sort();
+        *self = renamed;
+        Ok(touched)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::schema::test::{tn, type_system};
+
+    #[test]
+    fn rename() {
+        let mut ts = type_system();
+        let touched = ts.rename(&tn("Bytes"), tn("Script")).unwrap();
+        assert_eq!(touched.iter().map(TouchedField::to_string).collect::<Vec<_>>(), vec![
+            "Input.1: Bytes -> Script",
+            "Output.1: Bytes -> Script",
+            "Witness.0: Bytes -> Script"
+        ]);
+        assert!(ts.get(&tn("Bytes")).is_none());
+        assert_eq!(ts.get(&tn("Witness")).unwrap().to_string(), "Script*");
+        assert_eq!(ts.len(), type_system().len());
+        ts.check().unwrap();
+
+        let mut ts = type_system();
+        assert!(ts.rename(&tn("Meta"), tn("Metadata")).unwrap().is_empty());
+        assert_eq!(ts.rename(&tn("Block"), tn("Blocks")), Err(Error::UnknownType(tn("Block"))));
+        assert_eq!(ts.rename(&tn("Bytes"), tn("Txid")), Err(Error::DuplicatedType(tn("Txid"))));
+    }
+
+    #[test]
+    fn rename_all() {
+        let mut ts = type_system();
+        let touched = ts
+            .rename_all(&bmap! {
+                tn("Input") => tn("Output"),
+                tn("Output") => tn("Input"),
+                tn("Txid") => tn("TxId")
+            })
+            .unwrap();
+        assert_eq!(touched.len(), 3);
+        assert_eq!(ts.get(&tn("Transaction")).unwrap().to_string(), "U32, Output*, Input*, U32");
+        assert_eq!(ts.get(&tn("OutPoint")).unwrap().to_string(), "TxId, U16");
+        assert_eq!(ts.get(&tn("Input")), type_system().get(&tn("Output")));
+        ts.check().unwrap();
+
+        let mut ts = type_system();
+        assert_eq!(
+            ts.rename_all(&bmap! { tn("Input") => tn("Item"), tn("Output") => tn("Item") }),
+            Err(Error::DuplicatedType(tn("Item")))
+        );
+        assert_eq!(ts, type_system());
+    }
+}
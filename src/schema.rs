@@ -9,13 +9,15 @@
 // You should have received a copy of the MIT License along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
+use std::collections::BTreeSet;
 use std::fmt::{self, Debug, Display, Formatter};
 use std::hash::Hash;
 use std::io::Read;
+use std::str::FromStr;
 
 use strict_encoding::{StrictDecode, StrictEncode};
 
-use crate::{AsciiString, OversizeError, StrictMap, StrictSet, StrictVec};
+use crate::{AsciiString, DataStep, OversizeError, StrictMap, StrictSet, StrictVec};
 
 pub type TypeName = AsciiString<1, 32>;
 
@@ -56,6 +58,74 @@ pub enum PrimitiveType {
     UnicodeChar = 0xFF,
 }
 
+impl PrimitiveType {
+    /// All primitive types, ordered by their encoding.
+    pub const ALL: [PrimitiveType; 26] = [
+        PrimitiveType::U8,
+        PrimitiveType::U16,
+        PrimitiveType::U32,
+        PrimitiveType::U64,
+        PrimitiveType::U128,
+        PrimitiveType::U256,
+        PrimitiveType::U512,
+        PrimitiveType::U1024,
+        PrimitiveType::I8,
+        PrimitiveType::I16,
+        PrimitiveType::I32,
+        PrimitiveType::I64,
+        PrimitiveType::I128,
+        PrimitiveType::I256,
+        PrimitiveType::I512,
+        PrimitiveType::I1024,
+        PrimitiveType::F16b,
+        PrimitiveType::F16,
+        PrimitiveType::F32,
+        PrimitiveType::F64,
+        PrimitiveType::F80,
+        PrimitiveType::F128,
+        PrimitiveType::F256,
+        PrimitiveType::F512,
+        PrimitiveType::AsciiChar,
+        PrimitiveType::UnicodeChar,
+    ];
+
+    /// Returns number of bytes taken by the encoded primitive value, or `None`
+    /// for character types, which are encoded with a 16-bit length prefix
+    /// followed by the character data.
+    pub fn fixed_size(self) -> Option<u16> {
+        Some(match self {
+            PrimitiveType::U8 | PrimitiveType::I8 => 1,
+            PrimitiveType::U16 | PrimitiveType::I16 => 2,
+            PrimitiveType::U32 | PrimitiveType::I32 => 4,
+            PrimitiveType::U64 | PrimitiveType::I64 => 8,
+            PrimitiveType::U128 | PrimitiveType::I128 => 16,
+            PrimitiveType::U256 | PrimitiveType::I256 => 32,
+            PrimitiveType::U512 | PrimitiveType::I512 => 64,
+            PrimitiveType::U1024 | PrimitiveType::I1024 => 128,
+            PrimitiveType::F16b | PrimitiveType::F16 => 2,
+            PrimitiveType::F32 => 4,
+            PrimitiveType::F64 => 8,
+            PrimitiveType::F80 => 10,
+            PrimitiveType::F128 => 16,
+            PrimitiveType::F256 => 32,
+            PrimitiveType::F512 => 64,
+            PrimitiveType::AsciiChar | PrimitiveType::UnicodeChar => return None,
+        })
+    }
+
+    #[inline]
+    pub fn is_unsigned(self) -> bool { (self as u8) < 0x10 }
+
+    #[inline]
+    pub fn is_signed(self) -> bool { (0x10..0x20).contains(&(self as u8)) }
+
+    #[inline]
+    pub fn is_float(self) -> bool { (0x30..0x40).contains(&(self as u8)) }
+
+    #[inline]
+    pub fn is_char(self) -> bool { self.fixed_size().is_none() }
+}
+
 #[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(crate = "serde_crate"))]
 #[derive(StrictEncode, StrictDecode)]
@@ -75,6 +145,7 @@ impl Display for StructField {
 }
 
 impl StructField {
+    #[allow(clippy::unnecessary_fallible_conversions)]
     pub fn with(type_name: &'static str) -> Self {
         StructField {
             ty: type_name.try_into().expect("invalid struct type name"),
@@ -169,6 +240,23 @@ impl Display for StructType {
 impl StructType {
     #[doc(hidden)]
     pub unsafe fn from_unchecked(data: StrictVec<StructField, 1>) -> StructType { Self(data) }
+
+    /// Returns copy of the structure in which the names of the types
+    /// referenced by the fields are replaced by `f`.
+    pub(crate) fn map_refs(&self, f: impl Fn(&TypeName) -> TypeName) -> StructType {
+        let fields = self
+            .0
+            .iter()
+            .cloned()
+            .map(|mut field| {
+                if let Some(name) = field.ty.type_name_mut() {
+                    *name = f(name);
+                }
+                field
+            })
+            .collect::<Vec<_>>();
+        StructType(StrictVec::try_from(fields).expect("structure fields are not changed"))
+    }
 }
 
 impl<'me> IntoIterator for &'me StructType {
@@ -178,6 +266,22 @@ impl<'me> IntoIterator for &'me StructType {
     fn into_iter(self) -> Self::IntoIter { self.0.iter() }
 }
 
+/// Error parsing type from its string representation.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Display, Error)]
+#[display("invalid type `{0}`")]
+pub struct TypeParseError(pub String);
+
+impl FromStr for PrimitiveType {
+    type Err = TypeParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PrimitiveType::ALL
+            .into_iter()
+            .find(|ty| ty.to_string() == s)
+            .ok_or_else(|| TypeParseError(s.to_owned()))
+    }
+}
+
 #[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display, From)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(crate = "serde_crate"))]
 #[derive(StrictEncode, StrictDecode)]
@@ -203,6 +307,39 @@ impl KeyType {
     pub fn ascii_string() -> Self { KeyType::List(PrimitiveType::AsciiChar) }
 
     pub fn unicode_string() -> Self { KeyType::List(PrimitiveType::UnicodeChar) }
+
+    /// Returns primitive type of the key items.
+    pub fn primitive(self) -> PrimitiveType {
+        match self {
+            KeyType::Primitive(ty) | KeyType::Array(_, ty) | KeyType::List(ty) => ty,
+        }
+    }
+}
+
+impl FromStr for KeyType {
+    type Err = TypeParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || TypeParseError(s.to_owned());
+        match s.strip_suffix(']').and_then(|s| s.split_once('[')) {
+            None => PrimitiveType::from_str(s).map(KeyType::Primitive),
+            Some((ty, "")) => PrimitiveType::from_str(ty).map(KeyType::List),
+            Some((ty, len)) => Ok(KeyType::Array(
+                len.parse().map_err(|_| err())?,
+                PrimitiveType::from_str(ty).map_err(|_| err())?,
+            )),
+        }
+    }
+}
+
+impl From<KeyType> for TypeConstr<PrimitiveType> {
+    fn from(key: KeyType) -> Self {
+        match key {
+            KeyType::Primitive(ty) => TypeConstr::Plain(ty),
+            KeyType::Array(len, ty) => TypeConstr::Array(len, ty),
+            KeyType::List(ty) => TypeConstr::List(ty),
+        }
+    }
 }
 
 #[derive(Wrapper, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, From)]
@@ -223,11 +360,14 @@ impl Display for UnionType {
     }
 }
 
-#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display, From)]
+/// Maximal number of the constructors nested into each other accepted by
+/// the strict decoder of [`TypeRef`], protecting it from the stack overflow.
+const MAX_NESTING_DEPTH: usize = 32;
+
+#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, From)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(crate = "serde_crate"))]
-#[derive(StrictEncode, StrictDecode)]
+#[derive(StrictEncode)]
 #[strict_encoding(by_order, repr = u8)]
-#[display(inner)]
 pub enum TypeRef {
     #[from]
     #[from(PrimitiveType)]
@@ -236,6 +376,61 @@ pub enum TypeRef {
     #[from]
     #[from(TypeName)]
     NameRef(TypeConstr<TypeName>),
+
+    /// Constructor applied to a type reference which is itself a constructor,
+    /// like a list of byte strings. Use [`TypeRef::nested`] to construct the
+    /// canonical representation. Strict decoding fails for type references
+    /// nesting more than 32 constructors.
+    Nested(TypeConstr<Box<TypeRef>>),
+}
+
+/// Alternate form (`{:#}`) puts the references to constructors other than
+/// plain types in parentheses; it is used for the items of arrays and lists,
+/// so `({U8} -> U8)*` and `{U8} -> U8*` are told apart.
+impl Display for TypeRef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let grouped = f.alternate()
+            && !matches!(
+                self,
+                TypeRef::InPlace(TypeConstr::Plain(_))
+                    | TypeRef::NameRef(TypeConstr::Plain(_))
+                    | TypeRef::Nested(TypeConstr::Plain(_))
+            );
+        if grouped {
+            f.write_str("(")?;
+        }
+        match self {
+            TypeRef::InPlace(constr) => write!(f, "{}", constr)?,
+            TypeRef::NameRef(constr) => write!(f, "{}", constr)?,
+            TypeRef::Nested(constr) => write!(f, "{}", constr)?,
+        }
+        if grouped {
+            f.write_str(")")?;
+        }
+        Ok(())
+    }
+}
+
+impl StrictEncode for Box<TypeRef> {
+    fn strict_encode<E: std::io::Write>(&self, mut e: E) -> Result<usize, strict_encoding::Error> {
+        // Type erasure stops the otherwise infinite monomorphisation of the
+        // recursive encoder
+        self.as_ref().strict_encode(&mut e as &mut dyn std::io::Write)
+    }
+}
+
+impl StrictDecode for TypeRef {
+    fn strict_decode<D: Read>(mut d: D) -> Result<Self, strict_encoding::Error> {
+        // Type erasure stops the otherwise infinite monomorphisation of the
+        // recursive decoder
+        TypeRef::decode_nested(&mut d, 0)
+    }
+}
+
+impl StrictDecode for Box<TypeRef> {
+    fn strict_decode<D: Read>(d: D) -> Result<Self, strict_encoding::Error> {
+        TypeRef::strict_decode(d).map(Box::new)
+    }
 }
 
 impl From<&'static str> for TypeRef {
@@ -245,10 +440,101 @@ impl From<&'static str> for TypeRef {
 }
 
 impl TypeRef {
+    /// Decodes type reference located at the `depth` of nested constructors.
+    fn decode_nested(d: &mut dyn Read, depth: usize) -> Result<Self, strict_encoding::Error> {
+        match u8::strict_decode(&mut *d)? {
+            0x00 => TypeConstr::strict_decode(d).map(TypeRef::InPlace),
+            0x01 => TypeConstr::strict_decode(d).map(TypeRef::NameRef),
+            0x02 if depth >= MAX_NESTING_DEPTH => Err(strict_encoding::Error::DataIntegrityError(
+                format!("type reference nests more than {} constructors", MAX_NESTING_DEPTH),
+            )),
+            0x02 => TypeConstr::decode_with(d, |d| {
+                TypeRef::decode_nested(&mut **d, depth + 1).map(Box::new)
+            })
+            .map(TypeRef::Nested),
+            other => Err(strict_encoding::Error::EnumValueNotKnown("TypeRef", other as usize)),
+        }
+    }
+
     pub fn new(name: &'static str) -> TypeRef {
         TypeRef::NameRef(TypeConstr::Plain(name.try_into().expect("invalid type name")))
     }
 
+    /// Applies constructor to the type reference it contains, representing
+    /// the result without nesting whenever the item is a plain type.
+    pub fn nested(constr: TypeConstr<TypeRef>) -> TypeRef {
+        match constr.inner().clone() {
+            item if matches!(constr, TypeConstr::Plain(_)) => item,
+            TypeRef::InPlace(TypeConstr::Plain(ty)) => TypeRef::InPlace(constr.map(|_| ty)),
+            TypeRef::NameRef(TypeConstr::Plain(name)) => TypeRef::NameRef(constr.map(|_| name)),
+            _ => TypeRef::Nested(constr.map(Box::new)),
+        }
+    }
+
+    /// Returns name of the referenced type, if any, looking into the nested
+    /// constructors.
+    pub fn type_name(&self) -> Option<&TypeName> {
+        match self {
+            TypeRef::InPlace(_) => None,
+            TypeRef::NameRef(constr) => Some(constr.type_name()),
+            TypeRef::Nested(constr) => constr.inner().type_name(),
+        }
+    }
+
+    pub(crate) fn type_name_mut(&mut self) -> Option<&mut TypeName> {
+        match self {
+            TypeRef::InPlace(_) => None,
+            TypeRef::NameRef(constr) => Some(constr.inner_mut()),
+            TypeRef::Nested(constr) => constr.inner_mut().type_name_mut(),
+        }
+    }
+
+    /// Returns type of the data item reached by the data path `step` from the
+    /// data of this type: an item of array, list or set, a map value or a map
+    /// key. Steps into the fields of the referenced structures give `None`.
+    pub(crate) fn item(&self, step: &DataStep) -> Option<TypeRef> {
+        fn item<'a, T>(constr: &'a TypeConstr<T>, step: &DataStep) -> Option<&'a T>
+        where T: Clone + Ord + Eq + Hash + Debug {
+            match (constr, step) {
+                (
+                    TypeConstr::Array(_, ty) | TypeConstr::List(ty) | TypeConstr::Set(ty),
+                    DataStep::ArrayIndex(_),
+                )
+                | (TypeConstr::Map(_, ty), DataStep::MapEntry(_)) => Some(ty),
+                _ => None,
+            }
+        }
+
+        if let DataStep::MapKey(key) = step {
+            return Some(TypeRef::InPlace(TypeConstr::from(*key)));
+        }
+        match self {
+            TypeRef::InPlace(constr) => {
+                item(constr, step).map(|ty| TypeRef::InPlace(TypeConstr::Plain(*ty)))
+            }
+            TypeRef::NameRef(constr) => {
+                item(constr, step).map(|name| TypeRef::NameRef(TypeConstr::Plain(name.clone())))
+            }
+            TypeRef::Nested(constr) => item(constr, step).map(|ty| ty.as_ref().clone()),
+        }
+    }
+
+    /// Checks that the reference is represented as constructed by
+    /// [`TypeRef::nested`], i.e. constructors are nested only when the item
+    /// is a constructor.
+    pub fn is_canonical(&self) -> bool {
+        match self {
+            TypeRef::InPlace(_) | TypeRef::NameRef(_) => true,
+            TypeRef::Nested(TypeConstr::Plain(_)) => false,
+            TypeRef::Nested(constr) => match constr.inner().as_ref() {
+                TypeRef::InPlace(TypeConstr::Plain(_)) | TypeRef::NameRef(TypeConstr::Plain(_)) => {
+                    false
+                }
+                item => item.is_canonical(),
+            },
+        }
+    }
+
     pub fn bytes() -> TypeRef { TypeRef::InPlace(TypeConstr::List(PrimitiveType::U8)) }
 
     pub fn ascii_string() -> TypeRef {
@@ -288,6 +574,45 @@ where T: Clone + Ord + Eq + Hash + Debug
     Map(KeyType, T),
 }
 
+impl<T> TypeConstr<T>
+where T: Clone + Ord + Eq + Hash + Debug
+{
+    /// Returns type of the items.
+    pub fn inner(&self) -> &T {
+        match self {
+            TypeConstr::Plain(ty)
+            | TypeConstr::Array(_, ty)
+            | TypeConstr::List(ty)
+            | TypeConstr::Set(ty)
+            | TypeConstr::Map(_, ty) => ty,
+        }
+    }
+
+    /// Returns mutable reference to the type of the items.
+    pub fn inner_mut(&mut self) -> &mut T {
+        match self {
+            TypeConstr::Plain(ty)
+            | TypeConstr::Array(_, ty)
+            | TypeConstr::List(ty)
+            | TypeConstr::Set(ty)
+            | TypeConstr::Map(_, ty) => ty,
+        }
+    }
+
+    /// Returns the same constructor applied to the type of the items
+    /// converted by `f`.
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> TypeConstr<U>
+    where U: Clone + Ord + Eq + Hash + Debug {
+        match self {
+            TypeConstr::Plain(ty) => TypeConstr::Plain(f(ty)),
+            TypeConstr::Array(len, ty) => TypeConstr::Array(len, f(ty)),
+            TypeConstr::List(ty) => TypeConstr::List(f(ty)),
+            TypeConstr::Set(ty) => TypeConstr::Set(f(ty)),
+            TypeConstr::Map(key, ty) => TypeConstr::Map(key, f(ty)),
+        }
+    }
+}
+
 impl TypeConstr<TypeName> {
     pub fn type_name(&self) -> &TypeName {
         match self {
@@ -306,25 +631,12 @@ where T: Clone + Ord + Eq + Hash + Debug + Display
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
             TypeConstr::Plain(ty) => Display::fmt(ty, f),
-            TypeConstr::Array(size, ty) => {
-                Display::fmt(ty, f)?;
-                write!(f, "*{}", size)
-            }
-            TypeConstr::List(ty) => {
-                Display::fmt(ty, f)?;
-                f.write_str("*")
-            }
-            TypeConstr::Set(ty) => {
-                f.write_str("{")?;
-                Display::fmt(ty, f)?;
-                f.write_str("}")
-            }
-            TypeConstr::Map(key, ty) => {
-                f.write_str("{")?;
-                Display::fmt(key, f)?;
-                f.write_str("} -> ")?;
-                Display::fmt(ty, f)
-            }
+            // items of the postfix constructors are formatted in the alternate
+            // form, grouping the nested constructors; see `Display` for `TypeRef`
+            TypeConstr::Array(size, ty) => write!(f, "{:#}*{}", ty, size),
+            TypeConstr::List(ty) => write!(f, "{:#}*", ty),
+            TypeConstr::Set(ty) => write!(f, "{{{}}}", ty),
+            TypeConstr::Map(key, ty) => write!(f, "{{{}}} -> {}", key, ty),
         }
     }
 }
@@ -356,20 +668,26 @@ where T: Clone + Ord + Eq + Hash + Debug + StrictEncode
 impl<T> StrictDecode for TypeConstr<T>
 where T: Clone + Ord + Eq + Hash + Debug + StrictDecode
 {
-    fn strict_decode<D: Read>(mut d: D) -> Result<Self, strict_encoding::Error> {
+    fn strict_decode<D: Read>(d: D) -> Result<Self, strict_encoding::Error> {
+        TypeConstr::decode_with(d, |d| T::strict_decode(d))
+    }
+}
+
+impl<T> TypeConstr<T>
+where T: Clone + Ord + Eq + Hash + Debug
+{
+    /// Decodes constructor, decoding its inner type with `inner`.
+    fn decode_with<D: Read>(
+        mut d: D,
+        mut inner: impl FnMut(&mut D) -> Result<T, strict_encoding::Error>,
+    ) -> Result<Self, strict_encoding::Error> {
         let ty = u8::strict_decode(&mut d)?;
         Ok(match ty {
-            0x00 => Self::Plain(StrictDecode::strict_decode(&mut d)?),
-            0x10 => Self::Array(
-                StrictDecode::strict_decode(&mut d)?,
-                StrictDecode::strict_decode(&mut d)?,
-            ),
-            0x11 => Self::List(StrictDecode::strict_decode(&mut d)?),
-            0x12 => Self::Set(StrictDecode::strict_decode(&mut d)?),
-            0x13 => Self::Map(
-                StrictDecode::strict_decode(&mut d)?,
-                StrictDecode::strict_decode(&mut d)?,
-            ),
+            0x00 => Self::Plain(inner(&mut d)?),
+            0x10 => Self::Array(StrictDecode::strict_decode(&mut d)?, inner(&mut d)?),
+            0x11 => Self::List(inner(&mut d)?),
+            0x12 => Self::Set(inner(&mut d)?),
+            0x13 => Self::Map(StrictDecode::strict_decode(&mut d)?, inner(&mut d)?),
             other => {
                 return Err(strict_encoding::Error::EnumValueNotKnown("TypeConstr", other as usize))
             }
@@ -378,15 +696,132 @@ where T: Clone + Ord + Eq + Hash + Debug + StrictDecode
 }
 
 #[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Default)]
-#[derive(StrictEncode, StrictDecode)]
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(crate = "serde_crate"))]
-pub struct TypeSystem(StrictMap<TypeName, StructType>);
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", from = "SerdeTypeSystem", into = "SerdeTypeSystem")
+)]
+pub struct TypeSystem {
+    types: StrictMap<TypeName, StructType>,
+    /// Types declared as aliases, each having a single non-optional field.
+    aliases: StrictSet<TypeName>,
+}
+
+/// Serde representation of a type system. Type systems without aliases are
+/// represented by a plain map of the types, as before the aliases were
+/// introduced.
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "serde_crate", untagged)]
+enum SerdeTypeSystem {
+    Types(StrictMap<TypeName, StructType>),
+    Aliased {
+        types: StrictMap<TypeName, StructType>,
+        aliases: StrictSet<TypeName>,
+    },
+}
+
+#[cfg(feature = "serde")]
+impl From<SerdeTypeSystem> for TypeSystem {
+    fn from(ts: SerdeTypeSystem) -> Self {
+        match ts {
+            SerdeTypeSystem::Types(types) => TypeSystem {
+                types,
+                aliases: empty!(),
+            },
+            SerdeTypeSystem::Aliased { types, aliases } => TypeSystem { types, aliases },
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<TypeSystem> for SerdeTypeSystem {
+    fn from(ts: TypeSystem) -> Self {
+        if ts.aliases.is_empty() {
+            SerdeTypeSystem::Types(ts.types)
+        } else {
+            SerdeTypeSystem::Aliased {
+                types: ts.types,
+                aliases: ts.aliases,
+            }
+        }
+    }
+}
+
+/// Value of the `optional` flag of the field of an alias in the strict
+/// encoding of a type system. Fields of other types encode the flag as a
+/// boolean, thus type systems without aliases are encoded as a plain map of
+/// the types, as before the aliases were introduced.
+const ALIAS_FLAG: u8 = 0x02;
+
+impl StrictEncode for TypeSystem {
+    fn strict_encode<E: std::io::Write>(&self, mut e: E) -> Result<usize, strict_encoding::Error> {
+        let mut len = self.types.len().strict_encode(&mut e)?;
+        for (name, ty) in &self.types {
+            len += name.strict_encode(&mut e)?;
+            len += ty.len().strict_encode(&mut e)?;
+            for field in ty {
+                len += field.ty.strict_encode(&mut e)?;
+                let flag = if self.is_alias(name) { ALIAS_FLAG } else { field.optional as u8 };
+                len += flag.strict_encode(&mut e)?;
+            }
+        }
+        Ok(len)
+    }
+}
+
+impl StrictDecode for TypeSystem {
+    fn strict_decode<D: Read>(mut d: D) -> Result<Self, strict_encoding::Error> {
+        let mut ts = TypeSystem::new();
+        for _ in 0..u16::strict_decode(&mut d)? {
+            let name = TypeName::strict_decode(&mut d)?;
+            let count = u16::strict_decode(&mut d)?;
+            let mut fields = Vec::with_capacity(count as usize);
+            let mut alias = false;
+            for _ in 0..count {
+                let ty = TypeRef::strict_decode(&mut d)?;
+                let optional = match u8::strict_decode(&mut d)? {
+                    0 => false,
+                    1 => true,
+                    ALIAS_FLAG if count == 1 => {
+                        alias = true;
+                        false
+                    }
+                    flag => {
+                        return Err(strict_encoding::Error::ValueOutOfRange(
+                            "field flag",
+                            0..ALIAS_FLAG as u128 + 1,
+                            flag as u128,
+                        ))
+                    }
+                };
+                fields.push(StructField { ty, optional });
+            }
+            let fields = StrictVec::try_from(fields).map_err(|_| {
+                strict_encoding::Error::ValueOutOfRange(
+                    "array length",
+                    1..crate::STRICT_COLLECTION_MAX_LEN as u128,
+                    count as u128,
+                )
+            })?;
+            if ts.get(&name).is_some() {
+                return Err(strict_encoding::Error::RepeatedValue(format!(
+                    "non-unique map key {:?}",
+                    name
+                )));
+            }
+            ts.push_decl(name, StructType(fields), alias)
+                .expect("type system has less than STRICT_COLLECTION_MAX_LEN types");
+        }
+        Ok(ts)
+    }
+}
 
 impl Display for TypeSystem {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        for (name, ty) in &self.0 {
+        for (name, ty) in &self.types {
             Display::fmt(name, f)?;
-            f.write_str(" :: ")?;
+            f.write_str(if self.aliases.contains(name) { " = " } else { " :: " })?;
             Display::fmt(ty, f)?;
             f.write_str("\n")?;
         }
@@ -394,35 +829,180 @@ impl Display for TypeSystem {
     }
 }
 
+impl<'me> IntoIterator for &'me TypeSystem {
+    type Item = (&'me TypeName, &'me StructType);
+    type IntoIter = std::collections::btree_map::Iter<'me, TypeName, StructType>;
+
+    fn into_iter(self) -> Self::IntoIter { self.types.into_iter() }
+}
+
 impl TypeSystem {
     pub fn new() -> Self { default!() }
 
     pub fn push(&mut self, name: TypeName, ty: StructType) -> Result<(), Error> {
-        if self.0.contains_key(&name) {
+        if self.types.contains_key(&name) {
             return Err(Error::DuplicatedType(name));
         }
-        self.0.insert(name, ty)?;
+        self.types.insert(name, ty)?;
+        Ok(())
+    }
+
+    /// Declares type `name` as an alias of `ty`. Alias is encoded exactly as
+    /// the aliased type, and is represented by a structure with a single
+    /// non-optional field, but keeps its own name in the documentation,
+    /// generated code and display.
+    pub fn push_alias(&mut self, name: TypeName, ty: TypeRef) -> Result<(), Error> {
+        let field = StructField {
+            ty,
+            optional: false,
+        };
+        self.push_decl(name, StructType(crate::strict_vec![field]), true)
+    }
+
+    /// Adds type, declaring it as an alias if `alias` is set; alias types
+    /// must have a single non-optional field.
+    pub(crate) fn push_decl(
+        &mut self,
+        name: TypeName,
+        ty: StructType,
+        alias: bool,
+    ) -> Result<(), Error> {
+        debug_assert!(!alias || (ty.len() == 1 && !ty.0[0].optional));
+        self.push(name.clone(), ty)?;
+        if alias {
+            self.aliases.insert(name)?;
+        }
         Ok(())
     }
 
     #[inline]
-    pub fn get(&self, name: &TypeName) -> Option<&StructType> { self.0.get(name) }
+    pub fn get(&self, name: &TypeName) -> Option<&StructType> { self.types.get(name) }
+
+    /// Returns type aliased by `name`, if `name` is declared as an alias.
+    pub fn alias(&self, name: &TypeName) -> Option<&TypeRef> {
+        if !self.is_alias(name) {
+            return None;
+        }
+        self.get(name).map(|ty| &ty.0[0].ty)
+    }
+
+    #[inline]
+    pub fn is_alias(&self, name: &TypeName) -> bool { self.aliases.contains(name) }
+
+    #[inline]
+    pub fn len(&self) -> u16 { self.types.len() }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool { self.types.is_empty() }
+
+    /// Returns names of all types reachable from the `roots`, including the
+    /// roots themselves.
+    pub fn reachable<'a>(
+        &self,
+        roots: impl IntoIterator<Item = &'a TypeName>,
+    ) -> Result<BTreeSet<TypeName>, Error> {
+        let mut reached = BTreeSet::new();
+        let mut queue = roots.into_iter().cloned().collect::<Vec<_>>();
+        while let Some(name) = queue.pop() {
+            let ty = self.get(&name).ok_or_else(|| Error::UnknownType(name.clone()))?;
+            if !reached.insert(name) {
+                continue;
+            }
+            queue.extend(ty.into_iter().filter_map(|field| field.ty.type_name()).cloned());
+        }
+        Ok(reached)
+    }
 
+    /// Returns type system containing only the types reachable from the
+    /// `roots`, together with the names of the types which are not reachable
+    /// and were left out.
+    pub fn extract<'a>(
+        &self,
+        roots: impl IntoIterator<Item = &'a TypeName>,
+    ) -> Result<(TypeSystem, BTreeSet<TypeName>), Error> {
+        let reached = self.reachable(roots)?;
+        let mut extracted = TypeSystem::new();
+        let mut unused = BTreeSet::new();
+        for (name, ty) in self {
+            if reached.contains(name) {
+                extracted.push_decl(name.clone(), ty.clone(), self.is_alias(name))?;
+            } else {
+                unused.insert(name.clone());
+            }
+        }
+        Ok((extracted, unused))
+    }
+
+    /// Checks that all the referenced types are defined, returning the
+    /// references to unknown types otherwise. Other issues of the type system
+    /// are reported by [`TypeSystem::check`].
     pub fn validate(&self) -> Result<(), Vec<TypeInconsistency>> {
+        let issues = match self.check() {
+            Ok(()) => return Ok(()),
+            Err(issues) => issues,
+        };
+        let issues: Vec<_> = issues
+            .into_iter()
+            .filter_map(|issue| match issue {
+                ValidationIssue::UnknownType(inconsistency) => Some(inconsistency),
+                _ => None,
+            })
+            .collect();
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(issues)
+        }
+    }
+
+    /// Checks the type system for references to unknown types, alias cycles
+    /// and redundantly nested constructors.
+    pub fn check(&self) -> Result<(), Vec<ValidationIssue>> {
         let mut issues = vec![];
-        for (name, ty) in &*self.0 {
+        for (name, ty) in &*self.types {
             for (no, field) in ty.into_iter().enumerate() {
-                if let TypeRef::NameRef(r) = &field.ty {
-                    if self.get(r.type_name()).is_none() {
-                        issues.push(TypeInconsistency {
+                if !field.ty.is_canonical() {
+                    issues.push(ValidationIssue::RedundantNesting {
+                        container: name.clone(),
+                        field_no: no,
+                    });
+                }
+                match field.ty.type_name() {
+                    Some(absent) if self.get(absent).is_none() => issues.push(
+                        TypeInconsistency {
                             container: name.clone(),
                             field_no: no,
-                            absent_type: r.type_name().clone(),
-                        });
-                    }
+                            absent_type: absent.clone(),
+                        }
+                        .into(),
+                    ),
+                    _ => {}
                 }
             }
         }
+        // only plain references make alias cycles, since recursion through a
+        // collection is terminated by an empty one; an alias has a single
+        // field, so each alias refers directly to at most one other alias and
+        // each alias cycle is found by following the references from any of
+        // its aliases
+        let mut checked = BTreeSet::new();
+        for name in &self.aliases {
+            let mut path = Vec::<&TypeName>::new();
+            let mut next = Some(name);
+            while let Some(name) = next.filter(|name| !checked.contains(*name)) {
+                if let Some(pos) = path.iter().position(|seen| *seen == name) {
+                    let first = path[pos..].iter().min().expect("cycle is not empty");
+                    issues.push(ValidationIssue::AliasCycle((*first).clone()));
+                    break;
+                }
+                path.push(name);
+                next = match self.alias(name) {
+                    Some(TypeRef::NameRef(TypeConstr::Plain(r))) if self.is_alias(r) => Some(r),
+                    _ => None,
+                };
+            }
+            checked.extend(path);
+        }
         if issues.is_empty() {
             Ok(())
         } else {
@@ -441,6 +1021,35 @@ pub struct TypeInconsistency {
     pub absent_type: TypeName,
 }
 
+/// Issues of a type system reported by [`TypeSystem::check`].
+#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display, Error, From)]
+#[derive(StrictEncode, StrictDecode)]
+#[strict_encoding(by_order, repr = u8)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(crate = "serde_crate"))]
+pub enum ValidationIssue {
+    /// Field of the type referencing a type which is not defined.
+    #[display(inner)]
+    #[from]
+    UnknownType(TypeInconsistency),
+
+    /// Field of the type nesting constructors where the nesting is not
+    /// needed; see [`TypeRef::nested`].
+    #[display(
+        "type '{container}' nests constructors in its field #{field_no} where they are \
+         expressible without nesting"
+    )]
+    RedundantNesting {
+        container: TypeName,
+        field_no: usize,
+    },
+
+    /// Alias which is a part of a cycle of aliases referencing each other
+    /// without any constructor in between. Each cycle is reported once, by its
+    /// alias with the smallest name.
+    #[display("alias '{0}' refers to itself through other aliases")]
+    AliasCycle(TypeName),
+}
+
 #[macro_export]
 macro_rules! type_system {
     ($($name:literal :: { $($field:expr),+ $(,)? }),+ $(,)?) => { {
@@ -448,7 +1057,8 @@ macro_rules! type_system {
         let mut ts = $crate::TypeSystem::new();
         $(
         let name = $name.try_into().expect("inline strict_vec literal contains invalid number of items");
-        let ty = unsafe { $crate::StructType::from_unchecked(strict_vec![$($field),+]) };
+        let fields = strict_vec![$($field),+];
+        let ty = unsafe { $crate::StructType::from_unchecked(fields) };
         ts.push(name, ty).expect("invalid type declaration");
         )+
         ts
@@ -461,25 +1071,30 @@ pub enum Error {
     /// type `{0}` is already defined
     DuplicatedType(TypeName),
 
+    /// type `{0}` is not defined
+    UnknownType(TypeName),
+
     #[from]
     #[display(inner)]
     Oversize(OversizeError),
 }
 
 #[cfg(test)]
-mod test {
-    use amplify::hex::ToHex;
+pub(crate) mod test {
+    use amplify::hex::{FromHex, ToHex};
 
     use super::*;
     use crate::strict_vec;
 
     impl TypeSystem {
         fn remove(&mut self, name: &'static str) {
-            self.0.remove(&name.try_into().unwrap()).unwrap();
+            self.types.remove(&name.try_into().unwrap()).unwrap();
         }
     }
 
-    fn type_system() -> TypeSystem {
+    pub(crate) fn tn(name: &'static str) -> TypeName { name.try_into().unwrap() }
+
+    pub(crate) fn type_system() -> TypeSystem {
         type_system![
            "Transaction" :: {
                 StructField::primitive(PrimitiveType::U32),
@@ -511,6 +1126,53 @@ mod test {
         ]
     }
 
+    /// The same schema as [`type_system`] with witness stack represented by
+    /// a nested list of byte lists instead of a separate `Witness` type.
+    pub(crate) fn nested_type_system() -> TypeSystem {
+        let mut ts = type_system();
+        ts.remove("Witness");
+        let witness = TypeRef::nested(TypeConstr::List(TypeRef::InPlace(TypeConstr::List(
+            PrimitiveType::U8,
+        ))));
+        let input =
+            strict_vec![StructField::with("OutPoint"), StructField::with("Bytes"), StructField {
+                ty: witness,
+                optional: false
+            },];
+        ts.types.insert(tn("Input"), StructType(input)).unwrap();
+        ts
+    }
+
+    /// Compares generated text with the golden file from `tests/golden`
+    /// directory; the file is updated instead if `STENS_BLESS` environment
+    /// variable is set.
+    pub(crate) fn golden(file: &str, actual: &str) {
+        let path = format!("{}/tests/golden/{}", env!("CARGO_MANIFEST_DIR"), file);
+        if std::env::var_os("STENS_BLESS").is_some() {
+            std::fs::write(&path, actual).expect("unable to write golden file");
+        }
+        let expected = std::fs::read_to_string(&path).expect("missing golden file");
+        assert_eq!(actual, expected, "output differs from golden file {}", file);
+    }
+
+    /// Strict encoding of a `Transaction` with two inputs and two outputs.
+    pub(crate) fn transaction() -> Vec<u8> {
+        let mut data = vec![];
+        data.extend([1, 0, 0, 0]); // version
+        data.extend([2, 0]); // inputs
+        data.extend([1u8; 32]); // txid
+        data.extend([2, 0, 2, 0, 1, 2, 1, 0, 1, 0, 0xFF]); // vout, script, witness
+        data.extend([2u8; 32]); // txid
+        data.extend([0, 0, 0, 0, 0, 0]); // vout, script, witness
+        data.extend([2, 0]); // outputs
+        data.extend(100_000u64.to_le_bytes());
+        data.extend([1, 0, 0x51]);
+        data.extend(200_000u64.to_le_bytes());
+        data.extend([0, 0]);
+        data.extend([0, 0, 0, 0]); // lock time
+        data
+    }
+
     #[test]
     fn display() {
         println!("{}", type_system());
@@ -526,6 +1188,52 @@ mod test {
         assert_eq!(ts, ts2);
     }
 
+    #[test]
+    fn decode_previous_encoding() {
+        // Type system encoded before the aliases were introduced
+        let data = Vec::from_hex(
+            "020008004f7574506f696e740200010004005478696400000001000400547869640100001020000000",
+        )
+        .unwrap();
+        let mut ts = type_system![
+            "OutPoint" :: { StructField::with("Txid"), StructField::primitive(PrimitiveType::U16) },
+            "Txid" :: { StructField::array(PrimitiveType::U8, 32) }
+        ];
+        assert_eq!(TypeSystem::strict_deserialize(&data).unwrap(), ts);
+        assert_eq!(ts.strict_serialize().unwrap(), data);
+
+        ts.types.remove(&tn("Txid")).unwrap();
+        ts.push_alias(tn("Txid"), TypeRef::InPlace(TypeConstr::Array(32, PrimitiveType::U8)))
+            .unwrap();
+        let mut aliased = data.clone();
+        *aliased.last_mut().unwrap() = ALIAS_FLAG;
+        assert_eq!(ts.strict_serialize().unwrap(), aliased);
+        assert_eq!(TypeSystem::strict_deserialize(&aliased).unwrap(), ts);
+
+        // alias flag is not allowed for types with multiple fields
+        let mut invalid = data;
+        invalid[22] = ALIAS_FLAG;
+        assert!(TypeSystem::strict_deserialize(&invalid).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde_previous_format() {
+        let yaml =
+            "---\nTxid:\n  - ty:\n      InPlace:\n        Array:\n          - 32\n          - \
+             U8\n    optional: false\n";
+        let mut ts = type_system!["Txid" :: { StructField::array(PrimitiveType::U8, 32) }];
+        assert_eq!(serde_yaml::from_str::<TypeSystem>(yaml).unwrap(), ts);
+        assert_eq!(serde_yaml::to_string(&ts).unwrap(), yaml);
+
+        ts.types.remove(&tn("Txid")).unwrap();
+        ts.push_alias(tn("Txid"), TypeRef::InPlace(TypeConstr::Array(32, PrimitiveType::U8)))
+            .unwrap();
+        let yaml = serde_yaml::to_string(&ts).unwrap();
+        assert!(yaml.contains("aliases"));
+        assert_eq!(serde_yaml::from_str::<TypeSystem>(&yaml).unwrap(), ts);
+    }
+
     #[test]
     fn test_verify() {
         let mut ts = type_system();
@@ -533,6 +1241,185 @@ mod test {
         ts.remove("Bytes");
     }
 
+    #[test]
+    fn extract() {
+        let ts = type_system();
+        let (extracted, unused) = ts.extract([&tn("Output"), &tn("Witness")]).unwrap();
+        assert_eq!(
+            extracted.to_string(),
+            "Bytes :: U8*\nOutput :: U64, Bytes\nWitness :: Bytes*\n"
+        );
+        assert_eq!(
+            unused,
+            bset! { tn("Input"), tn("Meta"), tn("OutPoint"), tn("Transaction"), tn("Txid"), tn("UnicodeString") }
+        );
+        extracted.check().unwrap();
+
+        let (extracted, unused) = ts.extract([&tn("Transaction"), &tn("Meta")]).unwrap();
+        assert_eq!(extracted, ts);
+        assert!(unused.is_empty());
+
+        assert_eq!(ts.extract([&tn("Block")]).unwrap_err(), Error::UnknownType(tn("Block")));
+    }
+
+    #[test]
+    fn aliases() {
+        let mut ts = type_system();
+        ts.remove("Txid");
+        ts.push_alias(tn("Txid"), TypeRef::InPlace(TypeConstr::Array(32, PrimitiveType::U8)))
+            .unwrap();
+        assert!(ts.is_alias(&tn("Txid")) && !ts.is_alias(&tn("Bytes")));
+        assert_eq!(ts.alias(&tn("Txid")).unwrap().to_string(), "U8*32");
+        assert_eq!(ts.get(&tn("Txid")), type_system().get(&tn("Txid")));
+        assert!(ts.to_string().contains("\nTxid = U8*32\n"));
+        assert_eq!(
+            ts.decode(&tn("Transaction"), transaction()),
+            type_system().decode(&tn("Transaction"), transaction())
+        );
+        ts.check().unwrap();
+
+        let data = ts.strict_serialize().unwrap();
+        assert_eq!(TypeSystem::strict_deserialize(&data).unwrap(), ts);
+        assert_ne!(ts, type_system());
+
+        let (extracted, _) = ts.extract([&tn("OutPoint")]).unwrap();
+        assert!(extracted.is_alias(&tn("Txid")));
+        ts.rename(&tn("Txid"), tn("TxId")).unwrap();
+        assert_eq!(ts.alias(&tn("TxId")).unwrap().to_string(), "U8*32");
+    }
+
+    #[test]
+    fn alias_cycles() {
+        let mut ts = type_system();
+        let name_ref = |name| TypeRef::NameRef(TypeConstr::Plain(tn(name)));
+        ts.push_alias(tn("Script"), name_ref("Bytes")).unwrap();
+        ts.push_alias(tn("Chain"), TypeRef::NameRef(TypeConstr::List(tn("Link")))).unwrap();
+        ts.push_alias(tn("Link"), name_ref("Node")).unwrap();
+        ts.push_alias(tn("Node"), name_ref("Chain")).unwrap();
+        ts.push_alias(tn("Tail"), name_ref("Link")).unwrap();
+        ts.push_alias(tn("Loop"), name_ref("Loop")).unwrap();
+        ts.push_alias(tn("Ping"), name_ref("Pong")).unwrap();
+        ts.push_alias(tn("Pong"), name_ref("Ping")).unwrap();
+        assert_eq!(ts.check().unwrap_err(), vec![
+            ValidationIssue::AliasCycle(tn("Loop")),
+            ValidationIssue::AliasCycle(tn("Ping")),
+        ]);
+        assert_eq!(
+            ts.check().unwrap_err()[0].to_string(),
+            "alias 'Loop' refers to itself through other aliases"
+        );
+        ts.validate().unwrap();
+    }
+
+    #[test]
+    fn alias_recursion() {
+        // recursion through a list is terminated by an empty list
+        let mut ts = type_system();
+        ts.push_alias(tn("Chain"), TypeRef::NameRef(TypeConstr::List(tn("Link")))).unwrap();
+        ts.push_alias(tn("Link"), TypeRef::NameRef(TypeConstr::Plain(tn("Chain")))).unwrap();
+        ts.check().unwrap();
+        assert_eq!(ts.decode(&tn("Chain"), [1, 0, 0, 0]).unwrap().to_string(), "([(([]))])");
+    }
+
+    #[test]
+    fn nested() {
+        let bytes = TypeRef::InPlace(TypeConstr::List(PrimitiveType::U8));
+        assert_eq!(TypeRef::nested(TypeConstr::Plain(bytes.clone())), bytes);
+        assert_eq!(
+            TypeRef::nested(TypeConstr::Set(TypeRef::new("Bytes"))),
+            TypeRef::NameRef(TypeConstr::Set(tn("Bytes")))
+        );
+        let stack = TypeRef::nested(TypeConstr::List(bytes.clone()));
+        assert_eq!(stack, TypeRef::Nested(TypeConstr::List(Box::new(bytes))));
+        assert!(stack.is_canonical());
+        assert_eq!(stack.type_name(), None);
+
+        let ts = nested_type_system();
+        ts.check().unwrap();
+        assert!(ts.to_string().contains("Input :: OutPoint, Bytes, (U8*)*\n"));
+        let data = ts.strict_serialize().unwrap();
+        assert_eq!(TypeSystem::strict_deserialize(&data).unwrap(), ts);
+    }
+
+    #[test]
+    fn nested_display() {
+        use crate::cursor::ToShape;
+
+        let key = KeyType::Primitive(PrimitiveType::U8);
+        let map = TypeRef::InPlace(TypeConstr::Map(key, PrimitiveType::U16));
+        let list_of_maps = TypeRef::nested(TypeConstr::List(map));
+        let list = TypeRef::InPlace(TypeConstr::List(PrimitiveType::U16));
+        let map_of_lists = TypeRef::nested(TypeConstr::Map(key, list));
+        assert_eq!(list_of_maps.to_string(), "({U8} -> U16)*");
+        assert_eq!(map_of_lists.to_string(), "{U8} -> U16*");
+        assert_eq!(list_of_maps.shape().to_string(), list_of_maps.to_string());
+        assert_eq!(map_of_lists.shape().to_string(), map_of_lists.to_string());
+        let sets = TypeRef::NameRef(TypeConstr::Set(tn("Txid")));
+        let sets = TypeRef::nested(TypeConstr::Array(2, sets));
+        assert_eq!(sets.to_string(), "({Txid})*2");
+        assert_eq!(sets.shape().to_string(), sets.to_string());
+    }
+
+    #[test]
+    fn nesting_depth() {
+        let mut ty = TypeRef::InPlace(TypeConstr::List(PrimitiveType::U8));
+        for _ in 0..MAX_NESTING_DEPTH {
+            ty = TypeRef::Nested(TypeConstr::List(Box::new(ty)));
+        }
+        let data = ty.strict_serialize().unwrap();
+        assert_eq!(TypeRef::strict_deserialize(&data).unwrap(), ty);
+
+        let mut data = [0x02, 0x11].repeat(MAX_NESTING_DEPTH + 1);
+        data.extend([0x00, 0x11, 0x00]);
+        assert!(matches!(
+            TypeRef::strict_deserialize(&data),
+            Err(strict_encoding::Error::DataIntegrityError(_))
+        ));
+        let data = [0x02, 0x11].repeat(1_000_000);
+        assert!(matches!(
+            TypeRef::strict_deserialize(&data),
+            Err(strict_encoding::Error::DataIntegrityError(_))
+        ));
+    }
+
+    #[test]
+    fn nested_inconsistency() {
+        let mut ts = type_system();
+        let redundant = TypeRef::Nested(TypeConstr::List(Box::new(TypeRef::new("Bytes"))));
+        let unknown =
+            TypeRef::nested(TypeConstr::Array(2, TypeRef::NameRef(TypeConstr::List(tn("Block")))));
+        ts.push(
+            tn("Stack"),
+            StructType(strict_vec![
+                StructField {
+                    ty: redundant,
+                    optional: false
+                },
+                StructField {
+                    ty: unknown,
+                    optional: true
+                }
+            ]),
+        )
+        .unwrap();
+        assert_eq!(ts.check().unwrap_err(), vec![
+            ValidationIssue::RedundantNesting {
+                container: tn("Stack"),
+                field_no: 0
+            },
+            ValidationIssue::UnknownType(TypeInconsistency {
+                container: tn("Stack"),
+                field_no: 1,
+                absent_type: tn("Block")
+            }),
+        ]);
+        assert_eq!(ts.validate().unwrap_err(), vec![TypeInconsistency {
+            container: tn("Stack"),
+            field_no: 1,
+            absent_type: tn("Block")
+        }]);
+    }
+
     #[test]
     #[should_panic]
     fn test_verify_absent() {
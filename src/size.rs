@@ -0,0 +1,237 @@
+// Strict encoding schema library, implementing validation and parsing of strict
+// encoded data against the schema.
+//
+// Written in 2022 by
+//     Dr. Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2022 by LNP/BP Standards Association, Switzerland.
+//
+// You should have received a copy of the MIT License along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::ops::{Add, Mul};
+
+use crate::{KeyType, PrimitiveType, StructField, TypeConstr, TypeName, TypeRef, TypeSystem};
+
+/// Size of the encoded data in bytes.
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display)]
+pub enum DataSize {
+    /// Data always have the same size.
+    #[display("{0}")]
+    Fixed(usize),
+
+    /// Data size varies, with the given minimum.
+    #[display("{0}+")]
+    Variable(usize),
+}
+
+impl DataSize {
+    /// Returns minimal size of the data.
+    pub fn min(self) -> usize {
+        match self {
+            DataSize::Fixed(size) | DataSize::Variable(size) => size,
+        }
+    }
+
+    /// Returns size of the data if it is fixed.
+    pub fn fixed(self) -> Option<usize> {
+        match self {
+            DataSize::Fixed(size) => Some(size),
+            DataSize::Variable(_) => None,
+        }
+    }
+}
+
+impl Add for DataSize {
+    type Output = DataSize;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        match (self, rhs) {
+            (DataSize::Fixed(a), DataSize::Fixed(b)) => match a.checked_add(b) {
+                Some(size) => DataSize::Fixed(size),
+                None => DataSize::Variable(usize::MAX),
+            },
+            (a, b) => DataSize::Variable(a.min().saturating_add(b.min())),
+        }
+    }
+}
+
+impl Mul<u16> for DataSize {
+    type Output = DataSize;
+
+    fn mul(self, rhs: u16) -> Self::Output {
+        match self {
+            DataSize::Fixed(size) => match size.checked_mul(rhs as usize) {
+                Some(size) => DataSize::Fixed(size),
+                None => DataSize::Variable(usize::MAX),
+            },
+            DataSize::Variable(size) => DataSize::Variable(size.saturating_mul(rhs as usize)),
+        }
+    }
+}
+
+impl From<PrimitiveType> for DataSize {
+    fn from(ty: PrimitiveType) -> Self {
+        match ty.fixed_size() {
+            Some(size) => DataSize::Fixed(size as usize),
+            None => DataSize::Variable(2),
+        }
+    }
+}
+
+impl From<KeyType> for DataSize {
+    fn from(key: KeyType) -> Self {
+        match key {
+            KeyType::Primitive(ty) => ty.into(),
+            KeyType::Array(len, ty) => DataSize::from(ty) * len,
+            KeyType::List(_) => DataSize::Variable(2),
+        }
+    }
+}
+
+impl TypeSystem {
+    /// Returns size of the encoded data of type `name`.
+    ///
+    /// Types which are not defined, as well as types containing themselves
+    /// without a list, set, map or optional field in between, are reported as
+    /// having variable size with zero minimum. Sizes which don't fit into
+    /// `usize` are reported as variable, with `usize::MAX` minimum.
+    pub fn size_of(&self, name: &TypeName) -> DataSize { Sizer::new(self).struct_size(name) }
+
+    /// Returns size of the encoded data of the structure field.
+    pub fn field_size(&self, field: &StructField) -> DataSize {
+        Sizer::new(self).field_size(field)
+    }
+}
+
+/// Computes sizes of the types, memoizing the sizes of the structures.
+struct Sizer<'ts> {
+    ts: &'ts TypeSystem,
+    /// Structures which sizes are being computed
+    stack: Vec<&'ts TypeName>,
+    /// Number of references back to the structures on the stack met so far
+    cycles: usize,
+    /// Sizes of the structures which don't depend on the stack contents
+    sizes: BTreeMap<&'ts TypeName, DataSize>,
+}
+
+impl<'ts> Sizer<'ts> {
+    fn new(ts: &'ts TypeSystem) -> Self {
+        Sizer {
+            ts,
+            stack: vec![],
+            cycles: 0,
+            sizes: empty!(),
+        }
+    }
+
+    fn struct_size(&mut self, name: &'ts TypeName) -> DataSize {
+        if let Some(size) = self.sizes.get(name) {
+            return *size;
+        }
+        let ty = match self.ts.get(name) {
+            Some(ty) if !self.stack.contains(&name) => ty,
+            Some(_) => {
+                self.cycles += 1;
+                return DataSize::Variable(0);
+            }
+            None => return DataSize::Variable(0),
+        };
+        let cycles = self.cycles;
+        self.stack.push(name);
+        let size = ty
+            .into_iter()
+            .map(|field| self.field_size(field))
+            .fold(DataSize::Fixed(0), DataSize::add);
+        self.stack.pop();
+        // sizes cut by a cycle depend on where the computation has started
+        if self.cycles == cycles {
+            self.sizes.insert(name, size);
+        }
+        size
+    }
+
+    fn field_size(&mut self, field: &'ts StructField) -> DataSize {
+        if field.optional {
+            return DataSize::Variable(1);
+        }
+        self.ref_size(&field.ty)
+    }
+
+    fn ref_size(&mut self, ty: &'ts TypeRef) -> DataSize {
+        match ty {
+            TypeRef::InPlace(ty) => constr_size(ty, |ty| DataSize::from(*ty)),
+            TypeRef::NameRef(ty) => constr_size(ty, |name| self.struct_size(name)),
+            TypeRef::Nested(ty) => constr_size(ty, |item| self.ref_size(item)),
+        }
+    }
+}
+
+fn constr_size<'ty, T>(
+    ty: &'ty TypeConstr<T>,
+    mut item: impl FnMut(&'ty T) -> DataSize,
+) -> DataSize
+where T: Clone + Ord + Eq + Hash + Debug {
+    match ty {
+        TypeConstr::Plain(ty) => item(ty),
+        TypeConstr::Array(len, ty) => item(ty) * *len,
+        TypeConstr::List(_) | TypeConstr::Set(_) | TypeConstr::Map(..) => DataSize::Variable(2),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::schema::test::{tn, type_system};
+    use crate::{strict_vec, type_system, StrictVec, StructType};
+
+    #[test]
+    fn sizes() {
+        let ts = type_system();
+        assert_eq!(ts.size_of(&tn("Txid")), DataSize::Fixed(32));
+        assert_eq!(ts.size_of(&tn("OutPoint")), DataSize::Fixed(34));
+        assert_eq!(ts.size_of(&tn("Input")), DataSize::Variable(38));
+        assert_eq!(ts.size_of(&tn("Unknown")), DataSize::Variable(0));
+        assert_eq!(DataSize::Variable(38).to_string(), "38+");
+
+        let ts = type_system![
+            "Loop" :: { StructField::primitive(PrimitiveType::U16), StructField::with("Loop") }
+        ];
+        assert_eq!(ts.size_of(&tn("Loop")), DataSize::Variable(2));
+
+        let array = |name: &'static str| StructField {
+            ty: TypeRef::NameRef(TypeConstr::Array(u16::MAX, tn(name))),
+            optional: false,
+        };
+        let ts = type_system![
+            "Big" :: { StructField::array(PrimitiveType::U1024, u16::MAX) },
+            "Huge" :: { array("Big") },
+            "Vast" :: { array("Huge") },
+            "Immense" :: { array("Vast") }
+        ];
+        assert_eq!(ts.size_of(&tn("Big")), DataSize::Fixed(128 * u16::MAX as usize));
+        assert_eq!(ts.size_of(&tn("Immense")), DataSize::Variable(usize::MAX));
+    }
+
+    #[test]
+    fn sizes_memoized() {
+        // each type refers twice to the next one, so computing the size of
+        // the first type without memoization visits 2^64 types
+        let name = |no: usize| TypeName::try_from(format!("T{}", no)).unwrap();
+        let mut ts = TypeSystem::new();
+        for no in 0..64 {
+            let field = StructField {
+                ty: TypeRef::NameRef(TypeConstr::Plain(name(no + 1))),
+                optional: false,
+            };
+            let fields: StrictVec<StructField, 1> = strict_vec![field.clone(), field];
+            ts.push(name(no), StructType::from(fields)).unwrap();
+        }
+        ts.push(tn("T64"), type_system().get(&tn("Txid")).unwrap().clone()).unwrap();
+        assert_eq!(ts.size_of(&tn("T0")), DataSize::Variable(usize::MAX));
+        assert_eq!(ts.size_of(&tn("T50")), DataSize::Fixed(32 << 14));
+    }
+}
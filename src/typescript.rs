@@ -0,0 +1,655 @@
+// Strict encoding schema library, implementing validation and parsing of strict
+// encoded data against the schema.
+//
+// Written in 2022 by
+//     Dr. Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2022 by LNP/BP Standards Association, Switzerland.
+//
+// You should have received a copy of the MIT License along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::fmt::{Debug, Write};
+use std::hash::Hash;
+
+use crate::codegen::{identifier, words, Idents, Names};
+use crate::{PrimitiveType, StructType, TypeConstr, TypeRef, TypeSystem};
+
+/// TypeScript reserved words and names of the predefined types, which can't
+/// name the generated interfaces.
+const KEYWORDS: &[&str] = &[
+    "any", "as", "await", "bigint", "boolean", "break", "case", "catch", "class", "const",
+    "continue", "debugger", "declare", "default", "delete", "do", "else", "enum", "export",
+    "extends", "false", "finally", "for", "function", "if", "implements", "import", "in",
+    "instanceof", "interface", "let", "never", "new", "null", "number", "object", "package",
+    "private", "protected", "public", "return", "static", "string", "super", "switch", "symbol",
+    "this", "throw", "true", "try", "type", "typeof", "undefined", "unknown", "var", "void",
+    "while", "with", "yield",
+];
+
+/// Reader and writer classes with the error types, which are prepended to the
+/// generated module.
+const RUNTIME: &str = r#"export class DecodeError extends Error {}
+
+export class EncodeError extends Error {}
+
+/** Compares byte strings in lexicographic order. */
+export function compare(a: Uint8Array, b: Uint8Array): number {
+  for (let i = 0; i < Math.min(a.length, b.length); i++) {
+    if (a[i] !== b[i]) {
+      return a[i] - b[i];
+    }
+  }
+  return a.length - b.length;
+}
+
+export class StrictReader {
+  private offset = 0;
+
+  constructor(private readonly view: DataView) {}
+
+  private need(len: number): number {
+    const offset = this.offset;
+    if (offset + len > this.view.byteLength) {
+      throw new DecodeError(`unexpected end of data at offset ${offset}`);
+    }
+    this.offset += len;
+    return offset;
+  }
+
+  u8(): number { return this.view.getUint8(this.need(1)); }
+  u16(): number { return this.view.getUint16(this.need(2), true); }
+  u32(): number { return this.view.getUint32(this.need(4), true); }
+  i8(): number { return this.view.getInt8(this.need(1)); }
+  i16(): number { return this.view.getInt16(this.need(2), true); }
+  i32(): number { return this.view.getInt32(this.need(4), true); }
+  f32(): number { return this.view.getFloat32(this.need(4), true); }
+  f64(): number { return this.view.getFloat64(this.need(8), true); }
+
+  uint(size: number): bigint {
+    const offset = this.need(size);
+    let value = 0n;
+    for (let i = size - 1; i >= 0; i--) {
+      value = (value << 8n) | BigInt(this.view.getUint8(offset + i));
+    }
+    return value;
+  }
+
+  int(size: number): bigint {
+    const value = this.uint(size);
+    const bits = BigInt(size * 8);
+    return value >= 1n << (bits - 1n) ? value - (1n << bits) : value;
+  }
+
+  raw(size: number): Uint8Array {
+    const offset = this.need(size);
+    return new Uint8Array(this.view.buffer, this.view.byteOffset + offset, size).slice();
+  }
+
+  ascii(): string {
+    const data = this.raw(this.u16());
+    const code = data.find((byte) => byte >= 0x80);
+    if (code !== undefined) {
+      throw new DecodeError(`non-ASCII character 0x${code.toString(16)}`);
+    }
+    return String.fromCharCode(...data);
+  }
+
+  unicode(): string {
+    const data = this.raw(this.u16());
+    try {
+      return new TextDecoder("utf-8", { fatal: true }).decode(data);
+    } catch {
+      throw new DecodeError("invalid UTF-8 string");
+    }
+  }
+
+  optional<T>(item: () => T): T | null {
+    const tag = this.u8();
+    if (tag > 1) {
+      throw new DecodeError(`invalid optional tag ${tag}`);
+    }
+    return tag === 1 ? item() : null;
+  }
+
+  array<T>(len: number, item: () => T): T[] {
+    const items: T[] = [];
+    for (let i = 0; i < len; i++) {
+      items.push(item());
+    }
+    return items;
+  }
+
+  list<T>(item: () => T): T[] { return this.array(this.u16(), item); }
+
+  /** Reads data item, returning it together with its encoding. */
+  private encoded<T>(item: () => T): [T, Uint8Array] {
+    const start = this.offset;
+    const value = item();
+    const data = new Uint8Array(this.view.buffer, this.view.byteOffset + start, this.offset - start);
+    return [value, data];
+  }
+
+  set<T>(item: () => T): T[] {
+    let last: Uint8Array | null = null;
+    return this.list(() => {
+      const [value, data] = this.encoded(item);
+      if (last !== null && compare(last, data) >= 0) {
+        throw new DecodeError(compare(last, data) === 0 ? "repeated set item" : "set items are not sorted");
+      }
+      last = data;
+      return value;
+    });
+  }
+
+  map<K, V>(key: () => K, value: () => V): Array<[K, V]> {
+    let last: Uint8Array | null = null;
+    return this.list(() => {
+      const [k, data] = this.encoded(key);
+      if (last !== null && compare(last, data) >= 0) {
+        throw new DecodeError(compare(last, data) === 0 ? "repeated map key" : "map keys are not sorted");
+      }
+      last = data;
+      return [k, value()];
+    });
+  }
+
+  finish(): void {
+    if (this.offset < this.view.byteLength) {
+      throw new DecodeError(`${this.view.byteLength - this.offset} bytes of data are not consumed`);
+    }
+  }
+}
+
+export class StrictWriter {
+  private data: number[] = [];
+
+  private number(value: number, size: number, signed: boolean): void {
+    if (!Number.isInteger(value)) {
+      throw new EncodeError(`value ${value} is not an integer`);
+    }
+    if (signed) {
+      this.int(BigInt(value), size);
+    } else {
+      this.uint(BigInt(value), size);
+    }
+  }
+
+  u8(value: number): void { this.number(value, 1, false); }
+  u16(value: number): void { this.number(value, 2, false); }
+  u32(value: number): void { this.number(value, 4, false); }
+  i8(value: number): void { this.number(value, 1, true); }
+  i16(value: number): void { this.number(value, 2, true); }
+  i32(value: number): void { this.number(value, 4, true); }
+
+  f32(value: number): void {
+    const view = new DataView(new ArrayBuffer(4));
+    view.setFloat32(0, value, true);
+    this.bytes(new Uint8Array(view.buffer));
+  }
+
+  f64(value: number): void {
+    const view = new DataView(new ArrayBuffer(8));
+    view.setFloat64(0, value, true);
+    this.bytes(new Uint8Array(view.buffer));
+  }
+
+  uint(value: bigint, size: number): void {
+    if (value < 0n || value >= 1n << BigInt(size * 8)) {
+      throw new EncodeError(`value ${value} does not fit into ${size} bytes`);
+    }
+    for (let i = 0; i < size; i++) {
+      this.data.push(Number(value & 0xffn));
+      value >>= 8n;
+    }
+  }
+
+  int(value: bigint, size: number): void {
+    const bits = BigInt(size * 8);
+    const half = 1n << (bits - 1n);
+    if (value < -half || value >= half) {
+      throw new EncodeError(`value ${value} does not fit into ${size} bytes`);
+    }
+    this.uint(value < 0n ? value + (1n << bits) : value, size);
+  }
+
+  raw(value: Uint8Array, size: number): void {
+    if (value.length !== size) {
+      throw new EncodeError(`expected ${size} bytes, got ${value.length}`);
+    }
+    this.bytes(value);
+  }
+
+  ascii(value: string): void {
+    if (!/^[\x00-\x7f]*$/.test(value)) {
+      throw new EncodeError(`string "${value}" contains non-ASCII characters`);
+    }
+    const data = Uint8Array.from(value, (char) => char.charCodeAt(0));
+    this.len(data.length);
+    this.bytes(data);
+  }
+
+  unicode(value: string): void {
+    const data = new TextEncoder().encode(value);
+    this.len(data.length);
+    this.bytes(data);
+  }
+
+  optional<T>(value: T | null, item: (value: T) => void): void {
+    if (value === null) {
+      this.u8(0);
+    } else {
+      this.u8(1);
+      item(value);
+    }
+  }
+
+  array<T>(items: T[], len: number, item: (value: T) => void): void {
+    if (items.length !== len) {
+      throw new EncodeError(`expected ${len} items, got ${items.length}`);
+    }
+    items.forEach(item);
+  }
+
+  list<T>(items: T[], item: (value: T) => void): void {
+    this.len(items.length);
+    items.forEach(item);
+  }
+
+  set<T>(items: T[], item: (value: T) => void): void {
+    const encoded = items.map((value) => this.capture(() => item(value))).sort(compare);
+    if (encoded.some((data, i) => i > 0 && compare(encoded[i - 1], data) === 0)) {
+      throw new EncodeError("repeated set item");
+    }
+    this.list(encoded, (data) => this.bytes(data));
+  }
+
+  map<K, V>(entries: Array<[K, V]>, key: (key: K) => void, value: (value: V) => void): void {
+    const encoded = entries
+      .map(([k, v]): [Uint8Array, Uint8Array] => [this.capture(() => key(k)), this.capture(() => value(v))])
+      .sort((a, b) => compare(a[0], b[0]));
+    if (encoded.some((entry, i) => i > 0 && compare(encoded[i - 1][0], entry[0]) === 0)) {
+      throw new EncodeError("repeated map key");
+    }
+    this.list(encoded, ([k, v]) => {
+      this.bytes(k);
+      this.bytes(v);
+    });
+  }
+
+  finish(): Uint8Array { return Uint8Array.from(this.data); }
+
+  private len(len: number): void {
+    if (len > 0xffff) {
+      throw new EncodeError(`${len} items exceed 16-bit length limit`);
+    }
+    this.u16(len);
+  }
+
+  private bytes(data: Uint8Array): void { data.forEach((byte) => this.data.push(byte)); }
+
+  /** Returns encoding of the data written by `write`. */
+  private capture(write: () => void): Uint8Array {
+    const data = this.data;
+    this.data = [];
+    write();
+    const captured = Uint8Array.from(this.data);
+    this.data = data;
+    return captured;
+  }
+}
+"#;
+
+impl TypeSystem {
+    /// Generates TypeScript module with an interface for each type of the
+    /// type system, together with functions decoding and encoding the data.
+    ///
+    /// Structure fields are named `f0`, `f1` etc; optional fields may be
+    /// `null`. Integers up to 32 bits and `F32`/`F64` floats are represented
+    /// by `number`, wider integers by `bigint`, other floats by their raw
+    /// bytes in `Uint8Array` and characters by `string`. Sets are arrays and
+    /// maps are arrays of key-value pairs; the encoders sort them by the
+    /// encoding of the items (keys), and the decoders reject data in which
+    /// set items or map keys are not unique or not sorted.
+    ///
+    /// Aliases are exported as TypeScript type aliases of the aliased types.
+    ///
+    /// For each type `T` module exports `readT(reader)` and `writeT(writer,
+    /// value)` functions, operating on `StrictReader` and `StrictWriter`, and
+    /// standalone `decodeT(view: DataView)` and `encodeT(value)` functions.
+    ///
+    /// Characters of the type names, which are not allowed in TypeScript
+    /// identifiers, are replaced with underscores; names clashing with the
+    /// reserved words, the runtime or other types get numeric suffixes.
+    pub fn to_typescript(&self) -> String {
+        let mut names = Names::reserved(words(RUNTIME).chain(KEYWORDS.iter().copied()));
+        let ids: Idents = self
+            .into_iter()
+            .map(|(name, _)| {
+                let id = names.fresh_with(&identifier(name), |id| {
+                    ["", "read", "write", "decode", "encode"]
+                        .iter()
+                        .map(|prefix| format!("{}{}", prefix, id))
+                        .collect()
+                });
+                (name.clone(), id)
+            })
+            .collect();
+
+        let mut ts = String::new();
+        writeln!(ts, "// Generated from strict encoding schema; do not edit.").ok();
+        writeln!(ts).ok();
+        ts.push_str(RUNTIME);
+        for (name, ty) in self {
+            let id = &ids[name];
+            match self.alias(name) {
+                Some(aliased) => alias(&mut ts, &ids, id, aliased),
+                None => structure(&mut ts, &ids, id, ty),
+            }
+            codec(&mut ts, id);
+        }
+        ts
+    }
+}
+
+fn alias(ts: &mut String, ids: &Idents, name: &str, ty: &TypeRef) {
+    writeln!(ts).ok();
+    writeln!(ts, "/** {} */", ty).ok();
+    writeln!(ts, "export type {} = {};", name, type_ref(ids, ty)).ok();
+
+    writeln!(ts).ok();
+    writeln!(ts, "export function read{0}(r: StrictReader): {0} {{", name).ok();
+    writeln!(ts, "  return {};", read_ref(ids, ty)).ok();
+    writeln!(ts, "}}").ok();
+
+    writeln!(ts).ok();
+    writeln!(ts, "export function write{0}(w: StrictWriter, value: {0}): void {{", name).ok();
+    writeln!(ts, "  {};", write_ref(ids, ty, "value", 0)).ok();
+    writeln!(ts, "}}").ok();
+}
+
+fn structure(ts: &mut String, ids: &Idents, name: &str, ty: &StructType) {
+    writeln!(ts).ok();
+    writeln!(ts, "/** {} */", ty).ok();
+    writeln!(ts, "export interface {} {{", name).ok();
+    for (no, field) in ty.into_iter().enumerate() {
+        let mut field_ty = type_ref(ids, &field.ty);
+        if field.optional {
+            field_ty.push_str(" | null");
+        }
+        writeln!(ts, "  f{}: {};", no, field_ty).ok();
+    }
+    writeln!(ts, "}}").ok();
+
+    writeln!(ts).ok();
+    writeln!(ts, "export function read{0}(r: StrictReader): {0} {{", name).ok();
+    writeln!(ts, "  return {{").ok();
+    for (no, field) in ty.into_iter().enumerate() {
+        let mut expr = read_ref(ids, &field.ty);
+        if field.optional {
+            expr = format!("r.optional(() => {})", expr);
+        }
+        writeln!(ts, "    f{}: {},", no, expr).ok();
+    }
+    writeln!(ts, "  }};").ok();
+    writeln!(ts, "}}").ok();
+
+    writeln!(ts).ok();
+    writeln!(ts, "export function write{0}(w: StrictWriter, value: {0}): void {{", name).ok();
+    for (no, field) in ty.into_iter().enumerate() {
+        let value = format!("value.f{}", no);
+        let stmt = if field.optional {
+            format!("w.optional({}, (v0) => {})", value, write_ref(ids, &field.ty, "v0", 1))
+        } else {
+            write_ref(ids, &field.ty, &value, 0)
+        };
+        writeln!(ts, "  {};", stmt).ok();
+    }
+    writeln!(ts, "}}").ok();
+}
+
+fn codec(ts: &mut String, name: &str) {
+    writeln!(ts).ok();
+    writeln!(ts, "export function decode{0}(view: DataView): {0} {{", name).ok();
+    writeln!(ts, "  const reader = new StrictReader(view);").ok();
+    writeln!(ts, "  const value = read{}(reader);", name).ok();
+    writeln!(ts, "  reader.finish();").ok();
+    writeln!(ts, "  return value;").ok();
+    writeln!(ts, "}}").ok();
+
+    writeln!(ts).ok();
+    writeln!(ts, "export function encode{0}(value: {0}): Uint8Array {{", name).ok();
+    writeln!(ts, "  const writer = new StrictWriter();").ok();
+    writeln!(ts, "  write{}(writer, value);", name).ok();
+    writeln!(ts, "  return writer.finish();").ok();
+    writeln!(ts, "}}").ok();
+}
+
+fn primitive(ty: PrimitiveType) -> &'static str {
+    match ty {
+        PrimitiveType::AsciiChar | PrimitiveType::UnicodeChar => "string",
+        PrimitiveType::F32 | PrimitiveType::F64 => "number",
+        ty if ty.is_float() => "Uint8Array",
+        ty if ty.fixed_size().unwrap_or_default() <= 4 => "number",
+        _ => "bigint",
+    }
+}
+
+/// Returns TypeScript type of the data.
+fn constr<T>(ty: &TypeConstr<T>, item: &dyn Fn(&T) -> String) -> String
+where T: Clone + Ord + Eq + Hash + Debug {
+    match ty {
+        TypeConstr::Plain(ty) => item(ty),
+        TypeConstr::Array(_, ty) | TypeConstr::List(ty) | TypeConstr::Set(ty) => {
+            format!("{}[]", item(ty))
+        }
+        TypeConstr::Map(key, ty) => {
+            let key = constr(&TypeConstr::from(*key), &|ty| primitive(*ty).to_owned());
+            format!("Array<[{}, {}]>", key, item(ty))
+        }
+    }
+}
+
+fn type_ref(ids: &Idents, ty: &TypeRef) -> String {
+    match ty {
+        TypeRef::InPlace(ty) => constr(ty, &|ty| primitive(*ty).to_owned()),
+        TypeRef::NameRef(ty) => constr(ty, &|name| ids[name].clone()),
+        TypeRef::Nested(ty) => constr(ty, &|item| type_ref(ids, item)),
+    }
+}
+
+/// Returns TypeScript method name and size argument for the primitive.
+fn method(ty: PrimitiveType) -> (&'static str, Option<u16>) {
+    let size = ty.fixed_size();
+    match ty {
+        PrimitiveType::AsciiChar => ("ascii", None),
+        PrimitiveType::UnicodeChar => ("unicode", None),
+        PrimitiveType::U8 => ("u8", None),
+        PrimitiveType::U16 => ("u16", None),
+        PrimitiveType::U32 => ("u32", None),
+        PrimitiveType::I8 => ("i8", None),
+        PrimitiveType::I16 => ("i16", None),
+        PrimitiveType::I32 => ("i32", None),
+        PrimitiveType::F32 => ("f32", None),
+        PrimitiveType::F64 => ("f64", None),
+        ty if ty.is_float() => ("raw", size),
+        ty if ty.is_unsigned() => ("uint", size),
+        _ => ("int", size),
+    }
+}
+
+fn read_primitive(ty: PrimitiveType) -> String {
+    match method(ty) {
+        (method, Some(size)) => format!("r.{}({})", method, size),
+        (method, None) => format!("r.{}()", method),
+    }
+}
+
+fn read_constr<T>(ty: &TypeConstr<T>, item: &dyn Fn(&T) -> String) -> String
+where T: Clone + Ord + Eq + Hash + Debug {
+    match ty {
+        TypeConstr::Plain(ty) => item(ty),
+        TypeConstr::Array(len, ty) => format!("r.array({}, () => {})", len, item(ty)),
+        TypeConstr::List(ty) => format!("r.list(() => {})", item(ty)),
+        TypeConstr::Set(ty) => format!("r.set(() => {})", item(ty)),
+        TypeConstr::Map(key, ty) => {
+            let key = read_constr(&TypeConstr::from(*key), &|ty| read_primitive(*ty));
+            format!("r.map(() => {}, () => {})", key, item(ty))
+        }
+    }
+}
+
+fn read_ref(ids: &Idents, ty: &TypeRef) -> String {
+    match ty {
+        TypeRef::InPlace(ty) => read_constr(ty, &|ty| read_primitive(*ty)),
+        TypeRef::NameRef(ty) => read_constr(ty, &|name| format!("read{}(r)", ids[name])),
+        TypeRef::Nested(ty) => read_constr(ty, &|item| read_ref(ids, item)),
+    }
+}
+
+fn write_primitive(ty: PrimitiveType, value: &str) -> String {
+    match method(ty) {
+        (method, Some(size)) => format!("w.{}({}, {})", method, value, size),
+        (method, None) => format!("w.{}({})", method, value),
+    }
+}
+
+/// Returns TypeScript statement writing `value`; `depth` is used to name the
+/// variables of the nested closures.
+fn write_constr<T>(
+    ty: &TypeConstr<T>,
+    value: &str,
+    depth: usize,
+    item: &dyn Fn(&T, &str) -> String,
+) -> String
+where
+    T: Clone + Ord + Eq + Hash + Debug,
+{
+    let var = format!("v{}", depth);
+    match ty {
+        TypeConstr::Plain(ty) => item(ty, value),
+        TypeConstr::Array(len, ty) => {
+            format!("w.array({}, {}, ({}) => {})", value, len, var, item(ty, &var))
+        }
+        TypeConstr::List(ty) => format!("w.list({}, ({}) => {})", value, var, item(ty, &var)),
+        TypeConstr::Set(ty) => format!("w.set({}, ({}) => {})", value, var, item(ty, &var)),
+        TypeConstr::Map(key, ty) => {
+            let key_var = format!("k{}", depth);
+            let key = write_constr(&TypeConstr::from(*key), &key_var, depth + 1, &|ty, value| {
+                write_primitive(*ty, value)
+            });
+            format!("w.map({}, ({}) => {}, ({}) => {})", value, key_var, key, var, item(ty, &var))
+        }
+    }
+}
+
+fn write_ref(ids: &Idents, ty: &TypeRef, value: &str, depth: usize) -> String {
+    match ty {
+        TypeRef::InPlace(ty) => {
+            write_constr(ty, value, depth, &|ty, value| write_primitive(*ty, value))
+        }
+        TypeRef::NameRef(ty) => {
+            write_constr(ty, value, depth, &|name, value| {
+                format!("write{}(w, {})", ids[name], value)
+            })
+        }
+        TypeRef::Nested(ty) => {
+            write_constr(ty, value, depth, &|item, value| write_ref(ids, item, value, depth + 1))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::schema::test::{golden, tn, type_system};
+    use crate::{strict_vec, type_system, KeyType, StructField};
+
+    #[test]
+    fn typescript() { golden("types.ts", &type_system().to_typescript()); }
+
+    #[test]
+    fn primitives() {
+        let ts = type_system![
+            "Prims" :: {
+                StructField::primitive(PrimitiveType::U64),
+                StructField { ty: TypeRef::InPlace(TypeConstr::Set(PrimitiveType::I128)), optional: true },
+                StructField::primitive(PrimitiveType::F16),
+                StructField::map(KeyType::Array(4, PrimitiveType::U8), PrimitiveType::F32),
+            }
+        ];
+        let ts = ts.to_typescript();
+        assert!(ts.contains(
+            "export interface Prims {
+  f0: bigint;
+  f1: bigint[] | null;
+  f2: Uint8Array;
+  f3: Array<[number[], number]>;
+}"
+        ));
+        assert!(ts.contains(
+            "    f0: r.uint(8),
+    f1: r.optional(() => r.set(() => r.int(16))),
+    f2: r.raw(2),
+    f3: r.map(() => r.array(4, () => r.u8()), () => r.f32()),
+"
+        ));
+        assert!(ts.contains(
+            "  w.uint(value.f0, 8);
+  w.optional(value.f1, (v0) => w.set(v0, (v1) => w.int(v1, 16)));
+  w.raw(value.f2, 2);
+  w.map(value.f3, (k0) => w.array(k0, 4, (v1) => w.u8(v1)), (v0) => w.f32(v0));
+"
+        ));
+    }
+
+    #[test]
+    fn aliases() {
+        let mut ts = type_system![
+            "Input" :: { StructField::with("Txid"), StructField::primitive(PrimitiveType::U16) }
+        ];
+        ts.push_alias(tn("Txid"), TypeRef::InPlace(TypeConstr::Array(32, PrimitiveType::U8)))
+            .unwrap();
+        let ts = ts.to_typescript();
+        assert!(ts.contains(
+            "/** U8*32 */
+export type Txid = number[];
+
+export function readTxid(r: StrictReader): Txid {
+  return r.array(32, () => r.u8());
+}
+
+export function writeTxid(w: StrictWriter, value: Txid): void {
+  w.array(value, 32, (v0) => w.u8(v0));
+}
+
+export function decodeTxid(view: DataView): Txid {"
+        ));
+        assert!(ts.contains("  f0: Txid;\n"));
+    }
+
+    #[test]
+    fn identifiers() {
+        let ts = type_system![
+            "StrictReader" :: { StructField::with("Tx-Out"), StructField::with("string") },
+            "Tx-Out" :: { StructField::primitive(PrimitiveType::U8) },
+            "string" :: { StructField::primitive(PrimitiveType::U8) }
+        ];
+        let ts = ts.to_typescript();
+        assert!(ts.contains("export class StrictReader {"));
+        assert!(ts.contains(
+            "export interface StrictReader_2 {
+  f0: Tx_Out;
+  f1: string_2;
+}
+
+export function readStrictReader_2(r: StrictReader): StrictReader_2 {
+  return {
+    f0: readTx_Out(r),
+    f1: readstring_2(r),
+  };
+}"
+        ));
+        assert!(ts.contains("export function encodeTx_Out(value: Tx_Out): Uint8Array {"));
+    }
+}
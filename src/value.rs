@@ -0,0 +1,715 @@
+// Strict encoding schema library, implementing validation and parsing of strict
+// encoded data against the schema.
+//
+// Written in 2022 by
+//     Dr. Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2022 by LNP/BP Standards Association, Switzerland.
+//
+// You should have received a copy of the MIT License along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Dynamic representation of strict-encoded data parsed against a schema.
+
+use std::fmt::{self, Debug, Display, Formatter};
+use std::hash::Hash;
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+
+use amplify::hex::ToHex;
+use strict_encoding::StrictDecode;
+
+use crate::{
+    DataPath, DataStep, KeyType, PrimitiveType, StructField, StructType, TypeConstr, TypeName,
+    TypeRef, TypeSystem,
+};
+
+/// Primitive value, kept in its strict-encoded form (little-endian bytes for
+/// numbers and character data without the length prefix for characters).
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct PrimitiveValue {
+    ty: PrimitiveType,
+    data: Vec<u8>,
+}
+
+impl PrimitiveValue {
+    /// Constructs primitive value from its encoded representation, checking
+    /// that the data length matches the primitive type.
+    pub fn with(ty: PrimitiveType, data: impl Into<Vec<u8>>) -> Option<PrimitiveValue> {
+        let data = data.into();
+        match ty.fixed_size() {
+            Some(len) if len as usize != data.len() => None,
+            None if data.len() > u16::MAX as usize => None,
+            _ => Some(PrimitiveValue { ty, data }),
+        }
+    }
+
+    pub fn u8(val: u8) -> Self { Self::from_le(PrimitiveType::U8, val.to_le_bytes()) }
+    pub fn u16(val: u16) -> Self { Self::from_le(PrimitiveType::U16, val.to_le_bytes()) }
+    pub fn u32(val: u32) -> Self { Self::from_le(PrimitiveType::U32, val.to_le_bytes()) }
+    pub fn u64(val: u64) -> Self { Self::from_le(PrimitiveType::U64, val.to_le_bytes()) }
+    pub fn u128(val: u128) -> Self { Self::from_le(PrimitiveType::U128, val.to_le_bytes()) }
+
+    pub fn i8(val: i8) -> Self { Self::from_le(PrimitiveType::I8, val.to_le_bytes()) }
+    pub fn i16(val: i16) -> Self { Self::from_le(PrimitiveType::I16, val.to_le_bytes()) }
+    pub fn i32(val: i32) -> Self { Self::from_le(PrimitiveType::I32, val.to_le_bytes()) }
+    pub fn i64(val: i64) -> Self { Self::from_le(PrimitiveType::I64, val.to_le_bytes()) }
+    pub fn i128(val: i128) -> Self { Self::from_le(PrimitiveType::I128, val.to_le_bytes()) }
+
+    pub fn f32(val: f32) -> Self { Self::from_le(PrimitiveType::F32, val.to_le_bytes()) }
+    pub fn f64(val: f64) -> Self { Self::from_le(PrimitiveType::F64, val.to_le_bytes()) }
+
+    pub fn ascii(s: &str) -> Option<Self> {
+        if !s.is_ascii() {
+            return None;
+        }
+        Self::with(PrimitiveType::AsciiChar, s.as_bytes())
+    }
+
+    pub fn unicode(s: &str) -> Option<Self> { Self::with(PrimitiveType::UnicodeChar, s.as_bytes()) }
+
+    fn from_le(ty: PrimitiveType, data: impl AsRef<[u8]>) -> Self {
+        PrimitiveValue {
+            ty,
+            data: data.as_ref().to_vec(),
+        }
+    }
+
+    #[inline]
+    pub fn ty(&self) -> PrimitiveType { self.ty }
+
+    /// Returns value in its encoded form (without the length prefix for the
+    /// character types).
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] { &self.data }
+
+    /// Returns value of an unsigned or non-negative signed integer, if it fits
+    /// into `u128`.
+    pub fn as_u128(&self) -> Option<u128> {
+        match self.as_int()? {
+            (false, abs) => Some(abs),
+            (true, 0) => Some(0),
+            (true, _) => None,
+        }
+    }
+
+    /// Returns value of a signed or unsigned integer, if it fits into `i128`.
+    pub fn as_i128(&self) -> Option<i128> {
+        match self.as_int()? {
+            (false, abs) => i128::try_from(abs).ok(),
+            (true, abs) if abs == i128::MIN.unsigned_abs() => Some(i128::MIN),
+            (true, abs) => i128::try_from(abs).ok().map(|v| -v),
+        }
+    }
+
+    /// Returns integer value as a sign flag (`true` for negative values) and
+    /// its absolute value, if the absolute value fits into `u128`.
+    pub(crate) fn as_int(&self) -> Option<(bool, u128)> {
+        let (negative, data) = self.int_abs()?;
+        if data.iter().skip(16).any(|byte| *byte != 0) {
+            return None;
+        }
+        let mut buf = [0u8; 16];
+        let len = data.len().min(16);
+        buf[..len].copy_from_slice(&data[..len]);
+        Some((negative, u128::from_le_bytes(buf)))
+    }
+
+    /// Formats integer value of any width as a decimal number, with `-` sign
+    /// for negative values.
+    pub fn to_decimal(&self) -> Option<String> {
+        let (negative, mut data) = self.int_abs()?;
+        let mut digits = vec![];
+        loop {
+            // Long division of the little-endian absolute value by 10
+            let mut rem = 0u16;
+            for byte in data.iter_mut().rev() {
+                let cur = rem << 8 | *byte as u16;
+                *byte = (cur / 10) as u8;
+                rem = cur % 10;
+            }
+            digits.push(b'0' + rem as u8);
+            if data.iter().all(|byte| *byte == 0) {
+                break;
+            }
+        }
+        if negative {
+            digits.push(b'-');
+        }
+        digits.reverse();
+        Some(String::from_utf8(digits).expect("decimal digits are ASCII"))
+    }
+
+    /// Returns integer value as a sign flag (`true` for negative values) and
+    /// little-endian bytes of its absolute value.
+    fn int_abs(&self) -> Option<(bool, Vec<u8>)> {
+        if !self.ty.is_signed() && !self.ty.is_unsigned() {
+            return None;
+        }
+        let negative = self.is_negative();
+        // Computing two's complement for negative values
+        let mut data = self.data.clone();
+        if negative {
+            let mut carry = true;
+            for byte in &mut data {
+                *byte = !*byte;
+                if carry {
+                    let (val, overflow) = byte.overflowing_add(1);
+                    *byte = val;
+                    carry = overflow;
+                }
+            }
+        }
+        Some((negative, data))
+    }
+
+    /// Detects negative signed integer values.
+    pub(crate) fn is_negative(&self) -> bool {
+        self.ty.is_signed() && self.data.last().copied().unwrap_or_default() >= 0x80
+    }
+
+    /// Returns value of `F32` and `F64` floats.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self.ty {
+            PrimitiveType::F32 => {
+                Some(f32::from_le_bytes(self.data.as_slice().try_into().ok()?) as f64)
+            }
+            PrimitiveType::F64 => Some(f64::from_le_bytes(self.data.as_slice().try_into().ok()?)),
+            _ => None,
+        }
+    }
+
+    /// Returns character data as a string, if they are valid for the
+    /// character type.
+    pub fn as_str(&self) -> Option<&str> {
+        match self.ty {
+            PrimitiveType::AsciiChar if self.data.is_ascii() => {
+                std::str::from_utf8(&self.data).ok()
+            }
+            PrimitiveType::UnicodeChar => std::str::from_utf8(&self.data).ok(),
+            _ => None,
+        }
+    }
+}
+
+/// Checks that character data are valid for the character type; data of
+/// non-character types are always valid.
+pub(crate) fn check_chars(ty: PrimitiveType, data: &[u8]) -> Result<(), DataError> {
+    match ty {
+        PrimitiveType::AsciiChar => match data.iter().find(|byte| !byte.is_ascii()) {
+            Some(byte) => Err(DataError::NonAscii(*byte)),
+            None => Ok(()),
+        },
+        PrimitiveType::UnicodeChar => {
+            std::str::from_utf8(data).map(|_| ()).map_err(|_| DataError::InvalidUtf8)
+        }
+        _ => Ok(()),
+    }
+}
+
+impl Display for PrimitiveValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if let Some((negative, abs)) = self.as_int() {
+            if negative {
+                f.write_str("-")?;
+            }
+            return Display::fmt(&abs, f);
+        }
+        if let Some(val) = self.as_f64() {
+            return Display::fmt(&val, f);
+        }
+        if let Some(s) = self.as_str() {
+            return Debug::fmt(s, f);
+        }
+        // Wide numbers are displayed in big-endian hex, invalid characters as
+        // raw hex data
+        let mut data = self.data.clone();
+        if !self.ty.is_char() {
+            data.reverse();
+        }
+        write!(f, "0x{}", data.to_hex())
+    }
+}
+
+/// Dynamic value of a strict-encoded data, structured according to the
+/// schema.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, From)]
+pub enum Value {
+    #[from]
+    Primitive(PrimitiveValue),
+
+    /// Structure with the values of each of its fields; absent optional
+    /// fields are represented by `None`.
+    Struct(Vec<Option<Value>>),
+
+    /// Elements of an array, list or set.
+    List(Vec<Value>),
+
+    /// Map entries in the order of the encoded keys.
+    Map(Vec<(Value, Value)>),
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Primitive(val) => Display::fmt(val, f),
+            Value::Struct(fields) => {
+                f.write_str("(")?;
+                for (pos, field) in fields.iter().enumerate() {
+                    if pos > 0 {
+                        f.write_str(", ")?;
+                    }
+                    match field {
+                        None => f.write_str("~")?,
+                        Some(val) => Display::fmt(val, f)?,
+                    }
+                }
+                f.write_str(")")
+            }
+            Value::List(items) => {
+                f.write_str("[")?;
+                for (pos, item) in items.iter().enumerate() {
+                    if pos > 0 {
+                        f.write_str(", ")?;
+                    }
+                    Display::fmt(item, f)?;
+                }
+                f.write_str("]")
+            }
+            Value::Map(entries) => {
+                f.write_str("{")?;
+                for (pos, (key, val)) in entries.iter().enumerate() {
+                    if pos > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}: {}", key, val)?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+impl Value {
+    /// Returns nested value located at the given path. Paths pointing to map
+    /// keys resolve into the key value.
+    pub fn get(&self, path: &DataPath) -> Option<&Value> {
+        let mut val = self;
+        let mut steps = path.into_iter().peekable();
+        while let Some(step) = steps.next() {
+            val = match (step, val) {
+                (DataStep::StructField(no), Value::Struct(fields)) => {
+                    fields.get(*no as usize)?.as_ref()?
+                }
+                (DataStep::ArrayIndex(no), Value::List(items)) => items.get(*no as usize)?,
+                (DataStep::MapEntry(no), Value::Map(entries)) => {
+                    let (key, val) = entries.get(*no as usize)?;
+                    if let Some(DataStep::MapKey(_)) = steps.peek() {
+                        steps.next();
+                        key
+                    } else {
+                        val
+                    }
+                }
+                _ => return None,
+            };
+        }
+        Some(val)
+    }
+}
+
+/// Errors in strict-encoded data detected during their parsing against a
+/// schema.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum DataError {
+    /// type `{0}` is absent in the type system
+    UnknownType(TypeName),
+
+    /// unexpected end of data
+    UnexpectedEof,
+
+    /// I/O error: {0}
+    Io(String),
+
+    /// invalid optional field tag {0:#04x}
+    OptionalTag(u8),
+
+    /// non-ASCII byte {0:#04x} in ASCII character data
+    NonAscii(u8),
+
+    /// character data are not a valid UTF-8 string
+    InvalidUtf8,
+
+    /// character data length {0} exceeds 65535 bytes
+    CharsTooLong(usize),
+
+    /// number of collection items {0} exceeds 65535
+    TooManyItems(usize),
+
+    /// repeated set element
+    RepeatedSetItem,
+
+    /// set elements are not sorted in lexicographic order
+    UnsortedSetItems,
+
+    /// repeated map key
+    RepeatedMapKey,
+
+    /// map keys are not sorted in lexicographic order
+    UnsortedMapKeys,
+
+    /// {0} bytes of data left after the end of the value
+    DataNotConsumed(u64),
+}
+
+impl From<io::Error> for DataError {
+    fn from(err: io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::UnexpectedEof => DataError::UnexpectedEof,
+            _ => DataError::Io(err.to_string()),
+        }
+    }
+}
+
+impl From<strict_encoding::Error> for DataError {
+    fn from(err: strict_encoding::Error) -> Self {
+        match err {
+            strict_encoding::Error::Io(err) => io::Error::from(err).into(),
+            err => DataError::Io(err.to_string()),
+        }
+    }
+}
+
+/// Error parsing strict-encoded data against a schema, specifying location of
+/// the failure within the data.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Display, Error)]
+#[display("{error} at {path}")]
+pub struct DecodeError {
+    pub path: DataPath,
+    pub error: DataError,
+}
+
+impl DecodeError {
+    pub fn with(path: &DataPath, error: impl Into<DataError>) -> Self {
+        DecodeError {
+            path: path.clone(),
+            error: error.into(),
+        }
+    }
+}
+
+/// Parsing of strict-encoded data into [`Value`] according to the schema.
+pub trait Decode {
+    fn decode(
+        &self,
+        ts: &TypeSystem,
+        buf: &mut (impl Read + Seek),
+        path: &mut DataPath,
+    ) -> Result<Value, DecodeError>;
+}
+
+impl TypeSystem {
+    /// Parses data encoded as an instance of type `root`, requiring all data
+    /// to be consumed.
+    pub fn decode(&self, root: &TypeName, data: impl AsRef<[u8]>) -> Result<Value, DecodeError> {
+        let data = data.as_ref();
+        let mut path = DataPath::new();
+        let mut cursor = Cursor::new(data);
+        let value = root.decode(self, &mut cursor, &mut path)?;
+        let left = data.len() as u64 - cursor.position();
+        if left > 0 {
+            return Err(DecodeError::with(&path, DataError::DataNotConsumed(left)));
+        }
+        Ok(value)
+    }
+}
+
+impl Decode for TypeName {
+    fn decode(
+        &self,
+        ts: &TypeSystem,
+        buf: &mut (impl Read + Seek),
+        path: &mut DataPath,
+    ) -> Result<Value, DecodeError> {
+        match ts.get(self) {
+            None => Err(DecodeError::with(path, DataError::UnknownType(self.clone()))),
+            Some(ty) => ty.decode(ts, buf, path),
+        }
+    }
+}
+
+impl Decode for StructType {
+    fn decode(
+        &self,
+        ts: &TypeSystem,
+        buf: &mut (impl Read + Seek),
+        path: &mut DataPath,
+    ) -> Result<Value, DecodeError> {
+        let mut fields = Vec::with_capacity(self.len() as usize);
+        for (no, field) in self.into_iter().enumerate() {
+            path.push(DataStep::StructField(no as u16));
+            fields.push(decode_field(field, ts, buf, path)?);
+            path.pop();
+        }
+        Ok(Value::Struct(fields))
+    }
+}
+
+fn decode_field(
+    field: &StructField,
+    ts: &TypeSystem,
+    mut buf: &mut (impl Read + Seek),
+    path: &mut DataPath,
+) -> Result<Option<Value>, DecodeError> {
+    if field.optional {
+        match u8::strict_decode(&mut buf).map_err(|err| DecodeError::with(path, err))? {
+            0 => Ok(None),
+            1 => field.ty.decode(ts, buf, path).map(Some),
+            tag => Err(DecodeError::with(path, DataError::OptionalTag(tag))),
+        }
+    } else {
+        field.ty.decode(ts, buf, path).map(Some)
+    }
+}
+
+impl Decode for TypeRef {
+    fn decode(
+        &self,
+        ts: &TypeSystem,
+        buf: &mut (impl Read + Seek),
+        path: &mut DataPath,
+    ) -> Result<Value, DecodeError> {
+        match self {
+            TypeRef::InPlace(ty) => ty.decode(ts, buf, path),
+            TypeRef::NameRef(ty) => ty.decode(ts, buf, path),
+            TypeRef::Nested(ty) => ty.decode(ts, buf, path),
+        }
+    }
+}
+
+impl Decode for Box<TypeRef> {
+    fn decode(
+        &self,
+        ts: &TypeSystem,
+        buf: &mut (impl Read + Seek),
+        path: &mut DataPath,
+    ) -> Result<Value, DecodeError> {
+        self.as_ref().decode(ts, buf, path)
+    }
+}
+
+/// Decodes a collection item, returning its value together with its encoded
+/// representation.
+fn decode_item(
+    ty: &impl Decode,
+    ts: &TypeSystem,
+    buf: &mut (impl Read + Seek),
+    path: &mut DataPath,
+) -> Result<(Value, Vec<u8>), DecodeError> {
+    let err = |path: &DataPath, err: io::Error| DecodeError::with(path, err);
+    let pos_from = buf.stream_position().map_err(|e| err(path, e))?;
+    let val = ty.decode(ts, buf, path)?;
+    let pos_to = buf.stream_position().map_err(|e| err(path, e))?;
+    buf.seek(SeekFrom::Start(pos_from)).map_err(|e| err(path, e))?;
+    let mut data = vec![0u8; (pos_to - pos_from) as usize];
+    buf.read_exact(&mut data).map_err(|e| err(path, e))?;
+    Ok((val, data))
+}
+
+impl<T> Decode for TypeConstr<T>
+where T: Clone + Ord + Eq + Hash + Debug + Decode
+{
+    fn decode(
+        &self,
+        ts: &TypeSystem,
+        mut buf: &mut (impl Read + Seek),
+        path: &mut DataPath,
+    ) -> Result<Value, DecodeError> {
+        let len = match self {
+            TypeConstr::Plain(ty) => return ty.decode(ts, buf, path),
+            TypeConstr::Array(len, _) => *len,
+            TypeConstr::List(_) | TypeConstr::Set(_) | TypeConstr::Map(_, _) => {
+                u16::strict_decode(&mut buf).map_err(|err| DecodeError::with(path, err))?
+            }
+        };
+
+        let mut items = Vec::with_capacity(len as usize);
+        let mut entries = Vec::with_capacity(len as usize);
+        let mut last: Option<Vec<u8>> = None;
+        for no in 0..len {
+            match self {
+                TypeConstr::Plain(_) => unreachable!(),
+                TypeConstr::Array(_, ty) | TypeConstr::List(ty) => {
+                    path.push(DataStep::ArrayIndex(no));
+                    items.push(ty.decode(ts, buf, path)?);
+                }
+                TypeConstr::Set(ty) => {
+                    path.push(DataStep::ArrayIndex(no));
+                    let (val, data) = decode_item(ty, ts, buf, path)?;
+                    // Ensure lexicographic item uniqueness and sort order
+                    match last {
+                        Some(ref last) if data == *last => {
+                            return Err(DecodeError::with(path, DataError::RepeatedSetItem))
+                        }
+                        Some(ref last) if data < *last => {
+                            return Err(DecodeError::with(path, DataError::UnsortedSetItems))
+                        }
+                        _ => {}
+                    }
+                    last = Some(data);
+                    items.push(val);
+                }
+                TypeConstr::Map(key_ty, ty) => {
+                    path.push(DataStep::MapEntry(no));
+                    path.push(DataStep::MapKey(*key_ty));
+                    let (key, data) = decode_item(key_ty, ts, buf, path)?;
+                    // Ensure lexicographic key uniqueness and sort order
+                    match last {
+                        Some(ref last) if data == *last => {
+                            return Err(DecodeError::with(path, DataError::RepeatedMapKey))
+                        }
+                        Some(ref last) if data < *last => {
+                            return Err(DecodeError::with(path, DataError::UnsortedMapKeys))
+                        }
+                        _ => {}
+                    }
+                    last = Some(data);
+                    path.pop();
+                    entries.push((key, ty.decode(ts, buf, path)?));
+                }
+            }
+            path.pop();
+        }
+
+        Ok(match self {
+            TypeConstr::Map(..) => Value::Map(entries),
+            _ => Value::List(items),
+        })
+    }
+}
+
+impl Decode for KeyType {
+    fn decode(
+        &self,
+        ts: &TypeSystem,
+        buf: &mut (impl Read + Seek),
+        path: &mut DataPath,
+    ) -> Result<Value, DecodeError> {
+        match self {
+            KeyType::Primitive(ty) => ty.decode(ts, buf, path),
+            KeyType::Array(len, ty) => TypeConstr::Array(*len, *ty).decode(ts, buf, path),
+            KeyType::List(ty) => TypeConstr::List(*ty).decode(ts, buf, path),
+        }
+    }
+}
+
+impl Decode for PrimitiveType {
+    fn decode(
+        &self,
+        _: &TypeSystem,
+        mut buf: &mut (impl Read + Seek),
+        path: &mut DataPath,
+    ) -> Result<Value, DecodeError> {
+        let len = match self.fixed_size() {
+            Some(len) => len,
+            None => u16::strict_decode(&mut buf).map_err(|err| DecodeError::with(path, err))?,
+        };
+        let mut data = vec![0u8; len as usize];
+        buf.read_exact(&mut data).map_err(|err| DecodeError::with(path, err))?;
+        check_chars(*self, &data).map_err(|err| DecodeError::with(path, err))?;
+        Ok(Value::Primitive(PrimitiveValue { ty: *self, data }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::schema::test::{nested_type_system, tn, transaction, type_system};
+
+    #[test]
+    fn decode_transaction() {
+        let ts = type_system();
+        let value = ts.decode(&tn("Transaction"), transaction()).unwrap();
+        let txid = |byte: &str| format!("([{}])", [byte; 32].join(", "));
+        assert_eq!(
+            value.to_string(),
+            format!(
+                "(1, [(({}, 2), ([1, 2]), ([([255])])), (({}, 0), ([]), ([]))], [(100000, \
+                 ([81])), (200000, ([]))], 0)",
+                txid("1"),
+                txid("2")
+            )
+        );
+        let path = DataPath::new()
+            .with(DataStep::StructField(2))
+            .with(DataStep::ArrayIndex(1))
+            .with(DataStep::StructField(0));
+        assert_eq!(path.to_string(), ".2[1].0");
+        assert_eq!(value.get(&path), Some(&Value::Primitive(PrimitiveValue::u64(200000))));
+    }
+
+    #[test]
+    fn decode_nested() {
+        let ts = nested_type_system();
+        let value = ts.decode(&tn("Transaction"), transaction()).unwrap();
+        let txid = |byte: &str| format!("([{}])", [byte; 32].join(", "));
+        assert_eq!(
+            value.to_string(),
+            format!(
+                "(1, [(({}, 2), ([1, 2]), [[255]]), (({}, 0), ([]), [])], [(100000, ([81])), \
+                 (200000, ([]))], 0)",
+                txid("1"),
+                txid("2")
+            )
+        );
+    }
+
+    #[test]
+    fn decode_errors() {
+        let ts = type_system();
+        let mut data = transaction();
+        data.push(0);
+        assert_eq!(
+            ts.decode(&tn("Transaction"), &data).unwrap_err(),
+            DecodeError::with(&DataPath::new(), DataError::DataNotConsumed(1))
+        );
+        data.truncate(data.len() - 3);
+        assert_eq!(
+            ts.decode(&tn("Transaction"), &data).unwrap_err().to_string(),
+            "unexpected end of data at .3"
+        );
+    }
+
+    #[test]
+    fn decode_map() {
+        let ts = type_system();
+        // Name: ["a"], map { "k" -> ("v") }
+        let data = [1, 0, 1, 0, b'a', 1, 0, 1, 0, 1, 0, b'k', 1, 0, 1, 0, b'v'];
+        let value = ts.decode(&tn("Meta"), data).unwrap();
+        assert_eq!(value.to_string(), r#"(["a"], {["k"]: (["v"])})"#);
+
+        // Repeated key
+        let data = [0, 0, 2, 0, 1, 0, 1, 0, b'k', 0, 0, 1, 0, 1, 0, b'k', 0, 0];
+        assert_eq!(
+            ts.decode(&tn("Meta"), data).unwrap_err().to_string(),
+            "repeated map key at .1{1}<UnicodeChar[]>"
+        );
+    }
+
+    #[test]
+    fn primitive_display() {
+        assert_eq!(PrimitiveValue::i16(-300).to_string(), "-300");
+        assert_eq!(PrimitiveValue::i128(i128::MIN).as_i128(), Some(i128::MIN));
+        assert_eq!(PrimitiveValue::u128(u128::MAX).as_u128(), Some(u128::MAX));
+        assert_eq!(PrimitiveValue::f64(1.5).to_string(), "1.5");
+        let wide = PrimitiveValue::with(PrimitiveType::U256, [0xFFu8; 32]).unwrap();
+        assert_eq!(wide.as_u128(), None);
+        assert_eq!(wide.to_string(), format!("0x{}", "ff".repeat(32)));
+        let negative = PrimitiveValue::with(PrimitiveType::I256, [0xFFu8; 32]).unwrap();
+        assert_eq!(negative.as_i128(), Some(-1));
+        assert_eq!(negative.to_decimal().unwrap(), "-1");
+        assert_eq!(PrimitiveValue::u8(0).to_decimal().unwrap(), "0");
+        assert_eq!(PrimitiveValue::i128(i128::MIN).to_decimal().unwrap(), i128::MIN.to_string());
+        assert_eq!(PrimitiveValue::f64(1.5).to_decimal(), None);
+    }
+}
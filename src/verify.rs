@@ -16,67 +16,214 @@ use std::io::{Read, Seek, SeekFrom};
 
 use strict_encoding::StrictDecode;
 
+use crate::value::check_chars;
 use crate::{
-    KeyType, PrimitiveType, StructField, StructType, TypeConstr, TypeName, TypeRef, TypeSystem,
+    DataPath, DataStep, KeyType, PrimitiveType, StructField, StructType, TypeConstr, TypeName,
+    TypeRef, TypeSystem,
 };
 
+/// Callbacks invoked during data verification with [`Verify::verify_with`].
+///
+/// Each callback receives path to the data item and the offset of its first
+/// byte in the verified data. Callbacks are invoked in the order of the data
+/// encoding; all methods have empty default implementations.
+#[allow(unused_variables)]
+pub trait Visitor {
+    /// Called before verifying structure data; followed by [`Visitor::leave`]
+    /// once all structure fields are verified.
+    fn enter_struct(&mut self, path: &DataPath, offset: u64, name: &TypeName) {}
+
+    /// Called before verifying each structure field (including its optional
+    /// tag). The path includes the field step.
+    fn field(&mut self, path: &DataPath, offset: u64, no: u16, field: &StructField) {}
+
+    /// Called for the tag byte of an optional field.
+    fn optional_tag(&mut self, path: &DataPath, offset: u64, tag: u8) {}
+
+    /// Called for the 16-bit length prefix of a list, set or map.
+    fn list_len(&mut self, path: &DataPath, offset: u64, len: u16) {}
+
+    /// Called after verifying map key with the key encoding.
+    fn map_key(&mut self, path: &DataPath, offset: u64, key: &KeyType, data: &[u8]) {}
+
+    /// Called for each primitive value. For character types the offset
+    /// points to the 16-bit length prefix, while the data do not include it.
+    fn primitive(&mut self, path: &DataPath, offset: u64, ty: PrimitiveType, data: &[u8]) {}
+
+    /// Called once structure data are verified.
+    fn leave(&mut self, path: &DataPath, offset: u64) {}
+
+    /// Called once the data item at the path fails verification; the offset
+    /// points to the position where the failure was detected.
+    fn fail(&mut self, path: &DataPath, offset: u64) {}
+}
+
+impl Visitor for () {}
+
 pub trait Verify {
     fn verify(&self, ts: &TypeSystem, buf: &mut (impl Read + Seek)) -> bool;
+
+    /// Verifies data located at `path`, calling `visitor` callbacks for each
+    /// of the data items. The default implementation just calls
+    /// [`Verify::verify`], without invoking any of the callbacks.
+    #[allow(unused_variables)]
+    fn verify_with(
+        &self,
+        ts: &TypeSystem,
+        buf: &mut (impl Read + Seek),
+        path: &mut DataPath,
+        visitor: &mut impl Visitor,
+    ) -> bool {
+        self.verify(ts, buf)
+    }
+}
+
+macro_rules! pos {
+    ($buf:ident) => {
+        $buf.stream_position().expect("medium without stream position")
+    };
+}
+
+macro_rules! fail {
+    ($visitor:ident, $path:ident, $buf:ident) => {{
+        $visitor.fail($path, pos!($buf));
+        return false;
+    }};
 }
 
 impl Verify for TypeName {
     fn verify(&self, ts: &TypeSystem, buf: &mut (impl Read + Seek)) -> bool {
+        self.verify_with(ts, buf, &mut DataPath::new(), &mut ())
+    }
+
+    fn verify_with(
+        &self,
+        ts: &TypeSystem,
+        buf: &mut (impl Read + Seek),
+        path: &mut DataPath,
+        visitor: &mut impl Visitor,
+    ) -> bool {
         match ts.get(self) {
-            None => false,
-            Some(ty) => ty.verify(ts, buf),
+            None => fail!(visitor, path, buf),
+            Some(ty) => {
+                visitor.enter_struct(path, pos!(buf), self);
+                if !ty.verify_with(ts, buf, path, visitor) {
+                    return false;
+                }
+                visitor.leave(path, pos!(buf));
+                true
+            }
         }
     }
 }
 
 impl Verify for StructType {
     fn verify(&self, ts: &TypeSystem, buf: &mut (impl Read + Seek)) -> bool {
-        for field in self {
-            if !field.verify(ts, buf) {
+        self.verify_with(ts, buf, &mut DataPath::new(), &mut ())
+    }
+
+    fn verify_with(
+        &self,
+        ts: &TypeSystem,
+        buf: &mut (impl Read + Seek),
+        path: &mut DataPath,
+        visitor: &mut impl Visitor,
+    ) -> bool {
+        for (no, field) in self.into_iter().enumerate() {
+            path.push(DataStep::StructField(no as u16));
+            visitor.field(path, pos!(buf), no as u16, field);
+            if !field.verify_with(ts, buf, path, visitor) {
                 return false;
             }
+            path.pop();
         }
         true
     }
 }
 
 impl Verify for StructField {
-    fn verify(&self, ts: &TypeSystem, mut buf: &mut (impl Read + Seek)) -> bool {
+    fn verify(&self, ts: &TypeSystem, buf: &mut (impl Read + Seek)) -> bool {
+        self.verify_with(ts, buf, &mut DataPath::new(), &mut ())
+    }
+
+    fn verify_with(
+        &self,
+        ts: &TypeSystem,
+        mut buf: &mut (impl Read + Seek),
+        path: &mut DataPath,
+        visitor: &mut impl Visitor,
+    ) -> bool {
         if self.optional {
-            match u8::strict_decode(&mut buf) {
-                Err(_) => false,
-                Ok(0) => true,
-                Ok(1) => self.ty.verify(ts, buf),
-                Ok(_) => false,
+            let offset = pos!(buf);
+            let tag = match u8::strict_decode(&mut buf) {
+                Err(_) => fail!(visitor, path, buf),
+                Ok(tag) => tag,
+            };
+            visitor.optional_tag(path, offset, tag);
+            match tag {
+                0 => true,
+                1 => self.ty.verify_with(ts, buf, path, visitor),
+                _ => {
+                    visitor.fail(path, offset);
+                    false
+                }
             }
         } else {
-            self.ty.verify(ts, buf)
+            self.ty.verify_with(ts, buf, path, visitor)
         }
     }
 }
 
 impl Verify for TypeRef {
     fn verify(&self, ts: &TypeSystem, buf: &mut (impl Read + Seek)) -> bool {
+        self.verify_with(ts, buf, &mut DataPath::new(), &mut ())
+    }
+
+    fn verify_with(
+        &self,
+        ts: &TypeSystem,
+        buf: &mut (impl Read + Seek),
+        path: &mut DataPath,
+        visitor: &mut impl Visitor,
+    ) -> bool {
         match self {
-            TypeRef::InPlace(ty) => ty.verify(ts, buf),
-            TypeRef::NameRef(ty) => ty.verify(ts, buf),
+            TypeRef::InPlace(ty) => ty.verify_with(ts, buf, path, visitor),
+            TypeRef::NameRef(ty) => ty.verify_with(ts, buf, path, visitor),
+            TypeRef::Nested(ty) => ty.verify_with(ts, buf, path, visitor),
         }
     }
 }
 
+impl Verify for Box<TypeRef> {
+    fn verify(&self, ts: &TypeSystem, buf: &mut (impl Read + Seek)) -> bool {
+        self.as_ref().verify(ts, buf)
+    }
+
+    fn verify_with(
+        &self,
+        ts: &TypeSystem,
+        buf: &mut (impl Read + Seek),
+        path: &mut DataPath,
+        visitor: &mut impl Visitor,
+    ) -> bool {
+        self.as_ref().verify_with(ts, buf, path, visitor)
+    }
+}
+
 impl<T> Verify for TypeConstr<T>
 where T: Clone + Ord + Eq + Hash + Debug + Verify
 {
-    fn verify(&self, ts: &TypeSystem, mut buf: &mut (impl Read + Seek)) -> bool {
-        macro_rules! pos {
-            () => {
-                buf.stream_position().expect("medium without stream position")
-            };
-        }
+    fn verify(&self, ts: &TypeSystem, buf: &mut (impl Read + Seek)) -> bool {
+        self.verify_with(ts, buf, &mut DataPath::new(), &mut ())
+    }
+
+    fn verify_with(
+        &self,
+        ts: &TypeSystem,
+        mut buf: &mut (impl Read + Seek),
+        path: &mut DataPath,
+        visitor: &mut impl Visitor,
+    ) -> bool {
         macro_rules! read {
             ($pos_from:expr, $pos_to:expr) => {{
                 let len = $pos_to - $pos_from;
@@ -86,80 +233,98 @@ where T: Clone + Ord + Eq + Hash + Debug + Verify
                 vec
             }};
         }
+        macro_rules! read_len {
+            () => {{
+                let offset = pos!(buf);
+                match u16::strict_decode(&mut buf) {
+                    Err(_) => fail!(visitor, path, buf),
+                    Ok(len) => {
+                        visitor.list_len(path, offset, len);
+                        len
+                    }
+                }
+            }};
+        }
 
         match self {
-            TypeConstr::Plain(ty) => ty.verify(ts, buf),
+            TypeConstr::Plain(ty) => ty.verify_with(ts, buf, path, visitor),
             TypeConstr::Array(len, ty) => {
-                for _ in 0..*len {
-                    if !ty.verify(ts, buf) {
+                for no in 0..*len {
+                    path.push(DataStep::ArrayIndex(no));
+                    if !ty.verify_with(ts, buf, path, visitor) {
                         return false;
                     }
+                    path.pop();
                 }
                 true
             }
             TypeConstr::List(ty) => {
-                let len = match u16::strict_decode(&mut buf) {
-                    Err(_) => return false,
-                    Ok(len) => len,
-                };
-                for _ in 0..len {
-                    if !ty.verify(ts, buf) {
+                let len = read_len!();
+                for no in 0..len {
+                    path.push(DataStep::ArrayIndex(no));
+                    if !ty.verify_with(ts, buf, path, visitor) {
                         return false;
                     }
+                    path.pop();
                 }
                 true
             }
             TypeConstr::Set(ty) => {
-                let len = match u16::strict_decode(&mut buf) {
-                    Err(_) => return false,
-                    Ok(len) => len,
-                };
+                let len = read_len!();
                 let mut set = BTreeSet::new();
-                for _ in 0..len {
-                    let pos_from = pos!();
-                    if !ty.verify(ts, buf) {
+                for no in 0..len {
+                    path.push(DataStep::ArrayIndex(no));
+                    let pos_from = pos!(buf);
+                    if !ty.verify_with(ts, buf, path, visitor) {
                         return false;
                     }
                     // Ensure lexicographic key uniqueness and sort order
-                    let pos_to = pos!();
+                    let pos_to = pos!(buf);
                     let val = read!(pos_from, pos_to);
                     if let Some(last) = set.iter().last() {
                         if val <= *last {
+                            visitor.fail(path, pos_from);
                             return false;
                         }
                     }
                     if !set.insert(val) {
+                        visitor.fail(path, pos_from);
                         return false;
                     }
+                    path.pop();
                 }
                 true
             }
             TypeConstr::Map(key, val) => {
-                let len = match u16::strict_decode(&mut buf) {
-                    Err(_) => return false,
-                    Ok(len) => len,
-                };
+                let len = read_len!();
                 let mut set = BTreeSet::new();
-                for _ in 0..len {
-                    let pos_from = pos!();
-                    if !key.verify(ts, buf) {
+                for no in 0..len {
+                    path.push(DataStep::MapEntry(no));
+                    path.push(DataStep::MapKey(*key));
+                    let pos_from = pos!(buf);
+                    if !key.verify_with(ts, buf, path, visitor) {
                         return false;
                     }
                     // Ensure lexicographic key uniqueness and sort order
-                    let pos_to = pos!();
+                    let pos_to = pos!(buf);
                     let k = read!(pos_from, pos_to);
                     if let Some(last) = set.iter().last() {
                         if k <= *last {
+                            visitor.fail(path, pos_from);
                             return false;
                         }
                     }
+                    visitor.map_key(path, pos_from, key, &k);
                     if !set.insert(k) {
+                        visitor.fail(path, pos_from);
                         return false;
                     }
+                    path.pop();
 
-                    if !val.verify(ts, buf) {
+                    if !val.verify_with(ts, buf, path, visitor) {
                         return false;
                     }
+                    path.pop();
                 }
                 true
             }
@@ -169,51 +334,163 @@ where T: Clone + Ord + Eq + Hash + Debug + Verify
 
 impl Verify for KeyType {
     fn verify(&self, ts: &TypeSystem, buf: &mut (impl Read + Seek)) -> bool {
+        self.verify_with(ts, buf, &mut DataPath::new(), &mut ())
+    }
+
+    fn verify_with(
+        &self,
+        ts: &TypeSystem,
+        buf: &mut (impl Read + Seek),
+        path: &mut DataPath,
+        visitor: &mut impl Visitor,
+    ) -> bool {
         match self {
-            KeyType::Primitive(ty) => ty.verify(ts, buf),
-            KeyType::Array(len, ty) => TypeConstr::Array(*len, *ty).verify(ts, buf),
-            KeyType::List(ty) => TypeConstr::List(*ty).verify(ts, buf),
+            KeyType::Primitive(ty) => ty.verify_with(ts, buf, path, visitor),
+            KeyType::Array(len, ty) => {
+                TypeConstr::Array(*len, *ty).verify_with(ts, buf, path, visitor)
+            }
+            KeyType::List(ty) => TypeConstr::List(*ty).verify_with(ts, buf, path, visitor),
         }
     }
 }
 
 impl Verify for PrimitiveType {
-    fn verify(&self, _: &TypeSystem, mut buf: &mut (impl Read + Seek)) -> bool {
-        let len = match self {
-            PrimitiveType::U8 => 1,
-            PrimitiveType::U16 => 2,
-            PrimitiveType::U32 => 4,
-            PrimitiveType::U64 => 8,
-            PrimitiveType::U128 => 16,
-            PrimitiveType::U256 => 32,
-            PrimitiveType::U512 => 64,
-            PrimitiveType::U1024 => 128,
-            PrimitiveType::I8 => 1,
-            PrimitiveType::I16 => 2,
-            PrimitiveType::I32 => 4,
-            PrimitiveType::I64 => 8,
-            PrimitiveType::I128 => 16,
-            PrimitiveType::I256 => 32,
-            PrimitiveType::I512 => 64,
-            PrimitiveType::I1024 => 128,
-            PrimitiveType::F16b => 2,
-            PrimitiveType::F16 => 2,
-            PrimitiveType::F32 => 4,
-            PrimitiveType::F64 => 8,
-            PrimitiveType::F80 => 10,
-            PrimitiveType::F128 => 16,
-            PrimitiveType::F256 => 32,
-            PrimitiveType::F512 => 64,
-            PrimitiveType::AsciiChar | PrimitiveType::UnicodeChar => {
-                match u16::strict_decode(&mut buf) {
-                    Err(_) => return false,
-                    Ok(len) => len,
+    fn verify(&self, ts: &TypeSystem, buf: &mut (impl Read + Seek)) -> bool {
+        self.verify_with(ts, buf, &mut DataPath::new(), &mut ())
+    }
+
+    fn verify_with(
+        &self,
+        _: &TypeSystem,
+        mut buf: &mut (impl Read + Seek),
+        path: &mut DataPath,
+        visitor: &mut impl Visitor,
+    ) -> bool {
+        let offset = pos!(buf);
+        // The largest primitive (1024-bit integer) takes 128 bytes, so only
+        // character data require allocation
+        let mut fixed = [0u8; 128];
+        let mut chars = vec![];
+        let data = match self.fixed_size() {
+            Some(len) => &mut fixed[..len as usize],
+            None => match u16::strict_decode(&mut buf) {
+                Err(_) => fail!(visitor, path, buf),
+                Ok(len) => {
+                    chars.resize(len as usize, 0u8);
+                    &mut chars[..]
                 }
-            }
+            },
         };
-        match buf.seek(SeekFrom::Current(len as i64)) {
-            Err(_) => false,
-            Ok(_) => true,
+        if buf.read_exact(data).is_err() {
+            // Reporting failure at the start of the value, since the position after
+            // a failed read is unspecified
+            visitor.fail(path, offset);
+            return false;
         }
+        if check_chars(*self, data).is_err() {
+            visitor.fail(path, offset);
+            return false;
+        }
+        visitor.primitive(path, offset, *self, data);
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::schema::test::{tn, transaction, type_system};
+    use crate::{strict_vec, type_system};
+
+    #[derive(Default)]
+    struct Log(Vec<String>);
+
+    impl Visitor for Log {
+        fn enter_struct(&mut self, path: &DataPath, offset: u64, name: &TypeName) {
+            self.0.push(format!("{offset} {path} enter {name}"));
+        }
+        fn optional_tag(&mut self, path: &DataPath, offset: u64, tag: u8) {
+            self.0.push(format!("{offset} {path} tag {tag}"));
+        }
+        fn list_len(&mut self, path: &DataPath, offset: u64, len: u16) {
+            self.0.push(format!("{offset} {path} len {len}"));
+        }
+        fn map_key(&mut self, path: &DataPath, offset: u64, _: &KeyType, data: &[u8]) {
+            self.0.push(format!("{offset} {path} key {data:02x?}"));
+        }
+        fn primitive(&mut self, path: &DataPath, offset: u64, ty: PrimitiveType, data: &[u8]) {
+            self.0.push(format!("{offset} {path} {ty} {data:02x?}"));
+        }
+        fn leave(&mut self, path: &DataPath, offset: u64) {
+            self.0.push(format!("{offset} {path} leave"));
+        }
+        fn fail(&mut self, path: &DataPath, offset: u64) {
+            self.0.push(format!("{offset} {path} fail"));
+        }
+    }
+
+    fn notes() -> TypeSystem {
+        type_system![
+            "Note" :: {
+                StructField { ty: TypeRef::ascii_string(), optional: true },
+                StructField::typed_map(KeyType::unicode_string(), "UnicodeString"),
+            },
+            "UnicodeString" :: { StructField::unicode_string() }
+        ]
+    }
+
+    #[test]
+    fn visit_note() {
+        let ts = notes();
+        let data = [1, 1, 0, 1, 0, b'x', 1, 0, 1, 0, 1, 0, b'k', 0, 0];
+        let mut log = Log::default();
+        let mut buf = Cursor::new(data);
+        assert!(tn("Note").verify_with(&ts, &mut buf, &mut DataPath::new(), &mut log));
+        assert_eq!(log.0, vec![
+            "0 . enter Note",
+            "0 .0 tag 1",
+            "1 .0 len 1",
+            "3 .0[0] AsciiChar [78]",
+            "6 .1 len 1",
+            "8 .1{0}<UnicodeChar[]> len 1",
+            "10 .1{0}<UnicodeChar[]>[0] UnicodeChar [6b]",
+            "8 .1{0}<UnicodeChar[]> key [01, 00, 01, 00, 6b]",
+            "13 .1{0} enter UnicodeString",
+            "13 .1{0}.0 len 0",
+            "15 .1{0} leave",
+            "15 . leave",
+        ]);
+    }
+
+    #[test]
+    fn verify_chars() {
+        let ts = notes();
+        let verify = |data: &[u8]| tn("Note").verify(&ts, &mut Cursor::new(data));
+        assert!(verify(&[1, 1, 0, 1, 0, b'x', 1, 0, 1, 0, 2, 0, 0xC3, 0xA9, 0, 0]));
+        // Non-ASCII byte in ASCII character data
+        assert!(!verify(&[1, 1, 0, 1, 0, 0x80, 0, 0]));
+        // Truncated two-byte UTF-8 sequence in Unicode character data
+        assert!(!verify(&[0, 1, 0, 1, 0, 1, 0, 0xC3, 0, 0]));
+    }
+
+    #[test]
+    fn visit_failure() {
+        let ts = type_system();
+        let mut data = transaction();
+        // Truncating the last byte of the transaction lock time
+        data.pop();
+        let len = data.len() as u64;
+        let mut log = Log::default();
+        let mut buf = Cursor::new(data);
+        assert!(!tn("Transaction").verify_with(&ts, &mut buf, &mut DataPath::new(), &mut log));
+        assert_eq!(log.0.last().unwrap(), &format!("{} .3 fail", len - 3));
+
+        let ts = notes();
+        let mut log = Log::default();
+        let mut buf = Cursor::new([2u8]);
+        assert!(!tn("Note").verify_with(&ts, &mut buf, &mut DataPath::new(), &mut log));
+        assert_eq!(log.0, vec!["0 . enter Note", "0 .0 tag 2", "0 .0 fail"]);
     }
 }
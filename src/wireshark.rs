@@ -0,0 +1,522 @@
+// Strict encoding schema library, implementing validation and parsing of strict
+// encoded data against the schema.
+//
+// Written in 2022 by
+//     Dr. Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2022 by LNP/BP Standards Association, Switzerland.
+//
+// You should have received a copy of the MIT License along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::collections::BTreeMap;
+use std::fmt::{Debug, Write};
+use std::hash::Hash;
+
+use crate::codegen::{snake_case, Lines};
+use crate::{
+    DataSize, Error, PrimitiveType, StructField, StructType, TypeConstr, TypeName, TypeRef,
+    TypeSystem,
+};
+
+/// Helper functions used by the generated dissector code.
+const HELPERS: &str = r#"local Malformed = {}
+
+local function need(buf, tree, offset, len)
+  if offset + len > buf:len() then
+    tree:add_proto_expert_info(experts.truncated)
+    error(Malformed)
+  end
+end
+
+local function read_len(buf, tree, offset, min)
+  need(buf, tree, offset, 2)
+  local len = buf(offset, 2):le_uint()
+  local item = tree:add_le(fields.len, buf(offset, 2))
+  if len * min > buf:len() - offset - 2 then
+    item:add_proto_expert_info(experts.length)
+    error(Malformed)
+  end
+  return len, offset + 2
+end
+
+local function read_tag(buf, tree, offset)
+  need(buf, tree, offset, 1)
+  local tag = buf(offset, 1):uint()
+  local item = tree:add(fields.tag, buf(offset, 1))
+  if tag > 1 then
+    item:add_proto_expert_info(experts.tag)
+    error(Malformed)
+  end
+  return tag == 1, offset + 1
+end
+
+local function read_fixed(buf, tree, offset, field, size)
+  need(buf, tree, offset, size)
+  tree:add_le(field, buf(offset, size))
+  return offset + size
+end
+
+local function read_chars(buf, tree, offset, field, encoding)
+  need(buf, tree, offset, 2)
+  local len = buf(offset, 2):le_uint()
+  need(buf, tree, offset + 2, len)
+  tree:add(field, buf(offset, len + 2), buf(offset + 2, len):string(encoding))
+  return offset + 2 + len
+end
+"#;
+
+/// Parameters of Wireshark dissector generation.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct DissectorConfig {
+    /// Protocol abbreviation, used as a prefix of the display filter fields.
+    pub proto: String,
+
+    /// Human-readable protocol description.
+    pub description: String,
+
+    /// Mapping from message type ids to the types of the message data.
+    pub messages: BTreeMap<u16, TypeName>,
+
+    /// TCP port at which the dissector is registered.
+    pub tcp_port: Option<u16>,
+}
+
+impl DissectorConfig {
+    /// Constructs configuration without messages and TCP port.
+    pub fn new(proto: impl ToString, description: impl ToString) -> Self {
+        DissectorConfig {
+            proto: proto.to_string(),
+            description: description.to_string(),
+            messages: empty!(),
+            tcp_port: None,
+        }
+    }
+}
+
+impl TypeSystem {
+    /// Generates Wireshark dissector in Lua for the messages listed in
+    /// `config`.
+    ///
+    /// Each message is expected to start with a little-endian 16-bit message
+    /// type id, followed by the strict encoding of the message data. Primitive
+    /// data of each structure field are exposed as protocol fields named
+    /// `<proto>.<type>.f<no>`, where `<type>` is the snake-case type name;
+    /// lists, sets, arrays, maps and nested structures are put into subtrees.
+    /// Length prefixes exceeding the remaining data, invalid optional tags and
+    /// truncated data are reported as malformed packet expert info.
+    ///
+    /// Data of an alias are dissected as the data of the aliased type, without
+    /// a subtree of their own; its primitive data are exposed as
+    /// `<proto>.<type>.value` protocol fields.
+    pub fn to_wireshark(&self, config: &DissectorConfig) -> Result<String, Error> {
+        let types = self.reachable(config.messages.values())?;
+        let mut lua = Lua {
+            ts: self,
+            proto: &config.proto,
+            fields: vec![],
+            code: s!(""),
+        };
+        for name in &types {
+            let ty = self.get(name).expect("reachable types are defined");
+            match self.alias(name) {
+                Some(aliased) => lua.alias(name, aliased),
+                None => lua.structure(name, ty),
+            }
+        }
+
+        let proto = &config.proto;
+        let mut s = String::new();
+        writeln!(s, "-- Wireshark dissector for {}.", config.description).ok();
+        writeln!(s, "--").ok();
+        writeln!(s, "-- Generated from strict encoding schema. Each message is a little-endian")
+            .ok();
+        writeln!(s, "-- 16-bit message type followed by the strict encoding of the message data.")
+            .ok();
+        writeln!(s).ok();
+        writeln!(s, "local proto = Proto({:?}, {:?})", proto, config.description).ok();
+        writeln!(s).ok();
+        writeln!(s, "local messages = {{").ok();
+        for (id, name) in &config.messages {
+            writeln!(s, "  [0x{:04x}] = {:?},", id, name.as_str()).ok();
+        }
+        writeln!(s, "}}").ok();
+        writeln!(s).ok();
+        writeln!(s, "local fields = {{").ok();
+        writeln!(
+            s,
+            "  msg_type = ProtoField.uint16(\"{}.msg_type\", \"Message type\", base.HEX, \
+             messages),",
+            proto
+        )
+        .ok();
+        writeln!(s, "  len = ProtoField.uint16(\"{}.len\", \"Length\"),", proto).ok();
+        writeln!(s, "  tag = ProtoField.uint8(\"{}.tag\", \"Optional tag\"),", proto).ok();
+        for field in &lua.fields {
+            writeln!(s, "  {}", field).ok();
+        }
+        writeln!(s, "}}").ok();
+        writeln!(s, "proto.fields = fields").ok();
+        writeln!(s).ok();
+        writeln!(s, "local experts = {{").ok();
+        for (id, text, group, severity) in [
+            ("truncated", "Data are truncated", "MALFORMED", "ERROR"),
+            ("length", "Length prefix exceeds the remaining data", "MALFORMED", "ERROR"),
+            ("tag", "Invalid optional tag", "MALFORMED", "ERROR"),
+            ("unknown", "Unknown message type", "UNDECODED", "WARN"),
+            ("trailing", "Message data are not consumed", "MALFORMED", "WARN"),
+        ] {
+            writeln!(
+                s,
+                "  {id} = ProtoExpert.new(\"{proto}.{id}\", \"{text}\", expert.group.{group}, \
+                 expert.severity.{severity}),",
+                id = id,
+                proto = proto,
+                text = text,
+                group = group,
+                severity = severity
+            )
+            .ok();
+        }
+        writeln!(s, "}}").ok();
+        writeln!(s, "proto.experts = experts").ok();
+        writeln!(s).ok();
+        s.push_str(HELPERS);
+        writeln!(s).ok();
+        writeln!(s, "local dissect = {{}}").ok();
+        s.push_str(&lua.code);
+        writeln!(s).ok();
+        s.push_str(
+            r#"function proto.dissector(buf, pinfo, root)
+  pinfo.cols.protocol = proto.name
+  local tree = root:add(proto, buf())
+  if buf:len() < 2 then
+    tree:add_proto_expert_info(experts.truncated)
+    return
+  end
+  tree:add_le(fields.msg_type, buf(0, 2))
+  local name = messages[buf(0, 2):le_uint()]
+  if name == nil then
+    tree:add_proto_expert_info(experts.unknown)
+    return
+  end
+  pinfo.cols.info = name
+  local ok, offset = pcall(dissect[name], buf, tree, 2, name)
+  if not ok then
+    if offset ~= Malformed then
+      error(offset, 0)
+    end
+  elseif offset < buf:len() then
+    tree:add_proto_expert_info(experts.trailing)
+  end
+end
+"#,
+        );
+        if let Some(port) = config.tcp_port {
+            writeln!(s).ok();
+            writeln!(s, "DissectorTable.get(\"tcp.port\"):add({}, proto)", port).ok();
+        }
+        Ok(s)
+    }
+}
+
+/// Lua code generator.
+struct Lua<'a> {
+    ts: &'a TypeSystem,
+    proto: &'a str,
+    fields: Vec<String>,
+    code: String,
+}
+
+impl<'a> Lua<'a> {
+    fn declare(&mut self, key: &str, ty: PrimitiveType, label: &str) {
+        let kind = match (ty, ty.fixed_size()) {
+            (PrimitiveType::F32, _) => s!("float"),
+            (PrimitiveType::F64, _) => s!("double"),
+            (_, None) => s!("string"),
+            (ty, Some(size)) if size <= 8 && ty.is_unsigned() => format!("uint{}", size * 8),
+            (ty, Some(size)) if size <= 8 && ty.is_signed() => format!("int{}", size * 8),
+            _ => s!("bytes"),
+        };
+        self.fields.push(format!(
+            "[{key:?}] = ProtoField.{kind}({abbr:?}, {label:?}),",
+            key = key,
+            kind = kind,
+            abbr = format!("{}.{}", self.proto, key),
+            label = label
+        ));
+    }
+
+    /// Declares protocol fields for the primitives and map keys of the type
+    /// reference; items of the nested constructors use `.item` key suffix.
+    fn declare_ref(&mut self, key: &str, label: &str, ty: &TypeRef) {
+        if let TypeRef::InPlace(constr) = ty {
+            self.declare(key, *constr.inner(), label);
+        }
+        if let TypeRef::InPlace(TypeConstr::Map(k, _))
+        | TypeRef::NameRef(TypeConstr::Map(k, _))
+        | TypeRef::Nested(TypeConstr::Map(k, _)) = ty
+        {
+            self.declare(&format!("{}.key", key), k.primitive(), &format!("{} key", label));
+        }
+        if let TypeRef::Nested(constr) = ty {
+            self.declare_ref(&format!("{}.item", key), &format!("{} item", label), constr.inner());
+        }
+    }
+
+    fn alias(&mut self, name: &TypeName, ty: &TypeRef) {
+        let key = format!("{}.value", snake_case(name));
+        self.declare_ref(&key, name.as_str(), ty);
+        self.code.line(0, "");
+        let head = format!("dissect[{:?}] = function(buf, tree, offset, label)", name.as_str());
+        self.code.line(0, head);
+        self.code.line(2, format!("-- {}", ty));
+        self.type_ref(2, &key, "label", ty);
+        self.code.line(2, "return offset");
+        self.code.line(0, "end");
+    }
+
+    fn structure(&mut self, name: &TypeName, ty: &StructType) {
+        let snake = snake_case(name);
+        self.code.line(0, "");
+        let head = format!("dissect[{:?}] = function(buf, tree, offset, label)", name.as_str());
+        self.code.line(0, head);
+        self.code.line(2, "local start = offset");
+        self.code.line(2, "local tree = tree:add(buf(offset, 0), label)");
+        for (no, field) in ty.into_iter().enumerate() {
+            let key = format!("{}.f{}", snake, no);
+            let label = format!("{}.{}", name, no);
+            self.declare_ref(&key, &label, &field.ty);
+            self.code.line(2, format!("-- {}: {}", no, field));
+            self.field(2, no, &key, field);
+        }
+        self.code.line(2, "tree:set_len(offset - start)");
+        self.code.line(2, "return offset");
+        self.code.line(0, "end");
+    }
+
+    fn field(&mut self, mut indent: usize, no: usize, key: &str, field: &StructField) {
+        if field.optional {
+            self.code.line(indent, "do");
+            self.code.line(indent + 2, "local present");
+            self.code.line(indent + 2, "present, offset = read_tag(buf, tree, offset)");
+            self.code.line(indent + 2, "if present then");
+            indent += 4;
+        }
+        let label = format!("{:?}", format!("{}: {}", no, field.ty));
+        self.type_ref(indent, key, &label, &field.ty);
+        if field.optional {
+            self.code.line(indent - 2, "end");
+            self.code.line(indent - 4, "end");
+        }
+    }
+
+    fn type_ref(&mut self, indent: usize, key: &str, label: &str, ty: &TypeRef) {
+        match ty {
+            TypeRef::InPlace(constr) => {
+                let min = DataSize::from(*constr.inner()).min();
+                self.constr(indent, key, label, constr, min, &|lua, indent, ty, _| {
+                    lua.primitive(indent, key, *ty)
+                })
+            }
+            TypeRef::NameRef(constr) => {
+                let min = self.ts.size_of(constr.type_name()).min();
+                self.constr(indent, key, label, constr, min, &|lua, indent, name, label| {
+                    let name = name.as_str();
+                    let call = format!("offset = dissect[{:?}](buf, tree, offset, {})", name, label);
+                    lua.code.line(indent, call)
+                })
+            }
+            TypeRef::Nested(constr) => {
+                let item = StructField {
+                    ty: constr.inner().as_ref().clone(),
+                    optional: false,
+                };
+                let min = self.ts.field_size(&item).min();
+                let item_key = format!("{}.item", key);
+                self.constr(indent, key, label, constr, min, &|lua, indent, ty, label| {
+                    lua.type_ref(indent, &item_key, label, ty)
+                })
+            }
+        }
+    }
+
+    fn primitive(&mut self, indent: usize, key: &str, ty: PrimitiveType) {
+        let line = match (ty, ty.fixed_size()) {
+            (_, Some(size)) => {
+                format!("offset = read_fixed(buf, tree, offset, fields[{:?}], {})", key, size)
+            }
+            (PrimitiveType::AsciiChar, _) => {
+                format!("offset = read_chars(buf, tree, offset, fields[{:?}], ENC_ASCII)", key)
+            }
+            _ => format!("offset = read_chars(buf, tree, offset, fields[{:?}], ENC_UTF_8)", key),
+        };
+        self.code.line(indent, line);
+    }
+
+    fn constr<T>(
+        &mut self,
+        indent: usize,
+        key: &str,
+        label: &str,
+        ty: &TypeConstr<T>,
+        min: usize,
+        item: &dyn Fn(&mut Self, usize, &T, &str),
+    ) where
+        T: Clone + Ord + Eq + Hash + Debug,
+    {
+        let ty = match ty {
+            TypeConstr::Plain(ty) => return item(self, indent, ty, label),
+            ty => ty,
+        };
+        self.code.line(indent, "do");
+        self.code.line(indent + 2, "local start = offset");
+        self.code.line(indent + 2, format!("local tree = tree:add(buf(offset, 0), {})", label));
+        let count = match ty {
+            TypeConstr::Plain(_) => unreachable!(),
+            TypeConstr::Array(count, _) => count.to_string(),
+            TypeConstr::List(_) | TypeConstr::Set(_) => {
+                self.code.line(indent + 2, "local len");
+                self.code.line(
+                    indent + 2,
+                    format!("len, offset = read_len(buf, tree, offset, {})", min),
+                );
+                s!("len")
+            }
+            TypeConstr::Map(k, _) => {
+                let key_min = DataSize::from(*k).min();
+                self.code.line(indent + 2, "local len");
+                self.code.line(
+                    indent + 2,
+                    format!("len, offset = read_len(buf, tree, offset, {})", key_min + min),
+                );
+                s!("len")
+            }
+        };
+        self.code.line(indent + 2, format!("for i = 1, {} do", count));
+        match ty {
+            TypeConstr::Map(k, ty) => {
+                self.code.line(indent + 4, "local start = offset");
+                self.code.line(
+                    indent + 4,
+                    "local tree = tree:add(buf(offset, 0), \"{\" .. (i - 1) .. \"}\")",
+                );
+                let key = format!("{}.key", key);
+                let key_constr = TypeConstr::from(*k);
+                let key_min = DataSize::from(k.primitive()).min();
+                self.constr(
+                    indent + 4,
+                    &key,
+                    "\"key\"",
+                    &key_constr,
+                    key_min,
+                    &|lua, indent, ty, _| lua.primitive(indent, &key, *ty),
+                );
+                item(self, indent + 4, ty, "\"value\"");
+                self.code.line(indent + 4, "tree:set_len(offset - start)");
+            }
+            TypeConstr::Array(_, ty) | TypeConstr::List(ty) | TypeConstr::Set(ty) => {
+                item(self, indent + 4, ty, "\"[\" .. (i - 1) .. \"]\"")
+            }
+            TypeConstr::Plain(_) => unreachable!(),
+        }
+        self.code.line(indent + 2, "end");
+        self.code.line(indent + 2, "tree:set_len(offset - start)");
+        self.code.line(indent, "end");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::schema::test::{golden, tn, type_system};
+    use crate::{strict_vec, type_system};
+
+    #[test]
+    fn dissector() {
+        let mut config = DissectorConfig::new("lnp", "LNP test messages");
+        config.messages.insert(1, tn("Transaction"));
+        config.messages.insert(0x20, tn("Meta"));
+        config.tcp_port = Some(9735);
+        golden("wireshark.lua", &type_system().to_wireshark(&config).unwrap());
+
+        config.messages.insert(2, tn("Unknown"));
+        assert!(type_system().to_wireshark(&config).is_err());
+    }
+
+    #[test]
+    fn optional() {
+        let ts = type_system![
+            "Note" :: {
+                StructField { ty: TypeRef::InPlace(TypeConstr::Plain(PrimitiveType::U256)), optional: true },
+                StructField::with("Note"),
+            }
+        ];
+        let mut config = DissectorConfig::new("note", "Notes");
+        config.messages.insert(0, tn("Note"));
+        let lua = ts.to_wireshark(&config).unwrap();
+        assert!(lua.contains("[\"note.f0\"] = ProtoField.bytes(\"note.note.f0\", \"Note.0\"),"));
+        assert!(lua.contains(
+            "  do
+    local present
+    present, offset = read_tag(buf, tree, offset)
+    if present then
+      offset = read_fixed(buf, tree, offset, fields[\"note.f0\"], 32)
+    end
+  end
+"
+        ));
+        assert!(!lua.contains("tcp.port"));
+        assert_eq!(ts.size_of(&tn("Note")).min(), 1);
+    }
+
+    #[test]
+    fn aliases() {
+        let mut ts = type_system![
+            "Input" :: { StructField::with("Txid"), StructField::with("Height") }
+        ];
+        ts.push_alias(tn("Txid"), TypeRef::InPlace(TypeConstr::Array(32, PrimitiveType::U8)))
+            .unwrap();
+        ts.push_alias(tn("Height"), TypeRef::InPlace(TypeConstr::Plain(PrimitiveType::U32)))
+            .unwrap();
+        let mut config = DissectorConfig::new("tx", "Transactions");
+        config.messages.insert(0, tn("Input"));
+        let lua = ts.to_wireshark(&config).unwrap();
+        assert!(lua.contains("[\"txid.value\"] = ProtoField.uint8(\"tx.txid.value\", \"Txid\"),"));
+        assert!(lua.contains(
+            "dissect[\"Height\"] = function(buf, tree, offset, label)
+  -- U32
+  offset = read_fixed(buf, tree, offset, fields[\"height.value\"], 4)
+  return offset
+end
+"
+        ));
+        assert!(lua.contains(
+            "dissect[\"Txid\"] = function(buf, tree, offset, label)
+  -- U8*32
+  do
+    local start = offset
+    local tree = tree:add(buf(offset, 0), label)
+    for i = 1, 32 do
+"
+        ));
+        assert!(lua.contains("  offset = dissect[\"Txid\"](buf, tree, offset, \"0: Txid\")\n"));
+    }
+
+    #[test]
+    fn quoted_names() {
+        let mut ts = type_system![
+            "Msg" :: { StructField::with("Say\"Hi\\") }
+        ];
+        ts.push_alias(tn("Say\"Hi\\"), TypeRef::InPlace(TypeConstr::Plain(PrimitiveType::U8)))
+            .unwrap();
+        let mut config = DissectorConfig::new("msg", "Messages");
+        config.messages.insert(0, tn("Msg"));
+        let lua = ts.to_wireshark(&config).unwrap();
+        assert!(lua.contains(
+            r#"["say\"hi\\.value"] = ProtoField.uint8("msg.say\"hi\\.value", "Say\"Hi\\"),"#
+        ));
+        assert!(lua.contains(r#"dissect["Say\"Hi\\"] = function(buf, tree, offset, label)"#));
+        assert!(lua.contains(r#"fields["say\"hi\\.value"], 1)"#));
+    }
+}
@@ -0,0 +1,398 @@
+// Strict encoding schema library, implementing validation and parsing of strict
+// encoded data against the schema.
+//
+// Written in 2022 by
+//     Dr. Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2022 by LNP/BP Standards Association, Switzerland.
+//
+// You should have received a copy of the MIT License along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::io::Write;
+
+use crate::cursor::{SchemaCursor, Shape, SortMode};
+use crate::value::check_chars;
+use crate::{
+    CursorError, DataError, DataPath, PrimitiveType, PrimitiveValue, StructType, TypeName,
+    TypeSystem, Value,
+};
+
+/// Streaming writer of strict-encoded data, which checks that the data items
+/// are written in the order and with the types defined by the schema.
+///
+/// The data are written to the underlying writer as soon as possible; the
+/// only data kept in memory are elements of sets and entries of maps, which
+/// are sorted and written out once the set or map is left.
+#[derive(Debug)]
+pub struct SchemaWriter<'ts, W: Write> {
+    cursor: SchemaCursor<'ts>,
+    writer: W,
+}
+
+macro_rules! write_num {
+    ($name:ident, $ty:ty, $prim:ident) => {
+        pub fn $name(&mut self, val: $ty) -> Result<(), CursorError> {
+            self.write_typed(PrimitiveType::$prim, stringify!($name), &val.to_le_bytes())
+        }
+    };
+}
+
+impl<'ts, W: Write> SchemaWriter<'ts, W> {
+    /// Constructs writer for the data encoding an instance of type `root`.
+    pub fn new(ts: &'ts TypeSystem, root: &'ts TypeName, writer: W) -> Result<Self, CursorError> {
+        Ok(SchemaWriter {
+            cursor: SchemaCursor::new(ts, root, SortMode::Collect)?,
+            writer,
+        })
+    }
+
+    /// Returns path to the current data item.
+    #[inline]
+    pub fn path(&self) -> &DataPath { self.cursor.path() }
+
+    /// Returns number of data items still to be written into the current
+    /// structure or collection. Each map entry counts as two data items: a key
+    /// and a value.
+    #[inline]
+    pub fn remaining(&self) -> u32 { self.cursor.remaining() }
+
+    /// Detects whether the root data item was written completely.
+    #[inline]
+    pub fn is_finished(&self) -> bool { self.cursor.is_finished() }
+
+    /// Returns the underlying writer if the root data item was written
+    /// completely.
+    pub fn finish(mut self) -> Result<W, CursorError> {
+        if !self.is_finished() {
+            return Err(self.cursor.mismatch("finish"));
+        }
+        self.writer.flush().map_err(|err| self.cursor.data_error(err))?;
+        Ok(self.writer)
+    }
+
+    fn emit(&mut self, data: &[u8]) -> Result<(), CursorError> {
+        if self.cursor.record(data) {
+            return Ok(());
+        }
+        self.writer.write_all(data).map_err(|err| self.cursor.data_error(err))
+    }
+
+    /// Enters the structure which is the current data item.
+    pub fn enter_struct(&mut self) -> Result<&'ts StructType, CursorError> {
+        match self.cursor.begin("enter_struct", |shape| matches!(shape, Shape::Struct(_)))? {
+            Shape::Struct(name) => self.cursor.enter_struct(name),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Moves to the next field of the current structure, specifying whether
+    /// the field value is present. Only optional fields may be absent.
+    pub fn next_field(&mut self, present: bool) -> Result<(), CursorError> {
+        let field = match self.cursor.next_field("next_field")? {
+            None => return Err(self.cursor.mismatch("next_field")),
+            Some(field) => field,
+        };
+        match (field.optional, present) {
+            (false, true) => Ok(()),
+            (false, false) => Err(self.cursor.mismatch("skipping required field")),
+            (true, true) => self.emit(&[1]),
+            (true, false) => {
+                self.emit(&[0])?;
+                self.cursor.skip_field();
+                Ok(())
+            }
+        }
+    }
+
+    /// Enters the array, list or set which is the current data item, which
+    /// will contain `len` elements. For arrays `len` must match the array
+    /// size.
+    pub fn enter_list(&mut self, len: u16) -> Result<(), CursorError> {
+        let shape = self.cursor.begin("enter_list", |shape| match shape {
+            Shape::Array(size, _) => *size == len,
+            Shape::List(_) | Shape::Set(_) => true,
+            _ => false,
+        })?;
+        if !matches!(shape, Shape::Array(..)) {
+            self.emit(&len.to_le_bytes())?;
+        }
+        self.cursor.enter_collection(shape, len);
+        Ok(())
+    }
+
+    /// Enters the map which is the current data item, which will contain
+    /// `len` entries. Each entry is written as a key data item followed by a
+    /// value data item; the entries may come in any order.
+    pub fn enter_map(&mut self, len: u16) -> Result<(), CursorError> {
+        let shape = self.cursor.begin("enter_map", |shape| matches!(shape, Shape::Map(..)))?;
+        self.emit(&len.to_le_bytes())?;
+        self.cursor.enter_collection(shape, len);
+        Ok(())
+    }
+
+    /// Leaves the current structure or collection, which must have all of its
+    /// fields or elements written. Set elements and map entries are sorted
+    /// and written out at this point.
+    pub fn leave(&mut self) -> Result<(), CursorError> {
+        let frame = self.cursor.pop("leave")?;
+        let is_map = frame.is_map();
+        if let Some(mut sorting) = frame.sorting {
+            sorting.entries.sort();
+            let repeated = sorting.entries.windows(2).any(|pair| pair[0].0 == pair[1].0);
+            if repeated && is_map {
+                return Err(self.cursor.data_error(DataError::RepeatedMapKey));
+            } else if repeated {
+                return Err(self.cursor.data_error(DataError::RepeatedSetItem));
+            }
+            for (_, entry) in sorting.entries {
+                self.emit(&entry)?;
+            }
+        }
+        self.cursor.complete()
+    }
+
+    /// Writes the current data item, which must be of a primitive type.
+    pub fn write_primitive(&mut self, val: &PrimitiveValue) -> Result<(), CursorError> {
+        self.write_typed(val.ty(), "write_primitive", val.as_bytes())
+    }
+
+    fn write_typed(
+        &mut self,
+        ty: PrimitiveType,
+        op: &'static str,
+        data: &[u8],
+    ) -> Result<(), CursorError> {
+        self.cursor.begin(op, |shape| *shape == Shape::Primitive(ty))?;
+        check_chars(ty, data).map_err(|err| self.cursor.data_error(err))?;
+        if ty.is_char() {
+            let len = u16::try_from(data.len())
+                .map_err(|_| self.cursor.data_error(DataError::CharsTooLong(data.len())))?;
+            self.emit(&len.to_le_bytes())?;
+        }
+        self.emit(data)?;
+        self.cursor.complete()
+    }
+
+    write_num!(write_u8, u8, U8);
+    write_num!(write_u16, u16, U16);
+    write_num!(write_u32, u32, U32);
+    write_num!(write_u64, u64, U64);
+    write_num!(write_u128, u128, U128);
+    write_num!(write_i8, i8, I8);
+    write_num!(write_i16, i16, I16);
+    write_num!(write_i32, i32, I32);
+    write_num!(write_i64, i64, I64);
+    write_num!(write_i128, i128, I128);
+    write_num!(write_f32, f32, F32);
+    write_num!(write_f64, f64, F64);
+
+    /// Writes the current data item, taking its structure from the dynamic
+    /// value.
+    pub fn write_value(&mut self, val: &Value) -> Result<(), CursorError> {
+        match val {
+            Value::Primitive(val) => return self.write_primitive(val),
+            Value::Struct(fields) => {
+                self.enter_struct()?;
+                for field in fields {
+                    self.next_field(field.is_some())?;
+                    if let Some(val) = field {
+                        self.write_value(val)?;
+                    }
+                }
+            }
+            Value::List(items) => {
+                self.enter_list(self.collection_len(items.len())?)?;
+                for item in items {
+                    self.write_value(item)?;
+                }
+            }
+            Value::Map(entries) => {
+                self.enter_map(self.collection_len(entries.len())?)?;
+                for (key, val) in entries {
+                    self.write_value(key)?;
+                    self.write_value(val)?;
+                }
+            }
+        }
+        self.leave()
+    }
+
+    fn collection_len(&self, len: usize) -> Result<u16, CursorError> {
+        u16::try_from(len).map_err(|_| self.cursor.data_error(DataError::TooManyItems(len)))
+    }
+
+    /// Writes ASCII character data.
+    pub fn write_ascii(&mut self, s: &str) -> Result<(), CursorError> {
+        self.write_typed(PrimitiveType::AsciiChar, "write_ascii", s.as_bytes())
+    }
+
+    /// Writes Unicode character data.
+    pub fn write_unicode(&mut self, s: &str) -> Result<(), CursorError> {
+        self.write_typed(PrimitiveType::UnicodeChar, "write_unicode", s.as_bytes())
+    }
+}
+
+impl TypeSystem {
+    /// Encodes the dynamic value as an instance of type `root`.
+    pub fn encode(&self, root: &TypeName, val: &Value) -> Result<Vec<u8>, CursorError> {
+        let mut writer = SchemaWriter::new(self, root, vec![])?;
+        writer.write_value(val)?;
+        writer.finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::schema::test::{tn, transaction, type_system};
+    use crate::Verify;
+
+    #[test]
+    fn write_transaction() {
+        let ts = type_system();
+        let root = tn("Transaction");
+        let mut writer = SchemaWriter::new(&ts, &root, vec![]).unwrap();
+
+        let bytes = |writer: &mut SchemaWriter<_>, data: &[u8]| {
+            writer.enter_struct().unwrap();
+            writer.next_field(true).unwrap();
+            writer.enter_list(data.len() as u16).unwrap();
+            data.iter().for_each(|byte| writer.write_u8(*byte).unwrap());
+            writer.leave().unwrap();
+            writer.leave().unwrap();
+        };
+        let input = |writer: &mut SchemaWriter<_>, byte: u8, vout: u16, script: &[u8]| {
+            writer.enter_struct().unwrap();
+            writer.next_field(true).unwrap();
+            writer.enter_struct().unwrap();
+            writer.next_field(true).unwrap();
+            writer.enter_struct().unwrap();
+            writer.next_field(true).unwrap();
+            writer.enter_list(32).unwrap();
+            (0..32).for_each(|_| writer.write_u8(byte).unwrap());
+            writer.leave().unwrap();
+            writer.leave().unwrap();
+            writer.next_field(true).unwrap();
+            writer.write_u16(vout).unwrap();
+            writer.leave().unwrap();
+            writer.next_field(true).unwrap();
+            bytes(writer, script);
+            writer.next_field(true).unwrap();
+            writer.enter_struct().unwrap();
+            writer.next_field(true).unwrap();
+            if byte == 1 {
+                writer.enter_list(1).unwrap();
+                bytes(writer, &[0xFF]);
+            } else {
+                writer.enter_list(0).unwrap();
+            }
+            writer.leave().unwrap();
+            writer.leave().unwrap();
+            writer.leave().unwrap();
+        };
+        let output = |writer: &mut SchemaWriter<_>, value: u64, script: &[u8]| {
+            writer.enter_struct().unwrap();
+            writer.next_field(true).unwrap();
+            writer.write_u64(value).unwrap();
+            writer.next_field(true).unwrap();
+            bytes(writer, script);
+            writer.leave().unwrap();
+        };
+
+        writer.enter_struct().unwrap();
+        writer.next_field(true).unwrap();
+        writer.write_u32(1).unwrap();
+        writer.next_field(true).unwrap();
+        writer.enter_list(2).unwrap();
+        input(&mut writer, 1, 2, &[1, 2]);
+        input(&mut writer, 2, 0, &[]);
+        writer.leave().unwrap();
+        writer.next_field(true).unwrap();
+        writer.enter_list(2).unwrap();
+        output(&mut writer, 100_000, &[0x51]);
+        assert_eq!(writer.remaining(), 1);
+        output(&mut writer, 200_000, &[]);
+        writer.leave().unwrap();
+        writer.next_field(true).unwrap();
+        assert_eq!(
+            writer.write_u16(0).unwrap_err().to_string(),
+            "write_u16 is not allowed at .3, where the schema expects U32"
+        );
+        writer.write_u32(0).unwrap();
+        writer.leave().unwrap();
+
+        assert_eq!(writer.finish().unwrap(), transaction());
+    }
+
+    #[test]
+    fn write_value() {
+        let ts = type_system();
+        let root = tn("Transaction");
+        let value = ts.decode(&root, transaction()).unwrap();
+        assert_eq!(ts.encode(&root, &value).unwrap(), transaction());
+
+        let root = tn("Meta");
+        let value = ts.decode(&root, [0, 0, 0, 0]).unwrap();
+        assert_eq!(ts.encode(&root, &value).unwrap(), [0, 0, 0, 0]);
+        assert_eq!(
+            ts.encode(&tn("Witness"), &value).unwrap_err().to_string(),
+            "next_field is not allowed at ., where the schema expects end of structure"
+        );
+    }
+
+    #[test]
+    fn sorted_map() {
+        let ts = type_system();
+        let root = tn("Meta");
+        let mut writer = SchemaWriter::new(&ts, &root, vec![]).unwrap();
+        writer.enter_struct().unwrap();
+        writer.next_field(true).unwrap();
+        writer.enter_list(1).unwrap();
+        writer.write_ascii("a").unwrap();
+        writer.leave().unwrap();
+        writer.next_field(true).unwrap();
+        writer.enter_map(2).unwrap();
+        for key in ["k", "a"] {
+            writer.enter_list(1).unwrap();
+            writer.write_unicode(key).unwrap();
+            writer.leave().unwrap();
+            writer.enter_struct().unwrap();
+            writer.next_field(true).unwrap();
+            writer.enter_list(0).unwrap();
+            writer.leave().unwrap();
+            writer.leave().unwrap();
+        }
+        writer.leave().unwrap();
+        writer.leave().unwrap();
+        assert_eq!(
+            writer.leave().unwrap_err().to_string(),
+            "leave is not allowed at ., where the schema expects end of data"
+        );
+        let data = writer.finish().unwrap();
+        assert_eq!(data, vec![
+            1, 0, 1, 0, b'a', 2, 0, 1, 0, 1, 0, b'a', 0, 0, 1, 0, 1, 0, b'k', 0, 0
+        ]);
+        assert!(root.verify(&ts, &mut std::io::Cursor::new(data)));
+    }
+
+    #[test]
+    fn owed_elements() {
+        let ts = type_system();
+        let root = tn("Output");
+        let mut writer = SchemaWriter::new(&ts, &root, vec![]).unwrap();
+        writer.enter_struct().unwrap();
+        writer.next_field(true).unwrap();
+        writer.write_u64(1).unwrap();
+        writer.next_field(true).unwrap();
+        writer.enter_struct().unwrap();
+        writer.next_field(true).unwrap();
+        writer.enter_list(3).unwrap();
+        writer.write_u8(0).unwrap();
+        assert_eq!(writer.remaining(), 2);
+        assert_eq!(
+            writer.leave().unwrap_err().to_string(),
+            ".1.0 is left before all of its 3 items were processed (1 done)"
+        );
+    }
+}
@@ -0,0 +1,479 @@
+/* Generated from strict encoding schema; do not edit. */
+
+#ifndef TYPES_H
+#define TYPES_H
+
+#ifndef STRICT_RUNTIME_H
+#define STRICT_RUNTIME_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <string.h>
+
+#define STRICT_OK 0
+#define STRICT_ERR_TRUNCATED -1 /* unexpected end of data */
+#define STRICT_ERR_TAG -2       /* optional tag is neither 0 nor 1 */
+#define STRICT_ERR_ASCII -3     /* non-ASCII character */
+#define STRICT_ERR_UTF8 -4      /* invalid UTF-8 string */
+#define STRICT_ERR_ORDER -5     /* set items or map keys are not unique or not sorted */
+#define STRICT_ERR_TRAILING -6  /* data are not consumed */
+
+#define STRICT_TRY(expr)                  \
+    do {                                  \
+        int strict_err = (expr);          \
+        if (strict_err != STRICT_OK) {    \
+            return strict_err;            \
+        }                                 \
+    } while (0)
+
+/* Character data or encoding of a data item inside the decoded buffer */
+typedef struct {
+    const uint8_t *data;
+    size_t len;
+} strict_view_t;
+
+/* Encoded items of a list, set, map or array inside the decoded buffer */
+typedef struct {
+    uint16_t count;
+    const uint8_t *data;
+    size_t len;
+} strict_list_t;
+
+typedef struct {
+    const uint8_t *data;
+    size_t len;
+    size_t pos;
+} strict_cursor_t;
+
+static inline int strict_skip(strict_cursor_t *c, size_t size) {
+    if (c->len - c->pos < size) {
+        return STRICT_ERR_TRUNCATED;
+    }
+    c->pos += size;
+    return STRICT_OK;
+}
+
+static inline int strict_read_raw(strict_cursor_t *c, uint8_t *out, size_t size) {
+    if (c->len - c->pos < size) {
+        return STRICT_ERR_TRUNCATED;
+    }
+    memcpy(out, c->data + c->pos, size);
+    c->pos += size;
+    return STRICT_OK;
+}
+
+static inline int strict_read_le(strict_cursor_t *c, size_t size, uint64_t *out) {
+    uint64_t value = 0;
+    size_t i;
+    if (c->len - c->pos < size) {
+        return STRICT_ERR_TRUNCATED;
+    }
+    for (i = size; i > 0; i--) {
+        value = (value << 8) | c->data[c->pos + i - 1];
+    }
+    c->pos += size;
+    *out = value;
+    return STRICT_OK;
+}
+
+#define STRICT_READ_INT(name, type, utype, size)                         \
+    static inline int strict_read_##name(strict_cursor_t *c, type *out) { \
+        uint64_t value;                                                   \
+        STRICT_TRY(strict_read_le(c, size, &value));                      \
+        *out = (type)(utype)value;                                        \
+        return STRICT_OK;                                                 \
+    }
+
+STRICT_READ_INT(u8, uint8_t, uint8_t, 1)
+STRICT_READ_INT(u16, uint16_t, uint16_t, 2)
+STRICT_READ_INT(u32, uint32_t, uint32_t, 4)
+STRICT_READ_INT(u64, uint64_t, uint64_t, 8)
+STRICT_READ_INT(i8, int8_t, uint8_t, 1)
+STRICT_READ_INT(i16, int16_t, uint16_t, 2)
+STRICT_READ_INT(i32, int32_t, uint32_t, 4)
+STRICT_READ_INT(i64, int64_t, uint64_t, 8)
+
+static inline int strict_read_f32(strict_cursor_t *c, float *out) {
+    uint32_t bits;
+    STRICT_TRY(strict_read_u32(c, &bits));
+    memcpy(out, &bits, sizeof(bits));
+    return STRICT_OK;
+}
+
+static inline int strict_read_f64(strict_cursor_t *c, double *out) {
+    uint64_t bits;
+    STRICT_TRY(strict_read_u64(c, &bits));
+    memcpy(out, &bits, sizeof(bits));
+    return STRICT_OK;
+}
+
+static inline int strict_read_len(strict_cursor_t *c, uint16_t *out) {
+    return strict_read_u16(c, out);
+}
+
+static inline int strict_read_tag(strict_cursor_t *c, bool *present) {
+    uint8_t tag;
+    STRICT_TRY(strict_read_u8(c, &tag));
+    if (tag > 1) {
+        return STRICT_ERR_TAG;
+    }
+    *present = tag == 1;
+    return STRICT_OK;
+}
+
+static inline int strict_read_chars(strict_cursor_t *c, strict_view_t *out) {
+    uint16_t len;
+    STRICT_TRY(strict_read_len(c, &len));
+    out->data = c->data + c->pos;
+    out->len = len;
+    return strict_skip(c, len);
+}
+
+static inline int strict_read_ascii(strict_cursor_t *c, strict_view_t *out) {
+    size_t i;
+    STRICT_TRY(strict_read_chars(c, out));
+    for (i = 0; i < out->len; i++) {
+        if (out->data[i] >= 0x80) {
+            return STRICT_ERR_ASCII;
+        }
+    }
+    return STRICT_OK;
+}
+
+static inline int strict_read_unicode(strict_cursor_t *c, strict_view_t *out) {
+    const uint8_t *s;
+    size_t i = 0, n, k;
+    uint32_t cp;
+    STRICT_TRY(strict_read_chars(c, out));
+    s = out->data;
+    while (i < out->len) {
+        if (s[i] < 0x80) {
+            i++;
+            continue;
+        } else if ((s[i] & 0xE0) == 0xC0) {
+            n = 1;
+            cp = s[i] & 0x1F;
+        } else if ((s[i] & 0xF0) == 0xE0) {
+            n = 2;
+            cp = s[i] & 0x0F;
+        } else if ((s[i] & 0xF8) == 0xF0) {
+            n = 3;
+            cp = s[i] & 0x07;
+        } else {
+            return STRICT_ERR_UTF8;
+        }
+        if (out->len - i <= n) {
+            return STRICT_ERR_UTF8;
+        }
+        for (k = 1; k <= n; k++) {
+            if ((s[i + k] & 0xC0) != 0x80) {
+                return STRICT_ERR_UTF8;
+            }
+            cp = (cp << 6) | (s[i + k] & 0x3F);
+        }
+        if ((n == 1 && cp < 0x80) || (n == 2 && cp < 0x800) || (n == 3 && cp < 0x10000) ||
+            cp > 0x10FFFF || (cp >= 0xD800 && cp <= 0xDFFF)) {
+            return STRICT_ERR_UTF8;
+        }
+        i += n + 1;
+    }
+    return STRICT_OK;
+}
+
+/* Checks that encoding of a set item or map key follows the previous one */
+static inline int strict_check_order(const uint8_t *prev, size_t prev_len, const uint8_t *item,
+                                     size_t item_len) {
+    int cmp;
+    if (prev == NULL) {
+        return STRICT_OK;
+    }
+    cmp = memcmp(prev, item, prev_len < item_len ? prev_len : item_len);
+    if (cmp < 0 || (cmp == 0 && prev_len < item_len)) {
+        return STRICT_OK;
+    }
+    return STRICT_ERR_ORDER;
+}
+
+#endif /* STRICT_RUNTIME_H */
+
+/* U8* */
+typedef struct {
+    strict_list_t f0;
+} Bytes;
+
+/* U8*32 */
+#pragma pack(push, 1)
+typedef struct {
+    uint8_t f0[32];
+} Txid;
+#pragma pack(pop)
+#define Txid_SIZE 32
+typedef char Txid_size_check[sizeof(Txid) == Txid_SIZE ? 1 : -1];
+
+/* Txid, U16 */
+#pragma pack(push, 1)
+typedef struct {
+    Txid f0;
+    uint16_t f1;
+} OutPoint;
+#pragma pack(pop)
+#define OutPoint_SIZE 34
+typedef char OutPoint_size_check[sizeof(OutPoint) == OutPoint_SIZE ? 1 : -1];
+
+/* Bytes* */
+typedef struct {
+    strict_list_t f0;
+} Witness;
+
+/* OutPoint, Bytes, Witness */
+typedef struct {
+    OutPoint f0;
+    Bytes f1;
+    Witness f2;
+} Input;
+
+/* AsciiChar*, {UnicodeChar[]} -> UnicodeString */
+typedef struct {
+    strict_list_t f0;
+    strict_list_t f1;
+} Meta;
+
+/* U64, Bytes */
+typedef struct {
+    uint64_t f0;
+    Bytes f1;
+} Output;
+
+/* U32, Input*, Output*, U32 */
+typedef struct {
+    uint32_t f0;
+    strict_list_t f1;
+    strict_list_t f2;
+    uint32_t f3;
+} Transaction;
+
+/* UnicodeChar* */
+typedef struct {
+    strict_list_t f0;
+} UnicodeString;
+
+static inline int read_Bytes(strict_cursor_t *c, Bytes *out);
+static inline int read_Input(strict_cursor_t *c, Input *out);
+static inline int read_Meta(strict_cursor_t *c, Meta *out);
+static inline int read_OutPoint(strict_cursor_t *c, OutPoint *out);
+static inline int read_Output(strict_cursor_t *c, Output *out);
+static inline int read_Transaction(strict_cursor_t *c, Transaction *out);
+static inline int read_Txid(strict_cursor_t *c, Txid *out);
+static inline int read_UnicodeString(strict_cursor_t *c, UnicodeString *out);
+static inline int read_Witness(strict_cursor_t *c, Witness *out);
+
+static inline int read_Bytes(strict_cursor_t *c, Bytes *out) {
+    memset(out, 0, sizeof(*out));
+    {
+        size_t start;
+        STRICT_TRY(strict_read_len(c, &out->f0.count));
+        start = c->pos;
+        for (uint16_t i = 0; i < out->f0.count; i++) {
+            STRICT_TRY(strict_skip(c, 1));
+        }
+        out->f0.data = c->data + start;
+        out->f0.len = c->pos - start;
+    }
+    return STRICT_OK;
+}
+
+/* Decodes `Bytes` from `len` bytes of `data`, which must be consumed completely */
+static inline int decode_Bytes(const uint8_t *data, size_t len, Bytes *out) {
+    strict_cursor_t c = {data, len, 0};
+    STRICT_TRY(read_Bytes(&c, out));
+    return c.pos == len ? STRICT_OK : STRICT_ERR_TRAILING;
+}
+
+static inline int read_Input(strict_cursor_t *c, Input *out) {
+    memset(out, 0, sizeof(*out));
+    STRICT_TRY(read_OutPoint(c, &out->f0));
+    STRICT_TRY(read_Bytes(c, &out->f1));
+    STRICT_TRY(read_Witness(c, &out->f2));
+    return STRICT_OK;
+}
+
+/* Decodes `Input` from `len` bytes of `data`, which must be consumed completely */
+static inline int decode_Input(const uint8_t *data, size_t len, Input *out) {
+    strict_cursor_t c = {data, len, 0};
+    STRICT_TRY(read_Input(&c, out));
+    return c.pos == len ? STRICT_OK : STRICT_ERR_TRAILING;
+}
+
+static inline int read_Meta(strict_cursor_t *c, Meta *out) {
+    memset(out, 0, sizeof(*out));
+    {
+        size_t start;
+        STRICT_TRY(strict_read_len(c, &out->f0.count));
+        start = c->pos;
+        for (uint16_t i = 0; i < out->f0.count; i++) {
+            strict_view_t tmp;
+            STRICT_TRY(strict_read_ascii(c, &tmp));
+        }
+        out->f0.data = c->data + start;
+        out->f0.len = c->pos - start;
+    }
+    {
+        size_t start;
+        const uint8_t *prev = NULL;
+        size_t prev_len = 0;
+        STRICT_TRY(strict_read_len(c, &out->f1.count));
+        start = c->pos;
+        for (uint16_t i = 0; i < out->f1.count; i++) {
+            size_t item = c->pos;
+            {
+                uint16_t len;
+                STRICT_TRY(strict_read_len(c, &len));
+                for (uint16_t j = 0; j < len; j++) {
+                    strict_view_t key;
+                    STRICT_TRY(strict_read_unicode(c, &key));
+                }
+            }
+            STRICT_TRY(strict_check_order(prev, prev_len, c->data + item, c->pos - item));
+            prev = c->data + item;
+            prev_len = c->pos - item;
+            UnicodeString value;
+            STRICT_TRY(read_UnicodeString(c, &value));
+        }
+        out->f1.data = c->data + start;
+        out->f1.len = c->pos - start;
+    }
+    return STRICT_OK;
+}
+
+/* Decodes `Meta` from `len` bytes of `data`, which must be consumed completely */
+static inline int decode_Meta(const uint8_t *data, size_t len, Meta *out) {
+    strict_cursor_t c = {data, len, 0};
+    STRICT_TRY(read_Meta(&c, out));
+    return c.pos == len ? STRICT_OK : STRICT_ERR_TRAILING;
+}
+
+static inline int read_OutPoint(strict_cursor_t *c, OutPoint *out) {
+    memset(out, 0, sizeof(*out));
+    STRICT_TRY(read_Txid(c, &out->f0));
+    STRICT_TRY(strict_read_u16(c, &out->f1));
+    return STRICT_OK;
+}
+
+/* Decodes `OutPoint` from `len` bytes of `data`, which must be consumed completely */
+static inline int decode_OutPoint(const uint8_t *data, size_t len, OutPoint *out) {
+    strict_cursor_t c = {data, len, 0};
+    STRICT_TRY(read_OutPoint(&c, out));
+    return c.pos == len ? STRICT_OK : STRICT_ERR_TRAILING;
+}
+
+static inline int read_Output(strict_cursor_t *c, Output *out) {
+    memset(out, 0, sizeof(*out));
+    STRICT_TRY(strict_read_u64(c, &out->f0));
+    STRICT_TRY(read_Bytes(c, &out->f1));
+    return STRICT_OK;
+}
+
+/* Decodes `Output` from `len` bytes of `data`, which must be consumed completely */
+static inline int decode_Output(const uint8_t *data, size_t len, Output *out) {
+    strict_cursor_t c = {data, len, 0};
+    STRICT_TRY(read_Output(&c, out));
+    return c.pos == len ? STRICT_OK : STRICT_ERR_TRAILING;
+}
+
+static inline int read_Transaction(strict_cursor_t *c, Transaction *out) {
+    memset(out, 0, sizeof(*out));
+    STRICT_TRY(strict_read_u32(c, &out->f0));
+    {
+        size_t start;
+        STRICT_TRY(strict_read_len(c, &out->f1.count));
+        start = c->pos;
+        for (uint16_t i = 0; i < out->f1.count; i++) {
+            Input tmp;
+            STRICT_TRY(read_Input(c, &tmp));
+        }
+        out->f1.data = c->data + start;
+        out->f1.len = c->pos - start;
+    }
+    {
+        size_t start;
+        STRICT_TRY(strict_read_len(c, &out->f2.count));
+        start = c->pos;
+        for (uint16_t i = 0; i < out->f2.count; i++) {
+            Output tmp;
+            STRICT_TRY(read_Output(c, &tmp));
+        }
+        out->f2.data = c->data + start;
+        out->f2.len = c->pos - start;
+    }
+    STRICT_TRY(strict_read_u32(c, &out->f3));
+    return STRICT_OK;
+}
+
+/* Decodes `Transaction` from `len` bytes of `data`, which must be consumed completely */
+static inline int decode_Transaction(const uint8_t *data, size_t len, Transaction *out) {
+    strict_cursor_t c = {data, len, 0};
+    STRICT_TRY(read_Transaction(&c, out));
+    return c.pos == len ? STRICT_OK : STRICT_ERR_TRAILING;
+}
+
+static inline int read_Txid(strict_cursor_t *c, Txid *out) {
+    memset(out, 0, sizeof(*out));
+    STRICT_TRY(strict_read_raw(c, out->f0, 32));
+    return STRICT_OK;
+}
+
+/* Decodes `Txid` from `len` bytes of `data`, which must be consumed completely */
+static inline int decode_Txid(const uint8_t *data, size_t len, Txid *out) {
+    strict_cursor_t c = {data, len, 0};
+    STRICT_TRY(read_Txid(&c, out));
+    return c.pos == len ? STRICT_OK : STRICT_ERR_TRAILING;
+}
+
+static inline int read_UnicodeString(strict_cursor_t *c, UnicodeString *out) {
+    memset(out, 0, sizeof(*out));
+    {
+        size_t start;
+        STRICT_TRY(strict_read_len(c, &out->f0.count));
+        start = c->pos;
+        for (uint16_t i = 0; i < out->f0.count; i++) {
+            strict_view_t tmp;
+            STRICT_TRY(strict_read_unicode(c, &tmp));
+        }
+        out->f0.data = c->data + start;
+        out->f0.len = c->pos - start;
+    }
+    return STRICT_OK;
+}
+
+/* Decodes `UnicodeString` from `len` bytes of `data`, which must be consumed completely */
+static inline int decode_UnicodeString(const uint8_t *data, size_t len, UnicodeString *out) {
+    strict_cursor_t c = {data, len, 0};
+    STRICT_TRY(read_UnicodeString(&c, out));
+    return c.pos == len ? STRICT_OK : STRICT_ERR_TRAILING;
+}
+
+static inline int read_Witness(strict_cursor_t *c, Witness *out) {
+    memset(out, 0, sizeof(*out));
+    {
+        size_t start;
+        STRICT_TRY(strict_read_len(c, &out->f0.count));
+        start = c->pos;
+        for (uint16_t i = 0; i < out->f0.count; i++) {
+            Bytes tmp;
+            STRICT_TRY(read_Bytes(c, &tmp));
+        }
+        out->f0.data = c->data + start;
+        out->f0.len = c->pos - start;
+    }
+    return STRICT_OK;
+}
+
+/* Decodes `Witness` from `len` bytes of `data`, which must be consumed completely */
+static inline int decode_Witness(const uint8_t *data, size_t len, Witness *out) {
+    strict_cursor_t c = {data, len, 0};
+    STRICT_TRY(read_Witness(&c, out));
+    return c.pos == len ? STRICT_OK : STRICT_ERR_TRAILING;
+}
+
+#endif /* TYPES_H */
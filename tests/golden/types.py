@@ -0,0 +1,478 @@
+# Generated from strict encoding schema; do not edit.
+
+from __future__ import annotations
+
+import struct
+from dataclasses import dataclass
+from typing import Callable, List, Optional, Tuple, TypeVar
+
+T = TypeVar("T")
+K = TypeVar("K")
+V = TypeVar("V")
+
+
+class DecodeError(ValueError):
+    pass
+
+
+class EncodeError(ValueError):
+    pass
+
+
+class StrictReader:
+    def __init__(self, data: bytes) -> None:
+        self.data = bytes(data)
+        self.offset = 0
+
+    def _take(self, size: int) -> bytes:
+        if self.offset + size > len(self.data):
+            raise DecodeError(f"unexpected end of data at offset {self.offset}")
+        chunk = self.data[self.offset:self.offset + size]
+        self.offset += size
+        return chunk
+
+    def uint(self, size: int) -> int:
+        return int.from_bytes(self._take(size), "little")
+
+    def sint(self, size: int) -> int:
+        return int.from_bytes(self._take(size), "little", signed=True)
+
+    def f32(self) -> float:
+        return struct.unpack("<f", self._take(4))[0]
+
+    def f64(self) -> float:
+        return struct.unpack("<d", self._take(8))[0]
+
+    def raw(self, size: int) -> bytes:
+        return self._take(size)
+
+    def bytes(self) -> bytes:
+        return self._take(self.uint(2))
+
+    def ascii(self) -> str:
+        data = self.bytes()
+        for byte in data:
+            if byte >= 0x80:
+                raise DecodeError(f"non-ASCII character {byte:#04x}")
+        return data.decode("ascii")
+
+    def unicode(self) -> str:
+        try:
+            return self.bytes().decode("utf-8")
+        except UnicodeDecodeError:
+            raise DecodeError("invalid UTF-8 string") from None
+
+    def optional(self, item: Callable[[], T]) -> Optional[T]:
+        tag = self.uint(1)
+        if tag > 1:
+            raise DecodeError(f"invalid optional tag {tag}")
+        return item() if tag == 1 else None
+
+    def array(self, size: int, item: Callable[[], T]) -> List[T]:
+        return [item() for _ in range(size)]
+
+    def list(self, item: Callable[[], T]) -> List[T]:
+        return self.array(self.uint(2), item)
+
+    def _encoded(self, item: Callable[[], T]) -> Tuple[T, bytes]:
+        start = self.offset
+        value = item()
+        return value, self.data[start:self.offset]
+
+    def set(self, item: Callable[[], T]) -> List[T]:
+        items = []
+        last = None
+        for _ in range(self.uint(2)):
+            value, data = self._encoded(item)
+            if last is not None and last >= data:
+                raise DecodeError("repeated set item" if last == data else "set items are not sorted")
+            last = data
+            items.append(value)
+        return items
+
+    def map(self, key: Callable[[], K], value: Callable[[], V]) -> List[Tuple[K, V]]:
+        entries = []
+        last = None
+        for _ in range(self.uint(2)):
+            k, data = self._encoded(key)
+            if last is not None and last >= data:
+                raise DecodeError("repeated map key" if last == data else "map keys are not sorted")
+            last = data
+            entries.append((k, value()))
+        return entries
+
+    def finish(self) -> None:
+        if self.offset < len(self.data):
+            raise DecodeError(f"{len(self.data) - self.offset} bytes of data are not consumed")
+
+
+class StrictWriter:
+    def __init__(self) -> None:
+        self.data = bytearray()
+
+    def uint(self, value: int, size: int) -> None:
+        if not 0 <= value < 1 << (8 * size):
+            raise EncodeError(f"value {value} does not fit into {size} bytes")
+        self.data += value.to_bytes(size, "little")
+
+    def sint(self, value: int, size: int) -> None:
+        half = 1 << (8 * size - 1)
+        if not -half <= value < half:
+            raise EncodeError(f"value {value} does not fit into {size} bytes")
+        self.data += value.to_bytes(size, "little", signed=True)
+
+    def f32(self, value: float) -> None:
+        self.data += struct.pack("<f", value)
+
+    def f64(self, value: float) -> None:
+        self.data += struct.pack("<d", value)
+
+    def raw(self, value: bytes, size: int) -> None:
+        if len(value) != size:
+            raise EncodeError(f"expected {size} bytes, got {len(value)}")
+        self.data += value
+
+    def bytes(self, value: bytes) -> None:
+        self._len(len(value))
+        self.data += value
+
+    def ascii(self, value: str) -> None:
+        if not value.isascii():
+            raise EncodeError(f"string {value!r} contains non-ASCII characters")
+        self.bytes(value.encode("ascii"))
+
+    def unicode(self, value: str) -> None:
+        self.bytes(value.encode("utf-8"))
+
+    def optional(self, value: Optional[T], item: Callable[[T], None]) -> None:
+        if value is None:
+            self.uint(0, 1)
+        else:
+            self.uint(1, 1)
+            item(value)
+
+    def array(self, items: List[T], size: int, item: Callable[[T], None]) -> None:
+        if len(items) != size:
+            raise EncodeError(f"expected {size} items, got {len(items)}")
+        for value in items:
+            item(value)
+
+    def list(self, items: List[T], item: Callable[[T], None]) -> None:
+        self._len(len(items))
+        for value in items:
+            item(value)
+
+    def set(self, items: List[T], item: Callable[[T], None]) -> None:
+        encoded = sorted(self._capture(lambda: item(value)) for value in items)
+        if any(a == b for a, b in zip(encoded, encoded[1:])):
+            raise EncodeError("repeated set item")
+        self._len(len(encoded))
+        for data in encoded:
+            self.data += data
+
+    def map(
+        self,
+        entries: List[Tuple[K, V]],
+        key: Callable[[K], None],
+        value: Callable[[V], None],
+    ) -> None:
+        encoded = sorted(
+            (self._capture(lambda: key(k)), self._capture(lambda: value(v))) for k, v in entries
+        )
+        if any(a[0] == b[0] for a, b in zip(encoded, encoded[1:])):
+            raise EncodeError("repeated map key")
+        self._len(len(encoded))
+        for k, v in encoded:
+            self.data += k + v
+
+    def finish(self) -> bytes:
+        return bytes(self.data)
+
+    def _len(self, size: int) -> None:
+        if size > 0xFFFF:
+            raise EncodeError(f"{size} items exceed 16-bit length limit")
+        self.uint(size, 2)
+
+    def _capture(self, write: Callable[[], None]) -> bytes:
+        data = self.data
+        self.data = bytearray()
+        write()
+        captured = bytes(self.data)
+        self.data = data
+        return captured
+
+
+@dataclass
+class Bytes:
+    """U8*"""
+
+    f0: bytes
+
+    @classmethod
+    def read(cls, r: StrictReader) -> Bytes:
+        return cls(
+            r.bytes(),
+        )
+
+    def write(self, w: StrictWriter) -> None:
+        w.bytes(self.f0)
+
+    @classmethod
+    def decode(cls, data: bytes) -> Bytes:
+        reader = StrictReader(data)
+        value = cls.read(reader)
+        reader.finish()
+        return value
+
+    def encode(self) -> bytes:
+        writer = StrictWriter()
+        self.write(writer)
+        return writer.finish()
+
+
+@dataclass
+class Input:
+    """OutPoint, Bytes, Witness"""
+
+    f0: OutPoint
+    f1: Bytes
+    f2: Witness
+
+    @classmethod
+    def read(cls, r: StrictReader) -> Input:
+        return cls(
+            OutPoint.read(r),
+            Bytes.read(r),
+            Witness.read(r),
+        )
+
+    def write(self, w: StrictWriter) -> None:
+        self.f0.write(w)
+        self.f1.write(w)
+        self.f2.write(w)
+
+    @classmethod
+    def decode(cls, data: bytes) -> Input:
+        reader = StrictReader(data)
+        value = cls.read(reader)
+        reader.finish()
+        return value
+
+    def encode(self) -> bytes:
+        writer = StrictWriter()
+        self.write(writer)
+        return writer.finish()
+
+
+@dataclass
+class Meta:
+    """AsciiChar*, {UnicodeChar[]} -> UnicodeString"""
+
+    f0: List[str]
+    f1: List[Tuple[List[str], UnicodeString]]
+
+    @classmethod
+    def read(cls, r: StrictReader) -> Meta:
+        return cls(
+            r.list(lambda: r.ascii()),
+            r.map(lambda: r.list(lambda: r.unicode()), lambda: UnicodeString.read(r)),
+        )
+
+    def write(self, w: StrictWriter) -> None:
+        w.list(self.f0, lambda v0: w.ascii(v0))
+        w.map(self.f1, lambda k0: w.list(k0, lambda v1: w.unicode(v1)), lambda v0: v0.write(w))
+
+    @classmethod
+    def decode(cls, data: bytes) -> Meta:
+        reader = StrictReader(data)
+        value = cls.read(reader)
+        reader.finish()
+        return value
+
+    def encode(self) -> bytes:
+        writer = StrictWriter()
+        self.write(writer)
+        return writer.finish()
+
+
+@dataclass
+class OutPoint:
+    """Txid, U16"""
+
+    f0: Txid
+    f1: int
+
+    @classmethod
+    def read(cls, r: StrictReader) -> OutPoint:
+        return cls(
+            Txid.read(r),
+            r.uint(2),
+        )
+
+    def write(self, w: StrictWriter) -> None:
+        self.f0.write(w)
+        w.uint(self.f1, 2)
+
+    @classmethod
+    def decode(cls, data: bytes) -> OutPoint:
+        reader = StrictReader(data)
+        value = cls.read(reader)
+        reader.finish()
+        return value
+
+    def encode(self) -> bytes:
+        writer = StrictWriter()
+        self.write(writer)
+        return writer.finish()
+
+
+@dataclass
+class Output:
+    """U64, Bytes"""
+
+    f0: int
+    f1: Bytes
+
+    @classmethod
+    def read(cls, r: StrictReader) -> Output:
+        return cls(
+            r.uint(8),
+            Bytes.read(r),
+        )
+
+    def write(self, w: StrictWriter) -> None:
+        w.uint(self.f0, 8)
+        self.f1.write(w)
+
+    @classmethod
+    def decode(cls, data: bytes) -> Output:
+        reader = StrictReader(data)
+        value = cls.read(reader)
+        reader.finish()
+        return value
+
+    def encode(self) -> bytes:
+        writer = StrictWriter()
+        self.write(writer)
+        return writer.finish()
+
+
+@dataclass
+class Transaction:
+    """U32, Input*, Output*, U32"""
+
+    f0: int
+    f1: List[Input]
+    f2: List[Output]
+    f3: int
+
+    @classmethod
+    def read(cls, r: StrictReader) -> Transaction:
+        return cls(
+            r.uint(4),
+            r.list(lambda: Input.read(r)),
+            r.list(lambda: Output.read(r)),
+            r.uint(4),
+        )
+
+    def write(self, w: StrictWriter) -> None:
+        w.uint(self.f0, 4)
+        w.list(self.f1, lambda v0: v0.write(w))
+        w.list(self.f2, lambda v0: v0.write(w))
+        w.uint(self.f3, 4)
+
+    @classmethod
+    def decode(cls, data: bytes) -> Transaction:
+        reader = StrictReader(data)
+        value = cls.read(reader)
+        reader.finish()
+        return value
+
+    def encode(self) -> bytes:
+        writer = StrictWriter()
+        self.write(writer)
+        return writer.finish()
+
+
+@dataclass
+class Txid:
+    """U8*32"""
+
+    f0: bytes
+
+    @classmethod
+    def read(cls, r: StrictReader) -> Txid:
+        return cls(
+            r.raw(32),
+        )
+
+    def write(self, w: StrictWriter) -> None:
+        w.raw(self.f0, 32)
+
+    @classmethod
+    def decode(cls, data: bytes) -> Txid:
+        reader = StrictReader(data)
+        value = cls.read(reader)
+        reader.finish()
+        return value
+
+    def encode(self) -> bytes:
+        writer = StrictWriter()
+        self.write(writer)
+        return writer.finish()
+
+
+@dataclass
+class UnicodeString:
+    """UnicodeChar*"""
+
+    f0: List[str]
+
+    @classmethod
+    def read(cls, r: StrictReader) -> UnicodeString:
+        return cls(
+            r.list(lambda: r.unicode()),
+        )
+
+    def write(self, w: StrictWriter) -> None:
+        w.list(self.f0, lambda v0: w.unicode(v0))
+
+    @classmethod
+    def decode(cls, data: bytes) -> UnicodeString:
+        reader = StrictReader(data)
+        value = cls.read(reader)
+        reader.finish()
+        return value
+
+    def encode(self) -> bytes:
+        writer = StrictWriter()
+        self.write(writer)
+        return writer.finish()
+
+
+@dataclass
+class Witness:
+    """Bytes*"""
+
+    f0: List[Bytes]
+
+    @classmethod
+    def read(cls, r: StrictReader) -> Witness:
+        return cls(
+            r.list(lambda: Bytes.read(r)),
+        )
+
+    def write(self, w: StrictWriter) -> None:
+        w.list(self.f0, lambda v0: v0.write(w))
+
+    @classmethod
+    def decode(cls, data: bytes) -> Witness:
+        reader = StrictReader(data)
+        value = cls.read(reader)
+        reader.finish()
+        return value
+
+    def encode(self) -> bytes:
+        writer = StrictWriter()
+        self.write(writer)
+        return writer.finish()
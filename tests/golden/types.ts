@@ -0,0 +1,547 @@
+// Generated from strict encoding schema; do not edit.
+
+export class DecodeError extends Error {}
+
+export class EncodeError extends Error {}
+
+/** Compares byte strings in lexicographic order. */
+export function compare(a: Uint8Array, b: Uint8Array): number {
+  for (let i = 0; i < Math.min(a.length, b.length); i++) {
+    if (a[i] !== b[i]) {
+      return a[i] - b[i];
+    }
+  }
+  return a.length - b.length;
+}
+
+export class StrictReader {
+  private offset = 0;
+
+  constructor(private readonly view: DataView) {}
+
+  private need(len: number): number {
+    const offset = this.offset;
+    if (offset + len > this.view.byteLength) {
+      throw new DecodeError(`unexpected end of data at offset ${offset}`);
+    }
+    this.offset += len;
+    return offset;
+  }
+
+  u8(): number { return this.view.getUint8(this.need(1)); }
+  u16(): number { return this.view.getUint16(this.need(2), true); }
+  u32(): number { return this.view.getUint32(this.need(4), true); }
+  i8(): number { return this.view.getInt8(this.need(1)); }
+  i16(): number { return this.view.getInt16(this.need(2), true); }
+  i32(): number { return this.view.getInt32(this.need(4), true); }
+  f32(): number { return this.view.getFloat32(this.need(4), true); }
+  f64(): number { return this.view.getFloat64(this.need(8), true); }
+
+  uint(size: number): bigint {
+    const offset = this.need(size);
+    let value = 0n;
+    for (let i = size - 1; i >= 0; i--) {
+      value = (value << 8n) | BigInt(this.view.getUint8(offset + i));
+    }
+    return value;
+  }
+
+  int(size: number): bigint {
+    const value = this.uint(size);
+    const bits = BigInt(size * 8);
+    return value >= 1n << (bits - 1n) ? value - (1n << bits) : value;
+  }
+
+  raw(size: number): Uint8Array {
+    const offset = this.need(size);
+    return new Uint8Array(this.view.buffer, this.view.byteOffset + offset, size).slice();
+  }
+
+  ascii(): string {
+    const data = this.raw(this.u16());
+    const code = data.find((byte) => byte >= 0x80);
+    if (code !== undefined) {
+      throw new DecodeError(`non-ASCII character 0x${code.toString(16)}`);
+    }
+    return String.fromCharCode(...data);
+  }
+
+  unicode(): string {
+    const data = this.raw(this.u16());
+    try {
+      return new TextDecoder("utf-8", { fatal: true }).decode(data);
+    } catch {
+      throw new DecodeError("invalid UTF-8 string");
+    }
+  }
+
+  optional<T>(item: () => T): T | null {
+    const tag = this.u8();
+    if (tag > 1) {
+      throw new DecodeError(`invalid optional tag ${tag}`);
+    }
+    return tag === 1 ? item() : null;
+  }
+
+  array<T>(len: number, item: () => T): T[] {
+    const items: T[] = [];
+    for (let i = 0; i < len; i++) {
+      items.push(item());
+    }
+    return items;
+  }
+
+  list<T>(item: () => T): T[] { return this.array(this.u16(), item); }
+
+  /** Reads data item, returning it together with its encoding. */
+  private encoded<T>(item: () => T): [T, Uint8Array] {
+    const start = this.offset;
+    const value = item();
+    const data = new Uint8Array(this.view.buffer, this.view.byteOffset + start, this.offset - start);
+    return [value, data];
+  }
+
+  set<T>(item: () => T): T[] {
+    let last: Uint8Array | null = null;
+    return this.list(() => {
+      const [value, data] = this.encoded(item);
+      if (last !== null && compare(last, data) >= 0) {
+        throw new DecodeError(compare(last, data) === 0 ? "repeated set item" : "set items are not sorted");
+      }
+      last = data;
+      return value;
+    });
+  }
+
+  map<K, V>(key: () => K, value: () => V): Array<[K, V]> {
+    let last: Uint8Array | null = null;
+    return this.list(() => {
+      const [k, data] = this.encoded(key);
+      if (last !== null && compare(last, data) >= 0) {
+        throw new DecodeError(compare(last, data) === 0 ? "repeated map key" : "map keys are not sorted");
+      }
+      last = data;
+      return [k, value()];
+    });
+  }
+
+  finish(): void {
+    if (this.offset < this.view.byteLength) {
+      throw new DecodeError(`${this.view.byteLength - this.offset} bytes of data are not consumed`);
+    }
+  }
+}
+
+export class StrictWriter {
+  private data: number[] = [];
+
+  private number(value: number, size: number, signed: boolean): void {
+    if (!Number.isInteger(value)) {
+      throw new EncodeError(`value ${value} is not an integer`);
+    }
+    if (signed) {
+      this.int(BigInt(value), size);
+    } else {
+      this.uint(BigInt(value), size);
+    }
+  }
+
+  u8(value: number): void { this.number(value, 1, false); }
+  u16(value: number): void { this.number(value, 2, false); }
+  u32(value: number): void { this.number(value, 4, false); }
+  i8(value: number): void { this.number(value, 1, true); }
+  i16(value: number): void { this.number(value, 2, true); }
+  i32(value: number): void { this.number(value, 4, true); }
+
+  f32(value: number): void {
+    const view = new DataView(new ArrayBuffer(4));
+    view.setFloat32(0, value, true);
+    this.bytes(new Uint8Array(view.buffer));
+  }
+
+  f64(value: number): void {
+    const view = new DataView(new ArrayBuffer(8));
+    view.setFloat64(0, value, true);
+    this.bytes(new Uint8Array(view.buffer));
+  }
+
+  uint(value: bigint, size: number): void {
+    if (value < 0n || value >= 1n << BigInt(size * 8)) {
+      throw new EncodeError(`value ${value} does not fit into ${size} bytes`);
+    }
+    for (let i = 0; i < size; i++) {
+      this.data.push(Number(value & 0xffn));
+      value >>= 8n;
+    }
+  }
+
+  int(value: bigint, size: number): void {
+    const bits = BigInt(size * 8);
+    const half = 1n << (bits - 1n);
+    if (value < -half || value >= half) {
+      throw new EncodeError(`value ${value} does not fit into ${size} bytes`);
+    }
+    this.uint(value < 0n ? value + (1n << bits) : value, size);
+  }
+
+  raw(value: Uint8Array, size: number): void {
+    if (value.length !== size) {
+      throw new EncodeError(`expected ${size} bytes, got ${value.length}`);
+    }
+    this.bytes(value);
+  }
+
+  ascii(value: string): void {
+    if (!/^[\x00-\x7f]*$/.test(value)) {
+      throw new EncodeError(`string "${value}" contains non-ASCII characters`);
+    }
+    const data = Uint8Array.from(value, (char) => char.charCodeAt(0));
+    this.len(data.length);
+    this.bytes(data);
+  }
+
+  unicode(value: string): void {
+    const data = new TextEncoder().encode(value);
+    this.len(data.length);
+    this.bytes(data);
+  }
+
+  optional<T>(value: T | null, item: (value: T) => void): void {
+    if (value === null) {
+      this.u8(0);
+    } else {
+      this.u8(1);
+      item(value);
+    }
+  }
+
+  array<T>(items: T[], len: number, item: (value: T) => void): void {
+    if (items.length !== len) {
+      throw new EncodeError(`expected ${len} items, got ${items.length}`);
+    }
+    items.forEach(item);
+  }
+
+  list<T>(items: T[], item: (value: T) => void): void {
+    this.len(items.length);
+    items.forEach(item);
+  }
+
+  set<T>(items: T[], item: (value: T) => void): void {
+    const encoded = items.map((value) => this.capture(() => item(value))).sort(compare);
+    if (encoded.some((data, i) => i > 0 && compare(encoded[i - 1], data) === 0)) {
+      throw new EncodeError("repeated set item");
+    }
+    this.list(encoded, (data) => this.bytes(data));
+  }
+
+  map<K, V>(entries: Array<[K, V]>, key: (key: K) => void, value: (value: V) => void): void {
+    const encoded = entries
+      .map(([k, v]): [Uint8Array, Uint8Array] => [this.capture(() => key(k)), this.capture(() => value(v))])
+      .sort((a, b) => compare(a[0], b[0]));
+    if (encoded.some((entry, i) => i > 0 && compare(encoded[i - 1][0], entry[0]) === 0)) {
+      throw new EncodeError("repeated map key");
+    }
+    this.list(encoded, ([k, v]) => {
+      this.bytes(k);
+      this.bytes(v);
+    });
+  }
+
+  finish(): Uint8Array { return Uint8Array.from(this.data); }
+
+  private len(len: number): void {
+    if (len > 0xffff) {
+      throw new EncodeError(`${len} items exceed 16-bit length limit`);
+    }
+    this.u16(len);
+  }
+
+  private bytes(data: Uint8Array): void { data.forEach((byte) => this.data.push(byte)); }
+
+  /** Returns encoding of the data written by `write`. */
+  private capture(write: () => void): Uint8Array {
+    const data = this.data;
+    this.data = [];
+    write();
+    const captured = Uint8Array.from(this.data);
+    this.data = data;
+    return captured;
+  }
+}
+
+/** U8* */
+export interface Bytes {
+  f0: number[];
+}
+
+export function readBytes(r: StrictReader): Bytes {
+  return {
+    f0: r.list(() => r.u8()),
+  };
+}
+
+export function writeBytes(w: StrictWriter, value: Bytes): void {
+  w.list(value.f0, (v0) => w.u8(v0));
+}
+
+export function decodeBytes(view: DataView): Bytes {
+  const reader = new StrictReader(view);
+  const value = readBytes(reader);
+  reader.finish();
+  return value;
+}
+
+export function encodeBytes(value: Bytes): Uint8Array {
+  const writer = new StrictWriter();
+  writeBytes(writer, value);
+  return writer.finish();
+}
+
+/** OutPoint, Bytes, Witness */
+export interface Input {
+  f0: OutPoint;
+  f1: Bytes;
+  f2: Witness;
+}
+
+export function readInput(r: StrictReader): Input {
+  return {
+    f0: readOutPoint(r),
+    f1: readBytes(r),
+    f2: readWitness(r),
+  };
+}
+
+export function writeInput(w: StrictWriter, value: Input): void {
+  writeOutPoint(w, value.f0);
+  writeBytes(w, value.f1);
+  writeWitness(w, value.f2);
+}
+
+export function decodeInput(view: DataView): Input {
+  const reader = new StrictReader(view);
+  const value = readInput(reader);
+  reader.finish();
+  return value;
+}
+
+export function encodeInput(value: Input): Uint8Array {
+  const writer = new StrictWriter();
+  writeInput(writer, value);
+  return writer.finish();
+}
+
+/** AsciiChar*, {UnicodeChar[]} -> UnicodeString */
+export interface Meta {
+  f0: string[];
+  f1: Array<[string[], UnicodeString]>;
+}
+
+export function readMeta(r: StrictReader): Meta {
+  return {
+    f0: r.list(() => r.ascii()),
+    f1: r.map(() => r.list(() => r.unicode()), () => readUnicodeString(r)),
+  };
+}
+
+export function writeMeta(w: StrictWriter, value: Meta): void {
+  w.list(value.f0, (v0) => w.ascii(v0));
+  w.map(value.f1, (k0) => w.list(k0, (v1) => w.unicode(v1)), (v0) => writeUnicodeString(w, v0));
+}
+
+export function decodeMeta(view: DataView): Meta {
+  const reader = new StrictReader(view);
+  const value = readMeta(reader);
+  reader.finish();
+  return value;
+}
+
+export function encodeMeta(value: Meta): Uint8Array {
+  const writer = new StrictWriter();
+  writeMeta(writer, value);
+  return writer.finish();
+}
+
+/** Txid, U16 */
+export interface OutPoint {
+  f0: Txid;
+  f1: number;
+}
+
+export function readOutPoint(r: StrictReader): OutPoint {
+  return {
+    f0: readTxid(r),
+    f1: r.u16(),
+  };
+}
+
+export function writeOutPoint(w: StrictWriter, value: OutPoint): void {
+  writeTxid(w, value.f0);
+  w.u16(value.f1);
+}
+
+export function decodeOutPoint(view: DataView): OutPoint {
+  const reader = new StrictReader(view);
+  const value = readOutPoint(reader);
+  reader.finish();
+  return value;
+}
+
+export function encodeOutPoint(value: OutPoint): Uint8Array {
+  const writer = new StrictWriter();
+  writeOutPoint(writer, value);
+  return writer.finish();
+}
+
+/** U64, Bytes */
+export interface Output {
+  f0: bigint;
+  f1: Bytes;
+}
+
+export function readOutput(r: StrictReader): Output {
+  return {
+    f0: r.uint(8),
+    f1: readBytes(r),
+  };
+}
+
+export function writeOutput(w: StrictWriter, value: Output): void {
+  w.uint(value.f0, 8);
+  writeBytes(w, value.f1);
+}
+
+export function decodeOutput(view: DataView): Output {
+  const reader = new StrictReader(view);
+  const value = readOutput(reader);
+  reader.finish();
+  return value;
+}
+
+export function encodeOutput(value: Output): Uint8Array {
+  const writer = new StrictWriter();
+  writeOutput(writer, value);
+  return writer.finish();
+}
+
+/** U32, Input*, Output*, U32 */
+export interface Transaction {
+  f0: number;
+  f1: Input[];
+  f2: Output[];
+  f3: number;
+}
+
+export function readTransaction(r: StrictReader): Transaction {
+  return {
+    f0: r.u32(),
+    f1: r.list(() => readInput(r)),
+    f2: r.list(() => readOutput(r)),
+    f3: r.u32(),
+  };
+}
+
+export function writeTransaction(w: StrictWriter, value: Transaction): void {
+  w.u32(value.f0);
+  w.list(value.f1, (v0) => writeInput(w, v0));
+  w.list(value.f2, (v0) => writeOutput(w, v0));
+  w.u32(value.f3);
+}
+
+export function decodeTransaction(view: DataView): Transaction {
+  const reader = new StrictReader(view);
+  const value = readTransaction(reader);
+  reader.finish();
+  return value;
+}
+
+export function encodeTransaction(value: Transaction): Uint8Array {
+  const writer = new StrictWriter();
+  writeTransaction(writer, value);
+  return writer.finish();
+}
+
+/** U8*32 */
+export interface Txid {
+  f0: number[];
+}
+
+export function readTxid(r: StrictReader): Txid {
+  return {
+    f0: r.array(32, () => r.u8()),
+  };
+}
+
+export function writeTxid(w: StrictWriter, value: Txid): void {
+  w.array(value.f0, 32, (v0) => w.u8(v0));
+}
+
+export function decodeTxid(view: DataView): Txid {
+  const reader = new StrictReader(view);
+  const value = readTxid(reader);
+  reader.finish();
+  return value;
+}
+
+export function encodeTxid(value: Txid): Uint8Array {
+  const writer = new StrictWriter();
+  writeTxid(writer, value);
+  return writer.finish();
+}
+
+/** UnicodeChar* */
+export interface UnicodeString {
+  f0: string[];
+}
+
+export function readUnicodeString(r: StrictReader): UnicodeString {
+  return {
+    f0: r.list(() => r.unicode()),
+  };
+}
+
+export function writeUnicodeString(w: StrictWriter, value: UnicodeString): void {
+  w.list(value.f0, (v0) => w.unicode(v0));
+}
+
+export function decodeUnicodeString(view: DataView): UnicodeString {
+  const reader = new StrictReader(view);
+  const value = readUnicodeString(reader);
+  reader.finish();
+  return value;
+}
+
+export function encodeUnicodeString(value: UnicodeString): Uint8Array {
+  const writer = new StrictWriter();
+  writeUnicodeString(writer, value);
+  return writer.finish();
+}
+
+/** Bytes* */
+export interface Witness {
+  f0: Bytes[];
+}
+
+export function readWitness(r: StrictReader): Witness {
+  return {
+    f0: r.list(() => readBytes(r)),
+  };
+}
+
+export function writeWitness(w: StrictWriter, value: Witness): void {
+  w.list(value.f0, (v0) => writeBytes(w, v0));
+}
+
+export function decodeWitness(view: DataView): Witness {
+  const reader = new StrictReader(view);
+  const value = readWitness(reader);
+  reader.finish();
+  return value;
+}
+
+export function encodeWitness(value: Witness): Uint8Array {
+  const writer = new StrictWriter();
+  writeWitness(writer, value);
+  return writer.finish();
+}
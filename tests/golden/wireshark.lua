@@ -0,0 +1,289 @@
+-- Wireshark dissector for LNP test messages.
+--
+-- Generated from strict encoding schema. Each message is a little-endian
+-- 16-bit message type followed by the strict encoding of the message data.
+
+local proto = Proto("lnp", "LNP test messages")
+
+local messages = {
+  [0x0001] = "Transaction",
+  [0x0020] = "Meta",
+}
+
+local fields = {
+  msg_type = ProtoField.uint16("lnp.msg_type", "Message type", base.HEX, messages),
+  len = ProtoField.uint16("lnp.len", "Length"),
+  tag = ProtoField.uint8("lnp.tag", "Optional tag"),
+  ["bytes.f0"] = ProtoField.uint8("lnp.bytes.f0", "Bytes.0"),
+  ["meta.f0"] = ProtoField.string("lnp.meta.f0", "Meta.0"),
+  ["meta.f1.key"] = ProtoField.string("lnp.meta.f1.key", "Meta.1 key"),
+  ["out_point.f1"] = ProtoField.uint16("lnp.out_point.f1", "OutPoint.1"),
+  ["output.f0"] = ProtoField.uint64("lnp.output.f0", "Output.0"),
+  ["transaction.f0"] = ProtoField.uint32("lnp.transaction.f0", "Transaction.0"),
+  ["transaction.f3"] = ProtoField.uint32("lnp.transaction.f3", "Transaction.3"),
+  ["txid.f0"] = ProtoField.uint8("lnp.txid.f0", "Txid.0"),
+  ["unicode_string.f0"] = ProtoField.string("lnp.unicode_string.f0", "UnicodeString.0"),
+}
+proto.fields = fields
+
+local experts = {
+  truncated = ProtoExpert.new("lnp.truncated", "Data are truncated", expert.group.MALFORMED, expert.severity.ERROR),
+  length = ProtoExpert.new("lnp.length", "Length prefix exceeds the remaining data", expert.group.MALFORMED, expert.severity.ERROR),
+  tag = ProtoExpert.new("lnp.tag", "Invalid optional tag", expert.group.MALFORMED, expert.severity.ERROR),
+  unknown = ProtoExpert.new("lnp.unknown", "Unknown message type", expert.group.UNDECODED, expert.severity.WARN),
+  trailing = ProtoExpert.new("lnp.trailing", "Message data are not consumed", expert.group.MALFORMED, expert.severity.WARN),
+}
+proto.experts = experts
+
+local Malformed = {}
+
+local function need(buf, tree, offset, len)
+  if offset + len > buf:len() then
+    tree:add_proto_expert_info(experts.truncated)
+    error(Malformed)
+  end
+end
+
+local function read_len(buf, tree, offset, min)
+  need(buf, tree, offset, 2)
+  local len = buf(offset, 2):le_uint()
+  local item = tree:add_le(fields.len, buf(offset, 2))
+  if len * min > buf:len() - offset - 2 then
+    item:add_proto_expert_info(experts.length)
+    error(Malformed)
+  end
+  return len, offset + 2
+end
+
+local function read_tag(buf, tree, offset)
+  need(buf, tree, offset, 1)
+  local tag = buf(offset, 1):uint()
+  local item = tree:add(fields.tag, buf(offset, 1))
+  if tag > 1 then
+    item:add_proto_expert_info(experts.tag)
+    error(Malformed)
+  end
+  return tag == 1, offset + 1
+end
+
+local function read_fixed(buf, tree, offset, field, size)
+  need(buf, tree, offset, size)
+  tree:add_le(field, buf(offset, size))
+  return offset + size
+end
+
+local function read_chars(buf, tree, offset, field, encoding)
+  need(buf, tree, offset, 2)
+  local len = buf(offset, 2):le_uint()
+  need(buf, tree, offset + 2, len)
+  tree:add(field, buf(offset, len + 2), buf(offset + 2, len):string(encoding))
+  return offset + 2 + len
+end
+
+local dissect = {}
+
+dissect["Bytes"] = function(buf, tree, offset, label)
+  local start = offset
+  local tree = tree:add(buf(offset, 0), label)
+  -- 0: U8*
+  do
+    local start = offset
+    local tree = tree:add(buf(offset, 0), "0: U8*")
+    local len
+    len, offset = read_len(buf, tree, offset, 1)
+    for i = 1, len do
+      offset = read_fixed(buf, tree, offset, fields["bytes.f0"], 1)
+    end
+    tree:set_len(offset - start)
+  end
+  tree:set_len(offset - start)
+  return offset
+end
+
+dissect["Input"] = function(buf, tree, offset, label)
+  local start = offset
+  local tree = tree:add(buf(offset, 0), label)
+  -- 0: OutPoint
+  offset = dissect["OutPoint"](buf, tree, offset, "0: OutPoint")
+  -- 1: Bytes
+  offset = dissect["Bytes"](buf, tree, offset, "1: Bytes")
+  -- 2: Witness
+  offset = dissect["Witness"](buf, tree, offset, "2: Witness")
+  tree:set_len(offset - start)
+  return offset
+end
+
+dissect["Meta"] = function(buf, tree, offset, label)
+  local start = offset
+  local tree = tree:add(buf(offset, 0), label)
+  -- 0: AsciiChar*
+  do
+    local start = offset
+    local tree = tree:add(buf(offset, 0), "0: AsciiChar*")
+    local len
+    len, offset = read_len(buf, tree, offset, 2)
+    for i = 1, len do
+      offset = read_chars(buf, tree, offset, fields["meta.f0"], ENC_ASCII)
+    end
+    tree:set_len(offset - start)
+  end
+  -- 1: {UnicodeChar[]} -> UnicodeString
+  do
+    local start = offset
+    local tree = tree:add(buf(offset, 0), "1: {UnicodeChar[]} -> UnicodeString")
+    local len
+    len, offset = read_len(buf, tree, offset, 4)
+    for i = 1, len do
+      local start = offset
+      local tree = tree:add(buf(offset, 0), "{" .. (i - 1) .. "}")
+      do
+        local start = offset
+        local tree = tree:add(buf(offset, 0), "key")
+        local len
+        len, offset = read_len(buf, tree, offset, 2)
+        for i = 1, len do
+          offset = read_chars(buf, tree, offset, fields["meta.f1.key"], ENC_UTF_8)
+        end
+        tree:set_len(offset - start)
+      end
+      offset = dissect["UnicodeString"](buf, tree, offset, "value")
+      tree:set_len(offset - start)
+    end
+    tree:set_len(offset - start)
+  end
+  tree:set_len(offset - start)
+  return offset
+end
+
+dissect["OutPoint"] = function(buf, tree, offset, label)
+  local start = offset
+  local tree = tree:add(buf(offset, 0), label)
+  -- 0: Txid
+  offset = dissect["Txid"](buf, tree, offset, "0: Txid")
+  -- 1: U16
+  offset = read_fixed(buf, tree, offset, fields["out_point.f1"], 2)
+  tree:set_len(offset - start)
+  return offset
+end
+
+dissect["Output"] = function(buf, tree, offset, label)
+  local start = offset
+  local tree = tree:add(buf(offset, 0), label)
+  -- 0: U64
+  offset = read_fixed(buf, tree, offset, fields["output.f0"], 8)
+  -- 1: Bytes
+  offset = dissect["Bytes"](buf, tree, offset, "1: Bytes")
+  tree:set_len(offset - start)
+  return offset
+end
+
+dissect["Transaction"] = function(buf, tree, offset, label)
+  local start = offset
+  local tree = tree:add(buf(offset, 0), label)
+  -- 0: U32
+  offset = read_fixed(buf, tree, offset, fields["transaction.f0"], 4)
+  -- 1: Input*
+  do
+    local start = offset
+    local tree = tree:add(buf(offset, 0), "1: Input*")
+    local len
+    len, offset = read_len(buf, tree, offset, 38)
+    for i = 1, len do
+      offset = dissect["Input"](buf, tree, offset, "[" .. (i - 1) .. "]")
+    end
+    tree:set_len(offset - start)
+  end
+  -- 2: Output*
+  do
+    local start = offset
+    local tree = tree:add(buf(offset, 0), "2: Output*")
+    local len
+    len, offset = read_len(buf, tree, offset, 10)
+    for i = 1, len do
+      offset = dissect["Output"](buf, tree, offset, "[" .. (i - 1) .. "]")
+    end
+    tree:set_len(offset - start)
+  end
+  -- 3: U32
+  offset = read_fixed(buf, tree, offset, fields["transaction.f3"], 4)
+  tree:set_len(offset - start)
+  return offset
+end
+
+dissect["Txid"] = function(buf, tree, offset, label)
+  local start = offset
+  local tree = tree:add(buf(offset, 0), label)
+  -- 0: U8*32
+  do
+    local start = offset
+    local tree = tree:add(buf(offset, 0), "0: U8*32")
+    for i = 1, 32 do
+      offset = read_fixed(buf, tree, offset, fields["txid.f0"], 1)
+    end
+    tree:set_len(offset - start)
+  end
+  tree:set_len(offset - start)
+  return offset
+end
+
+dissect["UnicodeString"] = function(buf, tree, offset, label)
+  local start = offset
+  local tree = tree:add(buf(offset, 0), label)
+  -- 0: UnicodeChar*
+  do
+    local start = offset
+    local tree = tree:add(buf(offset, 0), "0: UnicodeChar*")
+    local len
+    len, offset = read_len(buf, tree, offset, 2)
+    for i = 1, len do
+      offset = read_chars(buf, tree, offset, fields["unicode_string.f0"], ENC_UTF_8)
+    end
+    tree:set_len(offset - start)
+  end
+  tree:set_len(offset - start)
+  return offset
+end
+
+dissect["Witness"] = function(buf, tree, offset, label)
+  local start = offset
+  local tree = tree:add(buf(offset, 0), label)
+  -- 0: Bytes*
+  do
+    local start = offset
+    local tree = tree:add(buf(offset, 0), "0: Bytes*")
+    local len
+    len, offset = read_len(buf, tree, offset, 2)
+    for i = 1, len do
+      offset = dissect["Bytes"](buf, tree, offset, "[" .. (i - 1) .. "]")
+    end
+    tree:set_len(offset - start)
+  end
+  tree:set_len(offset - start)
+  return offset
+end
+
+function proto.dissector(buf, pinfo, root)
+  pinfo.cols.protocol = proto.name
+  local tree = root:add(proto, buf())
+  if buf:len() < 2 then
+    tree:add_proto_expert_info(experts.truncated)
+    return
+  end
+  tree:add_le(fields.msg_type, buf(0, 2))
+  local name = messages[buf(0, 2):le_uint()]
+  if name == nil then
+    tree:add_proto_expert_info(experts.unknown)
+    return
+  end
+  pinfo.cols.info = name
+  local ok, offset = pcall(dissect[name], buf, tree, 2, name)
+  if not ok then
+    if offset ~= Malformed then
+      error(offset, 0)
+    end
+  elseif offset < buf:len() then
+    tree:add_proto_expert_info(experts.trailing)
+  end
+end
+
+DissectorTable.get("tcp.port"):add(9735, proto)